@@ -0,0 +1,12 @@
+#![no_main]
+
+use iab_gpp::v1::GPPString;
+use libfuzzer_sys::fuzz_target;
+
+// Parses arbitrary input as a full GPP string, which exercises the base64 reader on the
+// header, then decodes every section it declares.
+fuzz_target!(|s: &str| {
+    if let Ok(gpp) = GPPString::parse_str(s) {
+        let _ = gpp.decode_all_sections();
+    }
+});
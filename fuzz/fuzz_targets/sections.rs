@@ -0,0 +1,18 @@
+#![no_main]
+
+use iab_gpp::sections::SectionId;
+use iab_gpp::v1::GPPString;
+use libfuzzer_sys::fuzz_target;
+use num_traits::FromPrimitive;
+
+// The first byte selects the section type, the rest is decoded as that section's body.
+fuzz_target!(|data: &[u8]| {
+    let Some((&id, body)) = data.split_first() else {
+        return;
+    };
+    let (Some(id), Ok(body)) = (SectionId::from_u8(id), std::str::from_utf8(body)) else {
+        return;
+    };
+
+    let _ = GPPString::from_section_str(id, body).decode_section(id);
+});
@@ -0,0 +1,9 @@
+#![no_main]
+
+use iab_gpp::sections::tcfeuv2::TcfEuV2;
+use libfuzzer_sys::fuzz_target;
+use std::str::FromStr;
+
+fuzz_target!(|s: &str| {
+    let _ = TcfEuV2::from_str(s);
+});
@@ -1,10 +1,12 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 use iab_gpp::sections::tcfeuv2::TcfEuV2;
+use iab_gpp::sections::usnat::UsNat;
 use iab_gpp::v1::GPPString;
 use std::str::FromStr;
 
 const GPP_TCF_EU_USP: &str = "DBACNY~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN";
 const TCF_EU_V2: &str = "CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA";
+const US_NAT: &str = "BVVVVVVVVWA.YA";
 
 fn bench_gpp_parse(c: &mut Criterion) {
     c.bench_function("gpp_parse", |b| {
@@ -24,6 +26,15 @@ fn bench_tcf_eu_v2_decode(c: &mut Criterion) {
     });
 }
 
+fn bench_usnat_decode(c: &mut Criterion) {
+    c.bench_function("usnat_decode", |b| {
+        b.iter(|| {
+            let section = UsNat::from_str(black_box(US_NAT)).unwrap();
+            black_box(section);
+        });
+    });
+}
+
 fn bench_gpp_decode_all_sections(c: &mut Criterion) {
     c.bench_function("gpp_decode_all_sections", |b| {
         b.iter(|| {
@@ -38,6 +49,7 @@ criterion_group!(
     benches,
     bench_gpp_parse,
     bench_tcf_eu_v2_decode,
+    bench_usnat_decode,
     bench_gpp_decode_all_sections
 );
 criterion_main!(benches);
@@ -0,0 +1,166 @@
+//! A cache of decoded GPP strings.
+//!
+//! Ad servers frequently receive the same consent string across many requests from a single
+//! user. [`GPPCache`] memoizes the result of [`GPPString::decode_all_sections`] so that such
+//! strings are only decoded once.
+//!
+//! This module is only available with the `cache` feature.
+//!
+use crate::sections::{Section, SectionDecodeError};
+use crate::v1::{GPPDecodeError, GPPString};
+use lru::LruCache;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+/// The sections decoded from a GPP string, in the order they appear in the string.
+///
+/// This is the result of [`GPPString::decode_all_sections`], shared between all callers
+/// which decoded the same string.
+pub type DecodedSections = Arc<[Result<Section, SectionDecodeError>]>;
+
+/// A thread-safe, least recently used cache of decoded GPP strings.
+///
+/// Entries are keyed on the exact input string. Strings which cannot be parsed are not
+/// cached, so that invalid input cannot evict valid entries.
+///
+/// # Example
+///
+/// ```
+/// use std::num::NonZeroUsize;
+/// use iab_gpp::cache::GPPCache;
+/// use iab_gpp::sections::Section;
+/// use iab_gpp::v1::GPPDecodeError;
+///
+/// fn main() -> Result<(), GPPDecodeError> {
+///     let cache = GPPCache::new(NonZeroUsize::new(1024).unwrap());
+///
+///     let sections = cache.decode("DBABTA~1YNN")?;
+///     assert!(matches!(sections[0], Ok(Section::UspV1(_))));
+///
+///     // the second call is served from the cache
+///     cache.decode("DBABTA~1YNN")?;
+///     assert_eq!(cache.len(), 1);
+///
+///     Ok(())
+/// }
+/// ```
+pub struct GPPCache {
+    entries: Mutex<LruCache<Box<str>, DecodedSections>>,
+}
+
+impl GPPCache {
+    /// Creates a cache holding at most `capacity` decoded strings.
+    pub fn new(capacity: NonZeroUsize) -> Self {
+        Self {
+            entries: Mutex::new(LruCache::new(capacity)),
+        }
+    }
+
+    /// Returns the decoded sections of a GPP string, decoding it on a cache miss.
+    ///
+    /// Decoding happens without holding the cache lock, so concurrent misses on the same
+    /// string may each decode it.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`GPPDecodeError`] if unable to parse the string. Errors which occur when
+    /// decoding individual sections are part of the cached result.
+    pub fn decode(&self, s: &str) -> Result<DecodedSections, GPPDecodeError> {
+        if let Some(sections) = self.lock().get(s) {
+            return Ok(Arc::clone(sections));
+        }
+
+        let sections: DecodedSections = GPPString::parse_str(s)?.decode_all_sections().into();
+        self.lock().put(s.into(), Arc::clone(&sections));
+
+        Ok(sections)
+    }
+
+    /// Returns the number of cached strings.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Returns `true` if the cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    /// Removes all cached strings.
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    fn lock(&self) -> MutexGuard<'_, LruCache<Box<str>, DecodedSections>> {
+        // the cache is never left in an inconsistent state, so a poisoned lock is still usable
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sections::SectionId;
+
+    fn cache(capacity: usize) -> GPPCache {
+        GPPCache::new(NonZeroUsize::new(capacity).unwrap())
+    }
+
+    #[test]
+    fn decode_hit() {
+        let cache = cache(2);
+        let first = cache.decode("DBABTA~1YNN").unwrap();
+        let second = cache.decode("DBABTA~1YNN").unwrap();
+
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn decode_matches_uncached() {
+        let s = "DBACNY~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN";
+        let ids = cache(2)
+            .decode(s)
+            .unwrap()
+            .iter()
+            .map(|s| s.as_ref().unwrap().id())
+            .collect::<Vec<_>>();
+
+        assert_eq!(ids, vec![SectionId::TcfEuV2, SectionId::UspV1]);
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let cache = cache(2);
+        let first = cache.decode("DBABTA~1YNN").unwrap();
+        cache.decode("DBABTA~1NNN").unwrap();
+        cache.decode("DBABTA~1YNN").unwrap();
+        cache.decode("DBABTA~1YYY").unwrap();
+
+        assert_eq!(cache.len(), 2);
+        assert!(Arc::ptr_eq(&first, &cache.decode("DBABTA~1YNN").unwrap()));
+    }
+
+    #[test]
+    fn errors_are_not_cached() {
+        let cache = cache(2);
+
+        assert!(cache.decode("invalid").is_err());
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn clear() {
+        let cache = cache(2);
+        cache.decode("DBABTA~1YNN").unwrap();
+        cache.clear();
+
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<GPPCache>();
+    }
+}
@@ -13,20 +13,50 @@ pub enum DecodeError {
     InvalidByte(usize, u8),
 }
 
+/// The Base64 alphabets a reader accepts.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Alphabet {
+    /// Only the URL-safe alphabet, as required by the GPP specification.
+    #[default]
+    UrlSafe,
+    /// The URL-safe alphabet, as well as the `+` and `/` characters of the standard one.
+    UrlSafeOrStandard,
+}
+
 pub struct Base64SliceReader<'a> {
     input: &'a [u8],
     input_pos: usize,
     acc: u32,
     bits: u8,
+    alphabet: Alphabet,
 }
 
 impl<'a> Base64SliceReader<'a> {
     pub fn new(input: &'a [u8]) -> Self {
+        Self::with_alphabet(input, Alphabet::UrlSafe)
+    }
+
+    pub fn with_alphabet(input: &'a [u8], alphabet: Alphabet) -> Self {
         Self {
             input,
             input_pos: 0,
             acc: 0,
             bits: 0,
+            alphabet,
+        }
+    }
+
+    /// Returns the value of a character of the standard Base64 alphabet which is not part of
+    /// the URL-safe one, if the reader accepts them.
+    #[cold]
+    fn standard_alphabet_value(&self, byte: u8) -> Option<u8> {
+        if self.alphabet != Alphabet::UrlSafeOrStandard {
+            return None;
+        }
+        match byte {
+            b'+' => Some(62),
+            b'/' => Some(63),
+            _ => None,
         }
     }
 }
@@ -39,12 +69,14 @@ impl Read for Base64SliceReader<'_> {
             while self.bits < 8 && self.input_pos < self.input.len() {
                 let byte = self.input[self.input_pos];
                 self.input_pos += 1;
-                let value = base64_value(byte).ok_or_else(|| {
-                    io::Error::new(
-                        io::ErrorKind::InvalidData,
-                        DecodeError::InvalidByte(self.input_pos - 1, byte),
-                    )
-                })? as u32;
+                let value = base64_value(byte)
+                    .or_else(|| self.standard_alphabet_value(byte))
+                    .ok_or_else(|| {
+                        io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            DecodeError::InvalidByte(self.input_pos - 1, byte),
+                        )
+                    })? as u32;
                 self.acc = (self.acc << 6) | value;
                 self.bits += 6;
             }
@@ -71,6 +103,13 @@ impl Read for Base64SliceReader<'_> {
     }
 }
 
+/// The error returned when the input ends before an unaligned read is complete, which matches
+/// the one returned by `read_exact` for aligned reads, so that EOF-tolerant parsers can rely on
+/// its kind.
+fn unexpected_eof() -> io::Error {
+    io::Error::new(io::ErrorKind::UnexpectedEof, "failed to fill whole buffer")
+}
+
 pub struct Base64BitReader<'a> {
     reader: Base64SliceReader<'a>,
     value: u8,
@@ -79,8 +118,12 @@ pub struct Base64BitReader<'a> {
 
 impl<'a> Base64BitReader<'a> {
     pub fn new(input: &'a [u8]) -> Self {
+        Self::with_alphabet(input, Alphabet::UrlSafe)
+    }
+
+    pub fn with_alphabet(input: &'a [u8], alphabet: Alphabet) -> Self {
         Self {
-            reader: Base64SliceReader::new(input),
+            reader: Base64SliceReader::with_alphabet(input, alphabet),
             value: 0,
             bits: 0,
         }
@@ -93,10 +136,7 @@ impl<'a> Base64BitReader<'a> {
         if read == 1 {
             Ok(byte[0])
         } else {
-            Err(io::Error::new(
-                io::ErrorKind::UnexpectedEof,
-                "failed to fill whole buffer",
-            ))
+            Err(unexpected_eof())
         }
     }
 
@@ -299,7 +339,7 @@ const fn make_base64_decode_table() -> [i8; 256] {
 }
 
 #[inline]
-fn base64_value(b: u8) -> Option<u8> {
+pub(super) fn base64_value(b: u8) -> Option<u8> {
     let v = BASE64_DECODE_TABLE[b as usize];
     if v >= 0 {
         Some(v as u8)
@@ -336,6 +376,94 @@ mod tests {
         buf
     }
 
+    // "gA" is 1000 0000 0000, "_w" is 1111 1111 0000
+    #[test_case("gA", 1 => -1 ; "1 bit negative")]
+    #[test_case("AA", 1 => 0 ; "1 bit zero")]
+    #[test_case("gA", 2 => -2 ; "2 bits minimum")]
+    #[test_case("_w", 8 => -1 ; "8 bits minus one")]
+    #[test_case("gA", 8 => -128 ; "8 bits minimum")]
+    #[test_case("f_", 8 => 127 ; "8 bits maximum")]
+    fn read_signed_i8(s: &str, bits: u32) -> i8 {
+        Base64BitReader::new(s.as_bytes())
+            .read_signed_var::<i8>(bits)
+            .unwrap()
+    }
+
+    #[test_case("gAAAAAAAAAA", 64 => i64::MIN ; "64 bits minimum")]
+    #[test_case("f__________", 64 => i64::MAX ; "64 bits maximum")]
+    #[test_case("___________", 64 => -1 ; "64 bits minus one")]
+    #[test_case("gA", 1 => -1 ; "1 bit negative")]
+    fn read_signed_i64(s: &str, bits: u32) -> i64 {
+        Base64BitReader::new(s.as_bytes())
+            .read_signed_var::<i64>(bits)
+            .unwrap()
+    }
+
+    #[test_case(0 => io::ErrorKind::InvalidInput ; "no bits")]
+    #[test_case(9 => io::ErrorKind::InvalidInput ; "wider than type")]
+    fn read_signed_error(bits: u32) -> io::ErrorKind {
+        Base64BitReader::new(b"____")
+            .read_signed_var::<i8>(bits)
+            .unwrap_err()
+            .kind()
+    }
+
+    #[test_case("gA", 0 => 0 ; "no bits")]
+    #[test_case("gA", 1 => 1 ; "1 bit")]
+    #[test_case("___", 16 => u16::MAX ; "full width")]
+    fn read_unsigned_var(s: &str, bits: u32) -> u16 {
+        Base64BitReader::new(s.as_bytes())
+            .read_unsigned_var::<u16>(bits)
+            .unwrap()
+    }
+
+    #[test]
+    fn read_unsigned_var_wider_than_type() {
+        let e = Base64BitReader::new(b"____")
+            .read_unsigned_var::<u16>(17)
+            .unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    // "AQID" decodes to the bytes 1, 2 and 3
+    #[test_case(0, 3 => vec![1, 2, 3] ; "aligned")]
+    #[test_case(4, 2 => vec![0x10, 0x20] ; "unaligned")]
+    fn read_bytes(skip: u32, len: usize) -> Vec<u8> {
+        let mut r = Base64BitReader::new(b"AQID");
+        r.skip(skip).unwrap();
+        let mut buf = vec![0; len];
+        r.read_bytes(&mut buf).unwrap();
+        buf
+    }
+
+    #[test_case(0, 4 ; "aligned")]
+    #[test_case(4, 3 ; "unaligned")]
+    #[test_case(24, 1 ; "aligned at end")]
+    #[test_case(20, 1 ; "unaligned near end")]
+    fn read_bytes_past_end(skip: u32, len: usize) {
+        let mut r = Base64BitReader::new(b"AQID");
+        r.skip(skip).unwrap();
+        let mut buf = vec![0; len];
+        let e = r.read_bytes(&mut buf).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn standard_alphabet() {
+        let read = |s: &str, alphabet| {
+            let mut buf = vec![];
+            Base64SliceReader::with_alphabet(s.as_bytes(), alphabet)
+                .read_to_end(&mut buf)
+                .map(|_| buf)
+        };
+
+        assert!(read("+/+/", Alphabet::UrlSafe).is_err());
+        assert_eq!(
+            read("+/+/", Alphabet::UrlSafeOrStandard).unwrap(),
+            read("-_-_", Alphabet::UrlSafe).unwrap()
+        );
+    }
+
     #[test_case("===" => matches DecodeError::InvalidByte(0, b'=') ; "equal signs")]
     #[test_case("a  " => matches DecodeError::InvalidByte(1, b' ') ; "whitespaces")]
     fn test_base64_reader_error(s: &str) -> DecodeError {
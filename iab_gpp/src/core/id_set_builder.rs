@@ -0,0 +1,95 @@
+use std::collections::BTreeSet;
+
+/// Accumulates ids and inclusive ranges of ids while decoding, and builds the final set once.
+///
+/// Ids are stored as spans, and consecutive ids are merged into the previous span as they
+/// arrive. Encoders write ids in ascending order, in which case building the set is a single
+/// ordered pass. Out of order or overlapping input is still accepted, and sorted on build.
+#[derive(Debug)]
+pub(crate) struct IdSetBuilder {
+    spans: Vec<(u16, u16)>,
+    sorted: bool,
+}
+
+impl IdSetBuilder {
+    /// Creates a builder with room for `entries` spans.
+    pub(crate) fn with_capacity(entries: usize) -> Self {
+        Self {
+            spans: Vec::with_capacity(entries),
+            sorted: true,
+        }
+    }
+
+    /// Adds a single id.
+    pub(crate) fn insert(&mut self, id: u16) {
+        self.insert_range(id, id);
+    }
+
+    /// Adds every id from `start` to `end`, both included. Nothing is added if `end` is lower
+    /// than `start`.
+    pub(crate) fn insert_range(&mut self, start: u16, end: u16) {
+        if end < start {
+            return;
+        }
+        if let Some(last) = self.spans.last_mut() {
+            if last.1.checked_add(1) == Some(start) {
+                last.1 = end;
+                return;
+            }
+            if start <= last.1 {
+                self.sorted = false;
+            }
+        }
+        self.spans.push((start, end));
+    }
+
+    /// Builds the set of all ids added so far.
+    pub(crate) fn build(mut self) -> BTreeSet<u16> {
+        if !self.sorted {
+            self.spans.sort_unstable();
+        }
+        self.spans
+            .into_iter()
+            .flat_map(|(start, end)| start..=end)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merges_consecutive_ids() {
+        let mut b = IdSetBuilder::with_capacity(4);
+        b.insert(1);
+        b.insert(2);
+        b.insert_range(3, 5);
+        b.insert(8);
+
+        assert_eq!(b.spans, [(1, 5), (8, 8)]);
+        assert!(b.sorted);
+        assert_eq!(b.build(), BTreeSet::from([1, 2, 3, 4, 5, 8]));
+    }
+
+    #[test]
+    fn unordered_and_overlapping() {
+        let mut b = IdSetBuilder::with_capacity(0);
+        b.insert_range(10, 12);
+        b.insert(3);
+        b.insert_range(11, 14);
+        b.insert_range(6, 5);
+
+        assert!(!b.sorted);
+        assert_eq!(b.build(), BTreeSet::from([3, 10, 11, 12, 13, 14]));
+    }
+
+    #[test]
+    fn upper_bound() {
+        let mut b = IdSetBuilder::with_capacity(0);
+        b.insert(u16::MAX);
+        b.insert(u16::MAX);
+
+        assert_eq!(b.build(), BTreeSet::from([u16::MAX]));
+    }
+}
@@ -0,0 +1,205 @@
+//! Bit-level layout of decoded fields, used to debug section layouts and to trace the
+//! provenance of decoded values.
+//!
+//! This module is only available with the `bitfield-debug` feature, which the `provenance`
+//! feature enables.
+//!
+use bitstream_io::{
+    BitCount, BitRead, Endianness, Primitive, SignedBitCount, SignedInteger, UnsignedInteger,
+};
+use std::fmt;
+use std::fmt::Write;
+use std::io;
+
+/// A [`BitRead`] wrapper which counts the number of bits successfully read.
+pub(crate) struct BitCounter<R> {
+    inner: R,
+    bits_read: u64,
+}
+
+impl<R: BitRead> BitCounter<R> {
+    pub(crate) fn new(inner: R) -> Self {
+        Self {
+            inner,
+            bits_read: 0,
+        }
+    }
+
+    /// Returns the number of bits read so far.
+    pub(crate) fn bits_read(&self) -> u64 {
+        self.bits_read
+    }
+
+    fn count<T>(&mut self, bits: u32, r: io::Result<T>) -> io::Result<T> {
+        if r.is_ok() {
+            self.bits_read += u64::from(bits);
+        }
+        r
+    }
+}
+
+impl<R: BitRead> BitRead for BitCounter<R> {
+    fn read_bit(&mut self) -> io::Result<bool> {
+        let r = self.inner.read_bit();
+        self.count(1, r)
+    }
+
+    fn read_unsigned_counted<const MAX: u32, U>(&mut self, bits: BitCount<MAX>) -> io::Result<U>
+    where
+        U: UnsignedInteger,
+    {
+        let r = self.inner.read_unsigned_counted(bits);
+        self.count(u32::from(bits), r)
+    }
+
+    fn read_signed_counted<const MAX: u32, S>(
+        &mut self,
+        bits: impl TryInto<SignedBitCount<MAX>>,
+    ) -> io::Result<S>
+    where
+        S: SignedInteger,
+    {
+        let bits: SignedBitCount<MAX> = bits.try_into().map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "signed reads need at least 1 bit for sign",
+            )
+        })?;
+        let r = self.inner.read_signed_counted(bits);
+        self.count(u32::from(bits), r)
+    }
+
+    fn read_to<V>(&mut self) -> io::Result<V>
+    where
+        V: Primitive,
+    {
+        let r = self.inner.read_to::<V>();
+        self.count(size_of::<V>() as u32 * 8, r)
+    }
+
+    fn read_as_to<F, V>(&mut self) -> io::Result<V>
+    where
+        F: Endianness,
+        V: Primitive,
+    {
+        let r = self.inner.read_as_to::<F, V>();
+        self.count(size_of::<V>() as u32 * 8, r)
+    }
+
+    fn skip(&mut self, bits: u32) -> io::Result<()> {
+        let r = self.inner.skip(bits);
+        self.count(bits, r)
+    }
+
+    fn read_bytes(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        let r = self.inner.read_bytes(buf);
+        self.count(buf.len() as u32 * 8, r)
+    }
+
+    fn byte_aligned(&self) -> bool {
+        self.inner.byte_aligned()
+    }
+
+    fn byte_align(&mut self) {
+        self.inner.byte_align();
+        self.bits_read = self.bits_read.next_multiple_of(8);
+    }
+}
+
+/// The position, width and decoded value of a single field.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) struct FieldLayout {
+    pub(crate) name: &'static str,
+    pub(crate) offset: u64,
+    pub(crate) width: u64,
+    pub(crate) value: String,
+}
+
+impl FieldLayout {
+    pub(crate) fn new(name: &'static str, offset: u64, width: u64, value: &dyn fmt::Debug) -> Self {
+        Self {
+            name,
+            offset,
+            width,
+            value: format!("{value:?}"),
+        }
+    }
+}
+
+/// The bits a decoded field was read from, see the `decode_traced` function of section and
+/// segment types.
+///
+/// Offsets are counted in bits from the start of the decoded string, including the bits of
+/// fields which precede the one being traced. Nested types are traced as a single field.
+#[cfg(feature = "provenance")]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub struct FieldProvenance {
+    /// The name of the field in the decoded type.
+    pub name: &'static str,
+    /// The offset of the first bit of the field.
+    pub offset: u64,
+    /// The number of bits of the field.
+    pub width: u64,
+}
+
+#[cfg(feature = "provenance")]
+impl From<&FieldLayout> for FieldProvenance {
+    fn from(field: &FieldLayout) -> Self {
+        Self {
+            name: field.name,
+            offset: field.offset,
+            width: field.width,
+        }
+    }
+}
+
+/// Renders fields as a table of bit offsets, widths, names and values.
+pub(crate) fn render(fields: &[FieldLayout]) -> String {
+    let name_width = fields.iter().map(|f| f.name.len()).max().unwrap_or(0);
+    let mut s = String::new();
+    for f in fields {
+        // writing to a String cannot fail
+        let _ = writeln!(
+            s,
+            "{:>6} {:>5}  {:<name_width$}  {}",
+            f.offset, f.width, f.name, f.value
+        );
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitstream_io::{BigEndian, BitReader};
+
+    #[test]
+    fn bit_counter() {
+        let bytes = [0b1010_1010, 0b1111_0000, 0xFF];
+        let mut r = BitCounter::new(BitReader::endian(&bytes[..], BigEndian));
+
+        assert!(r.read_bit().unwrap());
+        r.read_unsigned::<6, u8>().unwrap();
+        assert_eq!(r.bits_read(), 7);
+
+        r.skip(5).unwrap();
+        assert_eq!(r.bits_read(), 12);
+
+        assert!(r.read_unsigned::<16, u16>().is_err());
+        assert_eq!(r.bits_read(), 12);
+    }
+
+    #[test]
+    fn render_fields() {
+        let fields = vec![
+            FieldLayout::new("version", 0, 6, &2u8),
+            FieldLayout::new("cmp_id", 6, 12, &31u16),
+        ];
+
+        assert_eq!(
+            render(&fields),
+            "     0     6  version  2\n     6    12  cmp_id   31\n"
+        );
+    }
+}
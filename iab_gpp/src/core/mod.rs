@@ -1,19 +1,34 @@
-use crate::core::base64::Base64BitReader;
+//! Low-level readers for the data types used by GPP sections.
+//!
+//! Section types are decoded by the derive macros using the [`DataRead`] trait, which is
+//! implemented for every [`BitRead`] reader. It is exposed so that custom field parsers
+//! (`#[gpp(parse_with = ...)]`) can reuse the exact same decoding logic.
+//!
+use crate::core::base64::{Base64SliceReader, base64_value};
 use crate::core::fibonacci::fibonacci_iterator;
-use bitstream_io::{BitRead, UnsignedInteger};
+use crate::core::id_set_builder::IdSetBuilder;
+use crate::sections::id_set::spans;
+use bitstream_io::{BitRead, BitWrite, UnsignedInteger};
 #[cfg(test)]
 use bitstream_io::{BigEndian, BitReader};
 use num_iter::range_inclusive;
 use num_traits::{CheckedAdd, Num, NumAssignOps, ToPrimitive};
 use std::collections::BTreeSet;
 use std::io;
-#[cfg(test)]
 use std::io::Read;
 use std::iter::repeat_with;
 
 mod base64;
 mod fibonacci;
+mod id_set_builder;
+#[cfg(feature = "bitfield-debug")]
+pub(crate) mod layout;
+
+pub(crate) use base64::{Alphabet, Base64BitReader};
+#[cfg(feature = "provenance")]
+pub use layout::FieldProvenance;
 
+/// An entry of an array of ranges: a key (usually a purpose id), a type and a set of ids.
 #[derive(Debug, Eq, PartialEq)]
 pub struct GenericRange<X, Y> {
     pub key: X,
@@ -21,36 +36,170 @@ pub struct GenericRange<X, Y> {
     pub ids: BTreeSet<u16>,
 }
 
+impl<X, Y> GenericRange<X, Y> {
+    /// Creates an entry from its key, type and ids.
+    pub fn new(key: X, range_type: Y, ids: BTreeSet<u16>) -> Self {
+        Self {
+            key,
+            range_type,
+            ids,
+        }
+    }
+
+    /// Returns the ids of this entry.
+    pub fn ids(&self) -> &BTreeSet<u16> {
+        &self.ids
+    }
+}
+
+impl<X: Copy, Y: Copy> GenericRange<X, Y> {
+    /// Returns the key of this entry.
+    pub fn key(&self) -> X {
+        self.key
+    }
+
+    /// Returns the type of this entry.
+    pub fn range_type(&self) -> Y {
+        self.range_type
+    }
+}
+
 pub type Range = GenericRange<u8, u8>;
 
+/// Readers for the GPP data types, implemented for any [`BitRead`].
 pub trait DataRead {
+    /// Reads a Fibonacci-encoded integer, terminated by two consecutive `1` bits.
     fn read_fibonacci_integer<T>(&mut self) -> io::Result<T>
     where
         T: CheckedAdd + Copy + Num + NumAssignOps;
 
+    /// Reads a string of `chars` 6-bit letters, where 0 is `A`.
     fn read_string(&mut self, chars: usize) -> io::Result<String>;
 
+    /// Reads a 36-bit timestamp in deciseconds and returns it in seconds.
     fn read_datetime_as_unix_timestamp(&mut self) -> io::Result<u64>;
 
+    /// Reads `bits` bits and returns the 1-based positions of the bits which are set.
     fn read_fixed_bitfield(&mut self, bits: usize) -> io::Result<BTreeSet<u16>>;
 
+    /// Reads a 16-bit length followed by a bitfield of that length.
     fn read_variable_bitfield(&mut self) -> io::Result<BTreeSet<u16>>;
 
+    /// Reads a 12-bit count of entries, each being either a single 16-bit id or an inclusive
+    /// range of 16-bit ids.
+    ///
+    /// Both ends of a range are part of it: a range from 5 to 6 yields `[5, 6]`, and a range
+    /// whose start and end are equal yields that single id. A range whose end is lower than
+    /// its start yields no ids.
+    ///
+    /// Ids are 1-based, so id 0 is invalid, but it is returned as is. Sections handle it
+    /// according to [`DecodeOptions::reject_zero_ids`](crate::sections::DecodeOptions::reject_zero_ids).
     fn read_integer_range(&mut self) -> io::Result<Vec<u16>>;
 
+    /// Same as [`read_integer_range`](DataRead::read_integer_range), returning a set.
     fn read_integer_range_set(&mut self) -> io::Result<BTreeSet<u16>>;
 
+    /// Same as [`read_integer_range_set`](DataRead::read_integer_range_set), with ids of `bits`
+    /// bits instead of 16.
+    ///
+    /// TCF sections only use 16-bit ids, but this allows decoding integer ranges whose ids do
+    /// not fit in a `u16`. Since a single group can then span billions of ids, at most `max_ids`
+    /// ids are read, and an [`InvalidData`](io::ErrorKind::InvalidData) error is returned if
+    /// the ranges hold more.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use bitstream_io::{BigEndian, BitReader};
+    /// use iab_gpp::DataRead;
+    /// use std::collections::BTreeSet;
+    ///
+    /// // one range from 70000 to 70002, with 20-bit ids
+    /// let bytes = [0x00, 0x18, 0x88, 0xB8, 0x08, 0x8B, 0x90];
+    /// let mut r = BitReader::endian(&bytes[..], BigEndian);
+    ///
+    /// let ids = r.read_integer_range_set_with_width::<u32>(20, 1000).unwrap();
+    /// assert_eq!(ids, BTreeSet::from([70000, 70001, 70002]));
+    /// ```
+    fn read_integer_range_set_with_width<I>(
+        &mut self,
+        bits: u32,
+        max_ids: usize,
+    ) -> io::Result<BTreeSet<I>>
+    where
+        I: UnsignedInteger + Num + Ord + ToPrimitive;
+
+    /// Reads a 12-bit count of entries, each being either a single Fibonacci-encoded id or a
+    /// group, with ids delta-encoded from the previous entry.
+    ///
+    /// A group is an offset from the previous id followed by a count of additional ids. Both
+    /// ends are inclusive, so an offset of 5 and a count of 1 yields `[5, 6]`.
     fn read_fibonacci_range<T>(&mut self) -> io::Result<Vec<T>>
     where
         T: CheckedAdd + Copy + Num + NumAssignOps + PartialOrd + ToPrimitive;
 
+    /// Reads a 1-bit flag, then either a Fibonacci range (flag set) or a variable bitfield.
     fn read_optimized_range(&mut self) -> io::Result<BTreeSet<u16>>;
 
+    /// Reads a set of ids encoded either as a bitfield or as a list of integer ranges,
+    /// whichever is shorter. This is the encoding used by TCF vendor sections.
+    ///
+    /// The bit format is:
+    ///
+    /// | Bits | Field                                                  |
+    /// |------|--------------------------------------------------------|
+    /// | 16   | maximum id (`n`)                                       |
+    /// | 1    | encoding type: `0` for a bitfield, `1` for ranges      |
+    /// | `n`  | if bitfield: one bit per id, starting at id 1          |
+    ///
+    /// If ranges are used, they are read with
+    /// [`read_integer_range`](DataRead::read_integer_range):
+    ///
+    /// | Bits | Field                                                  |
+    /// |------|--------------------------------------------------------|
+    /// | 12   | number of entries                                      |
+    /// | 1    | for each entry: `0` for a single id, `1` for a range   |
+    /// | 16   | single id, or first id of the range                    |
+    /// | 16   | if range: last id of the range (inclusive)             |
+    ///
+    /// The maximum id is ignored when ranges are used.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use bitstream_io::{BigEndian, BitReader};
+    /// use iab_gpp::DataRead;
+    ///
+    /// // max id 5, bitfield 10101
+    /// let bytes = [0b0000_0000, 0b0000_0101, 0b0101_0100];
+    /// let mut r = BitReader::endian(&bytes[..], BigEndian);
+    /// let ids = r.read_optimized_integer_range().unwrap();
+    ///
+    /// assert_eq!(ids.into_iter().collect::<Vec<_>>(), vec![1, 3, 5]);
+    /// ```
     fn read_optimized_integer_range(&mut self) -> io::Result<BTreeSet<u16>>;
 
-    #[allow(dead_code)]
+    /// Reads the 3-bit type which prefixes an optional segment of a segmented section.
+    ///
+    /// The registered segment types are:
+    ///
+    /// | Section   | Type | Segment            |
+    /// |-----------|------|--------------------|
+    /// | TCF EU v2 | 1    | disclosed vendors  |
+    /// | TCF EU v2 | 2    | allowed vendors    |
+    /// | TCF EU v2 | 3    | publisher purposes |
+    /// | TCF CA v1 | 1    | disclosed vendors  |
+    /// | TCF CA v1 | 3    | publisher purposes |
+    ///
+    /// US state sections use a 2-bit type instead, where 1 is the GPC segment.
+    fn read_segment_type(&mut self) -> io::Result<u8>;
+
+    /// Reads a 12-bit count of [`Range`] entries: 6-bit key, 2-bit type and an optimized
+    /// integer range.
     fn read_array_of_ranges(&mut self) -> io::Result<Vec<Range>>;
 
+    /// Reads a 12-bit count of [`GenericRange`] entries: `x`-bit key, `y`-bit type and an
+    /// optimized range.
     fn read_n_array_of_ranges<X, Y>(
         &mut self,
         x: u32,
@@ -59,6 +208,19 @@ pub trait DataRead {
     where
         X: UnsignedInteger,
         Y: UnsignedInteger;
+
+    /// Same as [`read_n_array_of_ranges`](DataRead::read_n_array_of_ranges), but fails with
+    /// [`io::ErrorKind::InvalidData`] if the count is greater than `max`, before reading any
+    /// entry, so that a corrupt count cannot trigger thousands of range reads.
+    fn read_n_array_of_ranges_bounded<X, Y>(
+        &mut self,
+        x: u32,
+        y: u32,
+        max: usize,
+    ) -> io::Result<Vec<GenericRange<X, Y>>>
+    where
+        X: UnsignedInteger,
+        Y: UnsignedInteger;
 }
 
 impl<T> DataRead for T
@@ -83,7 +245,7 @@ where
 
             let fib_value = fib.next().unwrap_or(N::zero());
             if bit {
-                total += fib_value;
+                total = total.checked_add(&fib_value).ok_or_else(overflow_error)?;
             }
             last_bit = bit;
         }
@@ -104,7 +266,7 @@ where
 
     // todo: use u16 or generic as input type (spec doesn't restrict bitfield size, but output must be u16)
     fn read_fixed_bitfield(&mut self, bits: usize) -> io::Result<BTreeSet<u16>> {
-        let mut result = BTreeSet::new();
+        let mut result = IdSetBuilder::with_capacity(0);
         for i in 1..=bits {
             let b = self.read_bit()?;
             if b {
@@ -112,7 +274,7 @@ where
             }
         }
 
-        Ok(result)
+        Ok(result.build())
     }
 
     fn read_variable_bitfield(&mut self) -> io::Result<BTreeSet<u16>> {
@@ -144,23 +306,58 @@ where
 
     fn read_integer_range_set(&mut self) -> io::Result<BTreeSet<u16>> {
         let n = self.read_unsigned::<12, u16>()?;
-        let mut range = BTreeSet::new();
+        let mut range = IdSetBuilder::with_capacity(n as usize);
 
         for _ in 0..n {
             let is_group = self.read_bit()?;
             if is_group {
                 let start = self.read_unsigned::<16, u16>()?;
                 let end = self.read_unsigned::<16, u16>()?;
-
-                for id in start..=end {
-                    range.insert(id);
-                }
+                range.insert_range(start, end);
             } else {
                 let id = self.read_unsigned::<16, u16>()?;
                 range.insert(id);
             }
         }
 
+        Ok(range.build())
+    }
+
+    fn read_integer_range_set_with_width<I>(
+        &mut self,
+        bits: u32,
+        max_ids: usize,
+    ) -> io::Result<BTreeSet<I>>
+    where
+        I: UnsignedInteger + Num + Ord + ToPrimitive,
+    {
+        let n = self.read_unsigned::<12, u16>()?;
+        let mut range = BTreeSet::new();
+        let mut ids = 0usize;
+
+        for _ in 0..n {
+            let is_group = self.read_bit()?;
+            let start = self.read_unsigned_var::<I>(bits)?;
+            let end = if is_group {
+                self.read_unsigned_var::<I>(bits)?
+            } else {
+                start
+            };
+            if start <= end {
+                ids = (end - start)
+                    .to_usize()
+                    .and_then(|len| ids.checked_add(len)?.checked_add(1))
+                    .unwrap_or(usize::MAX);
+                if ids > max_ids {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("too many ids (maximum {max_ids})"),
+                    ));
+                }
+            }
+            range.extend(range_inclusive(start, end));
+        }
+
         Ok(range)
     }
 
@@ -178,13 +375,16 @@ where
                 let offset = self.read_fibonacci_integer()?;
                 let count = self.read_fibonacci_integer()?;
 
-                for id in range_inclusive(last_id + offset, last_id + offset + count) {
+                let start = last_id.checked_add(&offset).ok_or_else(overflow_error)?;
+                let end = start.checked_add(&count).ok_or_else(overflow_error)?;
+                for id in range_inclusive(start, end) {
                     range.push(id);
                     last_id = id;
                 }
             } else {
-                let id = self.read_fibonacci_integer::<N>()?;
-                range.push(last_id + id);
+                let offset = self.read_fibonacci_integer::<N>()?;
+                let id = last_id.checked_add(&offset).ok_or_else(overflow_error)?;
+                range.push(id);
                 last_id = id;
             }
         }
@@ -211,6 +411,10 @@ where
         }
     }
 
+    fn read_segment_type(&mut self) -> io::Result<u8> {
+        self.read_unsigned::<3, u8>()
+    }
+
     fn read_array_of_ranges(&mut self) -> io::Result<Vec<Range>> {
         let n = self.read_unsigned::<12, u16>()? as usize;
         let mut ranges = Vec::with_capacity(n);
@@ -232,11 +436,25 @@ where
         x: u32,
         y: u32,
     ) -> io::Result<Vec<GenericRange<X, Y>>>
+    where
+        X: UnsignedInteger,
+        Y: UnsignedInteger,
+    {
+        self.read_n_array_of_ranges_bounded(x, y, usize::MAX)
+    }
+
+    fn read_n_array_of_ranges_bounded<X, Y>(
+        &mut self,
+        x: u32,
+        y: u32,
+        max: usize,
+    ) -> io::Result<Vec<GenericRange<X, Y>>>
     where
         X: UnsignedInteger,
         Y: UnsignedInteger,
     {
         let n = self.read_unsigned::<12, u16>()? as usize;
+        check_range_count(n, max)?;
         let mut ranges = Vec::with_capacity(n);
 
         for _ in 0..n {
@@ -252,13 +470,164 @@ where
     }
 }
 
+/// Writes the data types used by GPP sections, as read by [`DataRead`].
+///
+/// Only the id set encodings are provided for now, sections cannot be encoded as a whole. The
+/// optimized integer range encoding picks the shorter of its two representations, so that the
+/// strings produced are as short as those of other encoders.
+///
+/// # Example
+///
+/// ```
+/// use bitstream_io::{BigEndian, BitReader, BitWrite, BitWriter};
+/// use iab_gpp::{DataRead, DataWrite};
+/// use std::collections::BTreeSet;
+///
+/// let ids = BTreeSet::from_iter(1..=500);
+///
+/// let mut w = BitWriter::endian(Vec::new(), BigEndian);
+/// w.write_optimized_integer_range(&ids).unwrap();
+/// w.byte_align().unwrap();
+/// let bytes = w.into_writer();
+///
+/// // 16-bit maximum id, range flag, 12-bit count and a single 33-bit range
+/// assert_eq!(bytes.len(), 8);
+///
+/// let mut r = BitReader::endian(&bytes[..], BigEndian);
+/// assert_eq!(r.read_optimized_integer_range().unwrap(), ids);
+/// ```
+pub trait DataWrite {
+    /// Writes a bitfield of `bits` bits where the first bit is set if id 1 is in the set, the
+    /// counterpart of [`read_fixed_bitfield`](DataRead::read_fixed_bitfield).
+    ///
+    /// Ids which do not fit in `bits` bits are not written.
+    fn write_fixed_bitfield(&mut self, bits: usize, ids: &BTreeSet<u16>) -> io::Result<()>;
+
+    /// Writes a 12-bit count of entries and the entries, the counterpart of
+    /// [`read_integer_range`](DataRead::read_integer_range).
+    ///
+    /// Consecutive ids are written as a single range entry.
+    ///
+    /// Fails with [`io::ErrorKind::InvalidInput`] if there are more than 4095 entries.
+    fn write_integer_range(&mut self, ids: &BTreeSet<u16>) -> io::Result<()>;
+
+    /// Writes ids with the shorter of the bitfield and integer range encodings, the
+    /// counterpart of [`read_optimized_integer_range`](DataRead::read_optimized_integer_range).
+    ///
+    /// The maximum id is written in both cases, and the bitfield is used when both encodings
+    /// have the same length.
+    fn write_optimized_integer_range(&mut self, ids: &BTreeSet<u16>) -> io::Result<()>;
+}
+
+impl<T> DataWrite for T
+where
+    T: BitWrite,
+{
+    fn write_fixed_bitfield(&mut self, bits: usize, ids: &BTreeSet<u16>) -> io::Result<()> {
+        for id in 1..=bits {
+            self.write_bit(u16::try_from(id).is_ok_and(|id| ids.contains(&id)))?;
+        }
+        Ok(())
+    }
+
+    fn write_integer_range(&mut self, ids: &BTreeSet<u16>) -> io::Result<()> {
+        let entries = spans(ids).collect::<Vec<_>>();
+        let n = u16::try_from(entries.len())
+            .ok()
+            .filter(|&n| n < 1 << 12)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("too many ranges ({}, maximum 4095)", entries.len()),
+                )
+            })?;
+
+        self.write_unsigned::<12, u16>(n)?;
+        for (start, end) in entries {
+            self.write_bit(start != end)?;
+            self.write_unsigned::<16, u16>(start)?;
+            if start != end {
+                self.write_unsigned::<16, u16>(end)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_optimized_integer_range(&mut self, ids: &BTreeSet<u16>) -> io::Result<()> {
+        let max_id = ids.last().copied().unwrap_or(0);
+
+        self.write_unsigned::<16, u16>(max_id)?;
+        if prefers_integer_range(ids) {
+            self.write_bit(true)?;
+            self.write_integer_range(ids)
+        } else {
+            self.write_bit(false)?;
+            self.write_fixed_bitfield(max_id as usize, ids)
+        }
+    }
+}
+
+/// Returns the number of bits of `ids` encoded as a list of integer ranges, including the
+/// 12-bit count of entries.
+pub(crate) fn integer_range_bit_len(ids: &BTreeSet<u16>) -> usize {
+    12 + spans(ids)
+        .map(|(start, end)| if start == end { 1 + 16 } else { 1 + 2 * 16 })
+        .sum::<usize>()
+}
+
+/// Returns the number of bits of `ids` encoded by
+/// [`write_optimized_integer_range`](DataWrite::write_optimized_integer_range).
+pub(crate) fn optimized_integer_range_bit_len(ids: &BTreeSet<u16>) -> usize {
+    let bits = if prefers_integer_range(ids) {
+        integer_range_bit_len(ids)
+    } else {
+        ids.last().copied().unwrap_or(0) as usize
+    };
+    16 + 1 + bits
+}
+
+/// Returns whether `ids` are shorter encoded as integer ranges than as a bitfield.
+fn prefers_integer_range(ids: &BTreeSet<u16>) -> bool {
+    integer_range_bit_len(ids) < ids.last().copied().unwrap_or(0) as usize
+}
+
+/// Checks a declared number of ranges against the maximum allowed by the decode options.
+pub(crate) fn check_range_count(n: usize, max: usize) -> io::Result<()> {
+    if n > max {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("too many ranges ({n}, maximum {max})"),
+        ));
+    }
+    Ok(())
+}
+
+fn overflow_error() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "integer overflow")
+}
+
 pub(crate) fn base64_bit_reader(r: &[u8]) -> Base64BitReader<'_> {
     Base64BitReader::new(r)
 }
 
+/// Returns the offset of the first byte which is not part of the URL-safe base64 alphabet, or
+/// of the extra allowed bytes.
+pub(crate) fn find_invalid_base64_byte(r: &[u8], allowed: &[u8]) -> Option<usize> {
+    r.iter()
+        .position(|b| base64_value(*b).is_none() && !allowed.contains(b))
+}
+
+/// Decodes a whole base64 string into bytes, the last byte being padded with zero bits.
+pub(crate) fn base64_decode(r: &[u8]) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(r.len() * 6 / 8 + 1);
+    Base64SliceReader::new(r).read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use bitstream_io::BitWriter;
     use std::io::Cursor;
     use test_case::test_case;
 
@@ -302,6 +671,14 @@ mod tests {
         r(Cursor::new(b(s))).read_fibonacci_integer().unwrap()
     }
 
+    #[test_case("0000000001011" ; "sum overflow for u8")]
+    fn read_fibonacci_overflow(s: &str) {
+        let e = r(Cursor::new(b(s)))
+            .read_fibonacci_integer::<u8>()
+            .unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    }
+
     #[test_case("101010", 1 => "k")]
     #[test_case("101010 101011", 2 => "kl")]
     fn read_string(s: &str, chars: usize) -> String {
@@ -329,16 +706,82 @@ mod tests {
     }
 
     #[test_case("000000000010 0 0000000000000011 1 0000000000000101 0000000000001000" => vec![3, 5, 6, 7, 8] ; "test1")]
+    #[test_case("000000000001 1 0000000000000101 0000000000000110" => vec![5, 6] ; "two id group")]
+    #[test_case("000000000001 1 0000000000000101 0000000000000101" => vec![5] ; "single id group")]
+    #[test_case("000000000001 0 0000000000000101" => vec![5] ; "single id")]
+    #[test_case("000000000001 1 0000000000000110 0000000000000101" => Vec::<u16>::new() ; "reversed group")]
     fn read_integer_range(s: &str) -> Vec<u16> {
         r(Cursor::new(b(s))).read_integer_range().unwrap()
     }
 
+    #[test]
+    fn generic_range_new() {
+        let range = Range::new(2, 1, BTreeSet::from([5, 6]));
+        assert_eq!(range.key(), 2);
+        assert_eq!(range.range_type(), 1);
+        assert_eq!(range.ids(), &BTreeSet::from([5, 6]));
+        assert_eq!(
+            range,
+            GenericRange {
+                key: 2,
+                range_type: 1,
+                ids: BTreeSet::from([5, 6]),
+            }
+        );
+    }
+
+    #[test_case("000000000001 1 00000000000000000000000000000101 00000000000000000000000000000110" => BTreeSet::from_iter([5, 6]) ; "two id group")]
+    #[test_case("000000000001 0 11111111111111111111111111111111" => BTreeSet::from_iter([u32::MAX]) ; "single maximum id")]
+    #[test_case("000000000010 0 00000000000000010000000000000000 1 11111111111111111111111111111110 11111111111111111111111111111111" => BTreeSet::from_iter([65536, u32::MAX - 1, u32::MAX]) ; "ids above u16")]
+    fn read_integer_range_set_with_width(s: &str) -> BTreeSet<u32> {
+        r(Cursor::new(b(s)))
+            .read_integer_range_set_with_width::<u32>(32, 16)
+            .unwrap()
+    }
+
+    #[test_case("000000000001 1 00000000000000000000000000000001 11111111111111111111111111111111" ; "huge group")]
+    #[test_case("000000000010 1 00000000000000000000000000000001 00000000000000000000000000001000 1 00000000000000000000000000010001 00000000000000000000000000011000" ; "over the total")]
+    fn read_integer_range_set_with_width_too_many_ids(s: &str) {
+        let e = r(Cursor::new(b(s)))
+            .read_integer_range_set_with_width::<u32>(32, 15)
+            .unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn read_integer_range_set_with_width_16_bits() {
+        let s = "000000000010 1 0000000000000101 0000000000000110 0 0000000000001000";
+        let narrow = r(Cursor::new(b(s))).read_integer_range_set().unwrap();
+        let wide = r(Cursor::new(b(s)))
+            .read_integer_range_set_with_width::<u16>(16, usize::MAX)
+            .unwrap();
+        assert_eq!(narrow, wide);
+    }
+
+    #[test_case("000000000001 1 0000000000000101 0000000000000110" => BTreeSet::from_iter([5, 6]) ; "two id group")]
+    #[test_case("000000000001 1 0000000000000101 0000000000000101" => BTreeSet::from_iter([5]) ; "single id group")]
+    fn read_integer_range_set(s: &str) -> BTreeSet<u16> {
+        r(Cursor::new(b(s))).read_integer_range_set().unwrap()
+    }
+
     #[test_case("000000000010 0 0011 1 011 0011" => vec![3, 5, 6, 7, 8])]
     #[test_case("000000000010 0 011 0 1011" => vec![2, 6])]
+    #[test_case("000000000001 1 00011 11" => vec![5, 6] ; "two id group")]
+    #[test_case("000000000011 0 011 0 1011 0 11" => vec![2, 6, 7] ; "consecutive single ids")]
+    #[test_case("000000000011 0 011 1 11 11 0 11" => vec![2, 3, 4, 5] ; "single after group")]
     fn read_fibonacci_range(s: &str) -> Vec<u8> {
         r(Cursor::new(b(s))).read_fibonacci_range().unwrap()
     }
 
+    #[test_case("000000000001 1 0000000000011 00000000011" ; "group end")]
+    #[test_case("000000000010 1 0000000000011 00000000011 0 0000000000011" ; "single after group")]
+    fn read_fibonacci_range_overflow(s: &str) {
+        let e = r(Cursor::new(b(s)))
+            .read_fibonacci_range::<u8>()
+            .unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    }
+
     #[test_case("1 000000000010 0 0011 1 011 0011" => BTreeSet::from_iter([3, 5, 6, 7, 8]))]
     #[test_case("0 0000000000000101 10101" => BTreeSet::from_iter([1, 3, 5]))]
     fn read_optimized_range(s: &str) -> BTreeSet<u16> {
@@ -351,6 +794,74 @@ mod tests {
         r(Cursor::new(b(s))).read_optimized_integer_range().unwrap()
     }
 
+    /// Writes with `f`, and returns the written bits as literal binary digits, padded with
+    /// zeroes to whole bytes.
+    fn written(f: impl FnOnce(&mut BitWriter<Vec<u8>, BigEndian>) -> io::Result<()>) -> String {
+        let mut w = BitWriter::endian(Vec::new(), BigEndian);
+        f(&mut w).unwrap();
+        w.byte_align().unwrap();
+        w.into_writer().iter().map(|b| format!("{b:08b}")).collect()
+    }
+
+    #[test_case(&[], "0000000000000000 0" ; "empty")]
+    #[test_case(&[1, 3, 5], "0000000000000101 0 10101" ; "bitfield")]
+    #[test_case(&[3, 5, 6, 7, 8], "0000000000001000 0 00101111" ; "short bitfield over ranges")]
+    #[test_case(&[1, 100], "0000000001100100 1 000000000010 0 0000000000000001 0 0000000001100100" ; "ranges")]
+    #[test_case(&[1, 2, 3, 100], "0000000001100100 1 000000000010 1 0000000000000001 0000000000000011 0 0000000001100100" ; "group")]
+    #[test_case(&[29], "0000000000011101 0 00000000000000000000000000001" ; "same length")]
+    fn write_optimized_integer_range(ids: &[u16], expected: &str) {
+        let ids = BTreeSet::from_iter(ids.iter().copied());
+        let bits = written(|w| w.write_optimized_integer_range(&ids));
+
+        let expected = expected.replace(' ', "");
+        let width = expected.len().next_multiple_of(8);
+        assert_eq!(bits, format!("{expected:0<width$}"));
+        assert_eq!(
+            r(Cursor::new(b(&bits)))
+                .read_optimized_integer_range()
+                .unwrap(),
+            ids
+        );
+    }
+
+    #[test]
+    fn write_integer_range_too_many_entries() {
+        let ids = BTreeSet::from_iter((1..=8192).step_by(2));
+        let mut w = BitWriter::endian(Vec::new(), BigEndian);
+
+        let e = w.write_integer_range(&ids).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn range_and_bitfield_encodings_compare_equal() {
+        use std::hash::{BuildHasher, RandomState};
+
+        // {3, 5, 6, 7, 8} encoded once as a range and once as a bitfield
+        let from_range = r(Cursor::new(b(
+            "0000000000000000 1 000000000010 0 0000000000000011 1 0000000000000101 0000000000001000",
+        )))
+        .read_optimized_integer_range()
+        .unwrap();
+        let from_bitfield = r(Cursor::new(b("0000000000001000 0 00101111")))
+            .read_optimized_integer_range()
+            .unwrap();
+        let mut inserted = BTreeSet::new();
+        for id in [8, 3, 7, 5, 6] {
+            inserted.insert(id);
+        }
+
+        assert_eq!(from_range, from_bitfield);
+        assert_eq!(from_range, inserted);
+
+        let hasher = RandomState::new();
+        assert_eq!(
+            hasher.hash_one(&from_range),
+            hasher.hash_one(&from_bitfield)
+        );
+        assert_eq!(hasher.hash_one(&from_range), hasher.hash_one(&inserted));
+    }
+
     #[test_case("000000000000" => Vec::<Range>::new() ; "empty")]
     #[test_case("000000000001 000011 01 0000000000000101 0 10101" => vec![
         Range {
@@ -375,6 +886,12 @@ mod tests {
         r(Cursor::new(b(s))).read_array_of_ranges().unwrap()
     }
 
+    #[test_case("001" => 1 ; "disclosed vendors")]
+    #[test_case("011 1" => 3 ; "publisher purposes")]
+    fn read_segment_type(s: &str) -> u8 {
+        r(Cursor::new(b(s))).read_segment_type().unwrap()
+    }
+
     #[test_case("000000000000" => Vec::<GenericRange<u8, u8>>::new() ; "empty")]
     #[test_case("000000000001 000011 01 0 0000000000000101 10101" => vec![
         Range {
@@ -400,4 +917,13 @@ mod tests {
             .read_n_array_of_ranges::<u8, u8>(6, 2)
             .unwrap()
     }
+
+    #[test_case("111111111111 000011 01 0000000000000101 0" ; "maximum declared count")]
+    #[test_case("000000000011 000011 01 0000000000000101 0 10101" ; "just above maximum")]
+    fn read_n_array_of_ranges_too_many(s: &str) {
+        let e = r(Cursor::new(b(s)))
+            .read_n_array_of_ranges_bounded::<u8, u8>(6, 2, 2)
+            .unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    }
 }
@@ -0,0 +1,214 @@
+//! A C interface to the TCF EU v2 decoder.
+//!
+//! [`iab_gpp_decode_tcf_eu_v2`] decodes the TCF EU v2 core segment of a GPP string into a
+//! [`IabGppTcfEuV2Core`], a flat `#[repr(C)]` struct which C, Go or Swift callers can allocate
+//! themselves. Headers can be generated with `cbindgen`.
+//!
+//! Id sets are stored as fixed-size bitmasks sized to the maxima allowed by the specification.
+//! Vendor bitmasks are laid out like [`IdSetExt::to_bitmask`] and TCF bitfields: id `n` is set
+//! when bit `7 - (n - 1) % 8` of byte `(n - 1) / 8` is set, counting bits from the least
+//! significant one, so id 1 is `0x80` of the first byte. Purposes and special features are
+//! integer masks like [`IdSetExt::to_int_mask`], where id `n` is bit `n - 1`. Publisher
+//! restrictions are not part of the struct.
+//!
+//! `cbindgen` copies the field docs below into the generated header.
+//!
+//! This module is only available with the `ffi` feature.
+//!
+use crate::sections::tcfeuv2::{Core, TcfEuV2};
+use crate::sections::{IdSet, IdSetExt};
+use crate::v1::GPPString;
+use std::ffi::{CStr, c_char, c_int};
+
+/// Number of bytes of a vendor bitmask, enough for every 16-bit vendor id.
+pub const IAB_GPP_VENDOR_BITMASK_LEN: usize = (u16::MAX as usize).div_ceil(8);
+
+/// The string was decoded and the output struct was written.
+pub const IAB_GPP_OK: c_int = 0;
+/// A pointer argument is null, or the string is not valid UTF-8.
+pub const IAB_GPP_INVALID_ARGUMENT: c_int = -1;
+/// The GPP string could not be parsed.
+pub const IAB_GPP_PARSE_ERROR: c_int = -2;
+/// The GPP string has no TCF EU v2 section.
+pub const IAB_GPP_MISSING_SECTION: c_int = -3;
+/// The TCF EU v2 section could not be decoded.
+pub const IAB_GPP_DECODE_ERROR: c_int = -4;
+
+/// The core segment of a TCF EU v2 section, see [`Core`].
+#[repr(C)]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IabGppTcfEuV2Core {
+    pub created: u64,
+    pub last_updated: u64,
+    pub cmp_id: u16,
+    pub cmp_version: u16,
+    pub consent_screen: u8,
+    /// ASCII uppercase letters, not NUL-terminated.
+    pub consent_language: [u8; 2],
+    pub vendor_list_version: u16,
+    pub policy_version: u8,
+    pub is_service_specific: bool,
+    pub use_non_standard_stacks: bool,
+    /// Special feature `n` is bit `n - 1`, counting from the least significant bit.
+    pub special_feature_optins: u16,
+    /// Purpose `n` is bit `n - 1`, counting from the least significant bit.
+    pub purpose_consents: u32,
+    /// Purpose `n` is bit `n - 1`, counting from the least significant bit.
+    pub purpose_legitimate_interests: u32,
+    pub purpose_one_treatment: bool,
+    /// ASCII uppercase letters, not NUL-terminated.
+    pub publisher_country_code: [u8; 2],
+    /// Vendor `n` is the mask `0x80 >> ((n - 1) % 8)` of byte `(n - 1) / 8`, most significant bit
+    /// first.
+    pub vendor_consents: [u8; IAB_GPP_VENDOR_BITMASK_LEN],
+    /// Vendor `n` is the mask `0x80 >> ((n - 1) % 8)` of byte `(n - 1) / 8`, most significant bit
+    /// first.
+    pub vendor_legitimate_interests: [u8; IAB_GPP_VENDOR_BITMASK_LEN],
+}
+
+impl From<&Core> for IabGppTcfEuV2Core {
+    fn from(core: &Core) -> Self {
+        Self {
+            created: core.created,
+            last_updated: core.last_updated,
+            cmp_id: core.cmp_id,
+            cmp_version: core.cmp_version,
+            consent_screen: core.consent_screen,
+            consent_language: two_letters(core.consent_language.as_str()),
+            vendor_list_version: core.vendor_list_version,
+            policy_version: core.policy_version,
+            is_service_specific: core.is_service_specific,
+            use_non_standard_stacks: core.use_non_standard_stacks,
+            special_feature_optins: core.special_feature_optins.to_int_mask(12) as u16,
+            purpose_consents: core.purpose_consents.to_int_mask(24),
+            purpose_legitimate_interests: core.purpose_legitimate_interests.to_int_mask(24),
+            purpose_one_treatment: core.purpose_one_treatment,
+            publisher_country_code: two_letters(core.publisher_country_code.as_str()),
+            vendor_consents: byte_mask(&core.vendor_consents),
+            vendor_legitimate_interests: byte_mask(&core.vendor_legitimate_interests),
+        }
+    }
+}
+
+fn two_letters(s: &str) -> [u8; 2] {
+    match s.as_bytes() {
+        &[a, b] => [a, b],
+        _ => [0; 2],
+    }
+}
+
+fn byte_mask(ids: &IdSet) -> [u8; IAB_GPP_VENDOR_BITMASK_LEN] {
+    let mut mask = [0; IAB_GPP_VENDOR_BITMASK_LEN];
+    mask.copy_from_slice(&ids.to_bitmask(u16::MAX as usize));
+    mask
+}
+
+/// Decodes the TCF EU v2 core segment of a NUL-terminated GPP string into `out`.
+///
+/// Returns [`IAB_GPP_OK`] on success, or one of the negative `IAB_GPP_*` error codes, in
+/// which case `out` is left untouched.
+///
+/// # Safety
+///
+/// `gpp` must be null or point to a NUL-terminated string, and `out` must be null or point to
+/// memory valid for writing a [`IabGppTcfEuV2Core`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn iab_gpp_decode_tcf_eu_v2(
+    gpp: *const c_char,
+    out: *mut IabGppTcfEuV2Core,
+) -> c_int {
+    if gpp.is_null() || out.is_null() {
+        return IAB_GPP_INVALID_ARGUMENT;
+    }
+    // SAFETY: checked for null above, the caller guarantees the string is NUL-terminated
+    let Ok(s) = unsafe { CStr::from_ptr(gpp) }.to_str() else {
+        return IAB_GPP_INVALID_ARGUMENT;
+    };
+    let Ok(gpp_str) = GPPString::parse_str(s) else {
+        return IAB_GPP_PARSE_ERROR;
+    };
+    let tcf = match gpp_str.decode_first::<TcfEuV2>() {
+        Some(Ok(tcf)) => tcf,
+        Some(Err(_)) => return IAB_GPP_DECODE_ERROR,
+        None => return IAB_GPP_MISSING_SECTION,
+    };
+
+    // SAFETY: checked for null above, the caller guarantees the memory is valid for writes
+    unsafe { out.write(IabGppTcfEuV2Core::from(&tcf.core)) };
+    IAB_GPP_OK
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::mem::MaybeUninit;
+    use std::ptr;
+
+    fn decode(gpp: &CStr) -> Result<Box<IabGppTcfEuV2Core>, c_int> {
+        let mut out = Box::<IabGppTcfEuV2Core>::new_uninit();
+        match unsafe { iab_gpp_decode_tcf_eu_v2(gpp.as_ptr(), out.as_mut_ptr()) } {
+            // SAFETY: the struct is fully written on success
+            IAB_GPP_OK => Ok(unsafe { out.assume_init() }),
+            code => Err(code),
+        }
+    }
+
+    #[test]
+    fn decode_core() {
+        let core = decode(c"DBABM~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA").unwrap();
+        let tcf: TcfEuV2 = "CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA"
+            .parse()
+            .unwrap();
+
+        assert_eq!(*core, IabGppTcfEuV2Core::from(&tcf.core));
+        assert_eq!(core.created, 1650492000);
+        assert_eq!(core.cmp_id, 31);
+        assert_eq!(&core.consent_language, b"EN");
+        assert_eq!(&core.publisher_country_code, b"DE");
+    }
+
+    #[test]
+    fn bitmasks() {
+        let mut tcf: TcfEuV2 = "CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA"
+            .parse()
+            .unwrap();
+        tcf.core.purpose_consents = IdSet::from([1, 3, 24]);
+        tcf.core.vendor_consents = IdSet::from([1, 9, 755, u16::MAX]);
+
+        let core = IabGppTcfEuV2Core::from(&tcf.core);
+        assert_eq!(core.purpose_consents, 0x80_0005);
+        assert_eq!(core.vendor_consents[0], 0b1000_0000);
+        assert_eq!(core.vendor_consents[1], 0b1000_0000);
+        // 755 is the third id of byte 94
+        assert_eq!(core.vendor_consents[94], 0b0010_0000);
+        assert_eq!(
+            core.vendor_consents[IAB_GPP_VENDOR_BITMASK_LEN - 1],
+            0b0000_0010
+        );
+        assert_eq!(
+            &core.vendor_consents[..],
+            &tcf.core.vendor_consents.to_bitmask(u16::MAX as usize)[..]
+        );
+        assert_eq!(
+            core.vendor_consents
+                .iter()
+                .map(|b| b.count_ones())
+                .sum::<u32>(),
+            4
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(decode(c"DBABTA~1YNN"), Err(IAB_GPP_MISSING_SECTION));
+        assert_eq!(decode(c"DBABM"), Err(IAB_GPP_PARSE_ERROR));
+        assert_eq!(decode(c"DBABM~CPX"), Err(IAB_GPP_DECODE_ERROR));
+        assert_eq!(decode(c"DBABM~\xff"), Err(IAB_GPP_INVALID_ARGUMENT));
+
+        let mut out = MaybeUninit::<IabGppTcfEuV2Core>::uninit();
+        let code = unsafe { iab_gpp_decode_tcf_eu_v2(ptr::null(), out.as_mut_ptr()) };
+        assert_eq!(code, IAB_GPP_INVALID_ARGUMENT);
+        let code = unsafe { iab_gpp_decode_tcf_eu_v2(c"DBABTA~1YNN".as_ptr(), ptr::null_mut()) };
+        assert_eq!(code, IAB_GPP_INVALID_ARGUMENT);
+    }
+}
@@ -85,6 +85,40 @@
 //! This is done to avoid obtaining erroneous user consent information from potentially corrupted
 //! payloads.
 //!
-pub(crate) mod core;
+//! # Cargo features
+//!
+//! - `bitfield-debug`: adds an `as_bitfield_debug` function to section and segment types,
+//!   which renders the bit offset, width and decoded value of each field. This is meant to
+//!   debug layout mismatches and should not be enabled in production builds.
+//! - `cache`: provides [`GPPCache`](cache::GPPCache), a thread-safe LRU cache of decoded
+//!   strings for services which see the same consent strings repeatedly.
+//! - `ffi`: provides a C interface in [`ffi`], which decodes the TCF EU v2 core segment of a
+//!   GPP string into a flat `#[repr(C)]` struct.
+//! - `provenance`: adds a `decode_traced` function to section and segment types, which
+//!   returns the decoded value along with the bit offset and width of each field, see
+//!   [`FieldProvenance`]. This enables `bitfield-debug`.
+//! - `serde`: implements `Serialize` and `Deserialize` for all section types.
+//! - `serde-strict`: makes deserializing a TCF EU v2 section and its segments fail on unknown
+//!   fields, so that typos in stored JSON are caught instead of silently dropped. This enables
+//!   `serde`. JSON written by a later version of this crate may carry fields unknown to the
+//!   current one, since section types are `#[non_exhaustive]` and can gain fields in minor
+//!   releases, so only enable it when the JSON is produced by the same version.
+//! - `test-util`: implements `Default` for all section types. Since they are marked
+//!   `#[non_exhaustive]`, this is the only way to build them outside of this crate, for
+//!   example to write fixtures when testing code which consumes decoded sections. The default
+//!   values are not meaningful consent data.
+//! - `trace`: logs the id, input length and decode duration of each section at `trace` level
+//!   using the [`log`](https://docs.rs/log) crate.
+//!
+#[cfg(feature = "cache")]
+pub mod cache;
+mod core;
+#[cfg(feature = "ffi")]
+pub mod ffi;
 pub mod sections;
 pub mod v1;
+
+pub use core::{DataRead, DataWrite};
+#[cfg(feature = "provenance")]
+pub use core::FieldProvenance;
+pub use sections::supported_sections;
@@ -0,0 +1,94 @@
+use crate::core::{Alphabet, Base64BitReader, base64_bit_reader};
+use crate::sections::{DecodeOptions, DecodeWarning, IdSet, SectionDecodeError};
+use std::cell::RefCell;
+use std::io;
+
+/// The state of a single section decode: the options in effect and the warnings raised so far.
+///
+/// A context is created for each decode and passed to every parser involved, nested types
+/// included, so that they can honor the options and report warnings.
+#[derive(Debug, Default)]
+pub struct DecodeContext {
+    pub(crate) options: DecodeOptions,
+    warnings: RefCell<Vec<DecodeWarning>>,
+}
+
+impl DecodeContext {
+    pub(crate) fn new(options: DecodeOptions) -> Self {
+        Self {
+            options,
+            warnings: RefCell::default(),
+        }
+    }
+
+    /// Records a warning.
+    pub(crate) fn warn(&self, warning: DecodeWarning) {
+        self.warnings.borrow_mut().push(warning);
+    }
+
+    /// Returns the warnings raised with this context, in the order they were raised.
+    pub(crate) fn into_warnings(self) -> Vec<DecodeWarning> {
+        self.warnings.into_inner()
+    }
+
+    /// Handles id 0 in ids read from an integer range: it is kept with a warning by default,
+    /// and rejected with [`reject_zero_ids`](DecodeOptions::reject_zero_ids).
+    ///
+    /// Bitfields cannot encode id 0, so only ids read from ranges need to be checked.
+    pub(crate) fn check_ids(&self, ids: IdSet) -> Result<IdSet, SectionDecodeError> {
+        if ids.first() == Some(&0) {
+            if self.options.reject_zero_ids {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid id 0").into());
+            }
+            self.warn(DecodeWarning::ZeroId);
+        }
+        Ok(ids)
+    }
+
+    /// Returns a bit reader over a Base64 section string.
+    ///
+    /// The standard alphabet is only accepted with
+    /// [`accept_standard_base64`](DecodeOptions::accept_standard_base64), and raises a
+    /// [`DecodeWarning::NonStandardBase64Alphabet`] when found.
+    pub(crate) fn bit_reader<'s>(&self, s: &'s str) -> Base64BitReader<'s> {
+        if !self.options.accept_standard_base64 {
+            return base64_bit_reader(s.as_bytes());
+        }
+        if s.bytes().any(|b| b == b'+' || b == b'/') {
+            self.warn(DecodeWarning::NonStandardBase64Alphabet);
+        }
+        Base64BitReader::with_alphabet(s.as_bytes(), Alphabet::UrlSafeOrStandard)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zero_id_is_kept_with_a_warning() {
+        let ctx = DecodeContext::default();
+        let ids = ctx.check_ids(IdSet::from([0, 1, 2])).unwrap();
+
+        assert_eq!(ids, IdSet::from([0, 1, 2]));
+        assert_eq!(ctx.into_warnings(), [DecodeWarning::ZeroId]);
+    }
+
+    #[test]
+    fn zero_id_is_rejected() {
+        let ctx = DecodeContext::new(DecodeOptions::new().with_reject_zero_ids());
+
+        assert!(matches!(
+            ctx.check_ids(IdSet::from([0, 1, 2])),
+            Err(SectionDecodeError::Read { source }) if source.kind() == io::ErrorKind::InvalidData
+        ));
+    }
+
+    #[test]
+    fn ids_without_zero_raise_no_warning() {
+        let ctx = DecodeContext::default();
+        ctx.check_ids(IdSet::from([1, 2])).unwrap();
+
+        assert!(ctx.into_warnings().is_empty());
+    }
+}
@@ -0,0 +1,462 @@
+use crate::sections::IdSet;
+#[cfg(feature = "serde")]
+use serde::de::{SeqAccess, Visitor};
+#[cfg(feature = "serde")]
+use serde::ser::SerializeSeq;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::ops::{Deref, DerefMut, RangeInclusive};
+
+/// Returns the inclusive spans of consecutive ids in the set, in ascending order.
+pub(crate) fn spans(set: &IdSet) -> impl Iterator<Item = (u16, u16)> + '_ {
+    let mut ids = set.iter().copied().peekable();
+    std::iter::from_fn(move || {
+        let start = ids.next()?;
+        let mut end = start;
+        while let Some(&next) = ids.peek() {
+            if next != end + 1 {
+                break;
+            }
+            end = next;
+            ids.next();
+        }
+        Some((start, end))
+    })
+}
+
+/// Additional methods on [`IdSet`].
+///
+/// [`IdSet`] is an alias of [`BTreeSet`](std::collections::BTreeSet), so these are provided by
+/// an extension trait, which needs to be in scope.
+pub trait IdSetExt {
+    /// Returns a [`Display`](fmt::Display) implementation listing spans of consecutive ids
+    /// rather than every id, such as `{1-5, 8, 100-200}`.
+    ///
+    /// This keeps dumps of large vendor sets readable, while the [`Debug`] implementation
+    /// still lists every id.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::{IdSet, IdSetExt};
+    ///
+    /// let ids = IdSet::from_iter((1..=5).chain([8]).chain(100..=200));
+    ///
+    /// assert_eq!(ids.display_ranges().to_string(), "{1-5, 8, 100-200}");
+    /// ```
+    fn display_ranges(&self) -> DisplayRanges<'_>;
+
+    /// Adds every id from `start` to `end`, both included. Nothing is added if `end` is lower
+    /// than `start`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::{IdSet, IdSetExt};
+    ///
+    /// let mut ids = IdSet::from([8]);
+    /// ids.insert_range(1, 5);
+    ///
+    /// assert_eq!(ids, IdSet::from([1, 2, 3, 4, 5, 8]));
+    /// ```
+    fn insert_range(&mut self, start: u16, end: u16);
+
+    /// Builds a set from inclusive ranges of ids, which may overlap or be adjacent.
+    ///
+    /// [`IdSet`] is a foreign type, so this stands in for a `From<Vec<RangeInclusive<u16>>>`
+    /// implementation.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::{IdSet, IdSetExt};
+    ///
+    /// let ids = IdSet::from_ranges(vec![1..=100, 755..=755]);
+    ///
+    /// assert_eq!(ids.len(), 101);
+    /// assert_eq!(ids.as_ranges(), vec![1..=100, 755..=755]);
+    /// ```
+    fn from_ranges(ranges: impl IntoIterator<Item = RangeInclusive<u16>>) -> Self;
+
+    /// Returns the inclusive ranges of consecutive ids, in ascending order.
+    ///
+    /// Ranges are as long as possible, so adjacent or overlapping ranges given to
+    /// [`from_ranges`](IdSetExt::from_ranges) are merged.
+    fn as_ranges(&self) -> Vec<RangeInclusive<u16>>;
+
+    /// Returns the ids as a bitfield of `width` bits, padded with zero bits to whole bytes.
+    ///
+    /// This is the representation read by `#[gpp(fixed_bitfield(width))]`: the first bit,
+    /// starting from the most significant bit of the first byte, is set if id 1 is in the set.
+    /// Ids which do not fit in `width` bits, and id 0, are dropped, see
+    /// [`try_to_bitmask`](IdSetExt::try_to_bitmask) to detect them instead.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::{IdSet, IdSetExt};
+    ///
+    /// let ids = IdSet::from([1, 3, 10, 30]);
+    ///
+    /// assert_eq!(ids.to_bitmask(12), vec![0b1010_0000, 0b0100_0000]);
+    /// ```
+    fn to_bitmask(&self, width: usize) -> Vec<u8>;
+
+    /// Returns the ids as a bitfield of `width` bits like [`to_bitmask`](IdSetExt::to_bitmask),
+    /// or the first id which does not fit in `width` bits.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::{IdSet, IdSetExt};
+    ///
+    /// assert_eq!(IdSet::from([1, 3]).try_to_bitmask(4), Ok(vec![0b1010_0000]));
+    /// assert_eq!(IdSet::from([1, 3, 10, 30]).try_to_bitmask(4), Err(10));
+    /// ```
+    fn try_to_bitmask(&self, width: usize) -> Result<Vec<u8>, u16>;
+
+    /// Returns the ids of a bitfield of `width` bits packed into an integer, where bit `i` is
+    /// set if id `i + 1` is in the set.
+    ///
+    /// Ids which do not fit in `width` bits, and id 0, are dropped. Widths over 32 bits are
+    /// treated as 32 bits.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::{IdSet, IdSetExt};
+    ///
+    /// let ids = IdSet::from([1, 3, 4, 24, 25]);
+    ///
+    /// assert_eq!(ids.to_int_mask(24), 0x80_000d);
+    /// ```
+    fn to_int_mask(&self, width: u32) -> u32;
+}
+
+impl IdSetExt for IdSet {
+    fn display_ranges(&self) -> DisplayRanges<'_> {
+        DisplayRanges(self)
+    }
+
+    fn insert_range(&mut self, start: u16, end: u16) {
+        self.extend(start..=end);
+    }
+
+    fn from_ranges(ranges: impl IntoIterator<Item = RangeInclusive<u16>>) -> Self {
+        ranges.into_iter().flatten().collect()
+    }
+
+    fn as_ranges(&self) -> Vec<RangeInclusive<u16>> {
+        spans(self).map(|(start, end)| start..=end).collect()
+    }
+
+    fn to_bitmask(&self, width: usize) -> Vec<u8> {
+        let mut mask = vec![0; width.div_ceil(8)];
+        let ids = self.iter().map(|&id| id as usize);
+        for id in ids.filter(|id| (1..=width).contains(id)) {
+            mask[(id - 1) / 8] |= 0x80 >> ((id - 1) % 8);
+        }
+        mask
+    }
+
+    fn try_to_bitmask(&self, width: usize) -> Result<Vec<u8>, u16> {
+        match self.iter().find(|&&id| id == 0 || id as usize > width) {
+            Some(&id) => Err(id),
+            None => Ok(self.to_bitmask(width)),
+        }
+    }
+
+    fn to_int_mask(&self, width: u32) -> u32 {
+        let width = width.min(u32::BITS) as u16;
+        self.iter()
+            .filter(|&&id| (1..=width).contains(&id))
+            .fold(0, |mask, &id| mask | 1 << (id - 1))
+    }
+}
+
+/// Displays an [`IdSet`] as spans of consecutive ids, see [`IdSetExt::display_ranges`].
+#[derive(Clone, Copy, Debug)]
+pub struct DisplayRanges<'a>(&'a IdSet);
+
+impl fmt::Display for DisplayRanges<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("{")?;
+        for (i, (start, end)) in spans(self.0).enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            if start == end {
+                write!(f, "{start}")?;
+            } else {
+                write!(f, "{start}-{end}")?;
+            }
+        }
+        f.write_str("}")
+    }
+}
+
+/// An [`IdSet`] wrapper which serializes as a list of inclusive `[start, end]` spans.
+///
+/// For dense sets such as vendor consents, `[[1, 5], [8, 8], [100, 200]]` is much more compact
+/// than the flat list of ids produced by serializing an [`IdSet`] directly.
+///
+/// # Example
+///
+/// ```
+/// use iab_gpp::sections::{IdSet, RangeEncodedIdSet};
+///
+/// let ids = RangeEncodedIdSet::from(IdSet::from([1, 2, 3, 4, 5, 8]));
+///
+/// # #[cfg(feature = "serde")]
+/// assert_eq!(serde_json::to_string(&ids).unwrap(), "[[1,5],[8,8]]");
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct RangeEncodedIdSet(pub IdSet);
+
+impl RangeEncodedIdSet {
+    /// Returns the inclusive `(start, end)` spans of consecutive ids, in ascending order.
+    ///
+    /// These are the spans used for serialization.
+    pub fn spans(&self) -> impl Iterator<Item = (u16, u16)> + '_ {
+        spans(&self.0)
+    }
+}
+
+impl fmt::Display for RangeEncodedIdSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.display_ranges().fmt(f)
+    }
+}
+
+impl From<IdSet> for RangeEncodedIdSet {
+    fn from(set: IdSet) -> Self {
+        Self(set)
+    }
+}
+
+impl From<Vec<RangeInclusive<u16>>> for RangeEncodedIdSet {
+    fn from(ranges: Vec<RangeInclusive<u16>>) -> Self {
+        Self(IdSet::from_ranges(ranges))
+    }
+}
+
+impl From<RangeEncodedIdSet> for IdSet {
+    fn from(set: RangeEncodedIdSet) -> Self {
+        set.0
+    }
+}
+
+impl Deref for RangeEncodedIdSet {
+    type Target = IdSet;
+
+    fn deref(&self) -> &IdSet {
+        &self.0
+    }
+}
+
+impl DerefMut for RangeEncodedIdSet {
+    fn deref_mut(&mut self) -> &mut IdSet {
+        &mut self.0
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for RangeEncodedIdSet {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(None)?;
+        for (start, end) in self.spans() {
+            seq.serialize_element(&[start, end])?;
+        }
+        seq.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for RangeEncodedIdSet {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct SpansVisitor;
+
+        impl<'de> Visitor<'de> for SpansVisitor {
+            type Value = RangeEncodedIdSet;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a list of [start, end] id spans")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut set = IdSet::new();
+                while let Some([start, end]) = seq.next_element::<[u16; 2]>()? {
+                    if start > end {
+                        return Err(serde::de::Error::custom(format!(
+                            "invalid span [{start}, {end}]"
+                        )));
+                    }
+                    set.extend(start..=end);
+                }
+                Ok(RangeEncodedIdSet(set))
+            }
+        }
+
+        deserializer.deserialize_seq(SpansVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(&[] => Vec::<(u16, u16)>::new() ; "empty")]
+    #[test_case(&[7] => vec![(7, 7)] ; "single")]
+    #[test_case(&[1, 2, 3, 4, 5, 8, 100, 101] => vec![(1, 5), (8, 8), (100, 101)] ; "mixed")]
+    #[test_case(&[65534, 65535] => vec![(65534, 65535)] ; "upper bound")]
+    fn spans(ids: &[u16]) -> Vec<(u16, u16)> {
+        let set = IdSet::from_iter(ids.iter().copied());
+        super::spans(&set).collect()
+    }
+
+    #[test_case(&[] => "{}" ; "empty")]
+    #[test_case(&[7] => "{7}" ; "single")]
+    #[test_case(&[1, 2] => "{1-2}" ; "pair")]
+    #[test_case(&[1, 2, 3, 4, 5, 8, 100, 101] => "{1-5, 8, 100-101}" ; "mixed")]
+    fn display_ranges(ids: &[u16]) -> String {
+        let set = IdSet::from_iter(ids.iter().copied());
+        assert_eq!(
+            RangeEncodedIdSet(set.clone()).to_string(),
+            set.display_ranges().to_string()
+        );
+        set.display_ranges().to_string()
+    }
+
+    #[test_case(vec![] => Vec::<RangeInclusive<u16>>::new() ; "empty")]
+    #[test_case(vec![1..=100] => vec![1..=100] ; "single")]
+    #[test_case(vec![1..=5, 3..=8] => vec![1..=8] ; "overlapping")]
+    #[test_case(vec![1..=5, 6..=8] => vec![1..=8] ; "adjacent")]
+    #[test_case(vec![10..=12, 1..=2, 2..=2] => vec![1..=2, 10..=12] ; "unordered")]
+    #[test_case(vec![65534..=65535] => vec![65534..=65535] ; "upper bound")]
+    fn ranges(ranges: Vec<RangeInclusive<u16>>) -> Vec<RangeInclusive<u16>> {
+        let set = IdSet::from_ranges(ranges.clone());
+        assert_eq!(RangeEncodedIdSet::from(ranges).0, set);
+        set.as_ranges()
+    }
+
+    #[test_case(&[], 24 ; "empty")]
+    #[test_case(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11], 24 ; "purposes")]
+    #[test_case(&[1, 12], 12 ; "ends")]
+    #[test_case(&[2, 9, 17], 17 ; "partial last byte")]
+    fn bitmask_round_trip(ids: &[u16], width: usize) {
+        use crate::core::DataRead;
+        use bitstream_io::{BigEndian, BitReader};
+
+        let set = IdSet::from_iter(ids.iter().copied());
+        let mask = set.to_bitmask(width);
+        assert_eq!(mask.len(), width.div_ceil(8));
+
+        let decoded = BitReader::endian(&mask[..], BigEndian)
+            .read_fixed_bitfield(width)
+            .unwrap();
+        assert_eq!(decoded, set);
+        assert_eq!(set.try_to_bitmask(width), Ok(mask));
+    }
+
+    #[test_case(&[0, 1], 8 => (vec![0b1000_0000], Err(0)) ; "zero")]
+    #[test_case(&[1, 9], 8 => (vec![0b1000_0000], Err(9)) ; "beyond width")]
+    #[test_case(&[1], 0 => (vec![], Err(1)) ; "zero width")]
+    fn bitmask_out_of_range(ids: &[u16], width: usize) -> (Vec<u8>, Result<Vec<u8>, u16>) {
+        let set = IdSet::from_iter(ids.iter().copied());
+        (set.to_bitmask(width), set.try_to_bitmask(width))
+    }
+
+    #[test_case(&[], 24 => 0 ; "empty")]
+    #[test_case(&[1, 3, 4, 24], 24 => 0x80_000d ; "purposes")]
+    #[test_case(&[0, 1, 12, 13], 12 => 0x801 ; "out of range")]
+    #[test_case(&[32, 33], 40 => 1 << 31 ; "over 32 bits")]
+    fn int_mask(ids: &[u16], width: u32) -> u32 {
+        IdSet::from_iter(ids.iter().copied()).to_int_mask(width)
+    }
+
+    #[test_case(1, 5 => IdSet::from([1, 2, 3, 4, 5]) ; "range")]
+    #[test_case(3, 3 => IdSet::from([3]) ; "single id")]
+    #[test_case(5, 1 => IdSet::new() ; "reversed")]
+    #[test_case(65534, 65535 => IdSet::from([65534, 65535]) ; "upper bound")]
+    fn insert_range(start: u16, end: u16) -> IdSet {
+        let mut set = IdSet::new();
+        set.insert_range(start, end);
+        set
+    }
+
+    #[test_case(&[], &[] ; "empty")]
+    #[test_case(&[1, 2, 3], &[] ; "one empty")]
+    #[test_case(&[1, 2, 3], &[2, 3, 4] ; "overlapping")]
+    #[test_case(&[1, 2], &[755, 1000] ; "disjoint")]
+    fn operators_match_methods(a: &[u16], b: &[u16]) {
+        let a = IdSet::from_iter(a.iter().copied());
+        let b = IdSet::from_iter(b.iter().copied());
+
+        assert_eq!(&a - &b, a.difference(&b).copied().collect());
+        assert_eq!(&a & &b, a.intersection(&b).copied().collect());
+        assert_eq!(&a | &b, a.union(&b).copied().collect());
+        assert_eq!(&a ^ &b, a.symmetric_difference(&b).copied().collect());
+    }
+
+    #[test]
+    fn ascending_iteration() {
+        let mut set = IdSet::new();
+        for id in [755, 2, 1000, 1, 32] {
+            set.insert(id);
+        }
+
+        assert_eq!(
+            set.iter().copied().collect::<Vec<_>>(),
+            [1, 2, 32, 755, 1000]
+        );
+        assert_eq!(set.into_iter().collect::<Vec<_>>(), [1, 2, 32, 755, 1000]);
+    }
+
+    #[test]
+    fn group_by_id_set() {
+        use std::collections::HashMap;
+
+        let profiles = [
+            IdSet::from([1, 2, 755]),
+            IdSet::from_iter([755, 2, 1]),
+            IdSet::from_iter((1..=2).chain([755])),
+            IdSet::from([1]),
+        ];
+        let mut counts = HashMap::new();
+        for profile in profiles {
+            *counts.entry(profile).or_insert(0) += 1;
+        }
+
+        assert_eq!(counts.len(), 2);
+        assert_eq!(counts[&IdSet::from([1, 2, 755])], 3);
+        assert_eq!(counts[&IdSet::from([1])], 1);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test_case(&[] => "[]" ; "empty")]
+    #[test_case(&[1, 2, 3, 4, 5, 8] => "[[1,5],[8,8]]" ; "mixed")]
+    fn serialize(ids: &[u16]) -> String {
+        let set = RangeEncodedIdSet(IdSet::from_iter(ids.iter().copied()));
+        serde_json::to_string(&set).unwrap()
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn round_trip() {
+        let set = RangeEncodedIdSet(IdSet::from_iter((1..=5).chain([8]).chain(100..=200)));
+        let json = serde_json::to_string(&set).unwrap();
+        assert_eq!(json, "[[1,5],[8,8],[100,200]]");
+        assert_eq!(
+            serde_json::from_str::<RangeEncodedIdSet>(&json).unwrap(),
+            set
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn deserialize_invalid_span() {
+        assert!(serde_json::from_str::<RangeEncodedIdSet>("[[5,1]]").is_err());
+    }
+}
@@ -19,7 +19,25 @@
 //! section types are marked with the `#[non_exhaustive]` attribute to preserve minor version
 //! compatibility.
 //!
-use crate::core::{DataRead, base64_bit_reader};
+//! Every section type implements [`FromStr`], so a section string can be decoded on its own with
+//! [`str::parse`], or with [`TryFrom<&str>`]:
+//!
+//! ```
+//! use iab_gpp::sections::SectionDecodeError;
+//! use iab_gpp::sections::tcfeuv2::TcfEuV2;
+//! use iab_gpp::sections::uspv1::{Flag, UspV1};
+//!
+//! fn main() -> Result<(), SectionDecodeError> {
+//!     let tcf = "CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA".parse::<TcfEuV2>()?;
+//!     let usp = UspV1::try_from("1YNN")?;
+//!
+//!     assert_eq!(tcf.core.cmp_id, 31);
+//!     assert_eq!(usp.opt_out_notice, Flag::Yes);
+//!     Ok(())
+//! }
+//! ```
+//!
+use crate::core::DataRead;
 use crate::sections::tcfcav1::TcfCaV1;
 use crate::sections::tcfeuv1::TcfEuV1;
 use crate::sections::tcfeuv2::TcfEuV2;
@@ -45,16 +63,27 @@ use crate::sections::ustn::UsTn;
 use crate::sections::ustx::UsTx;
 use crate::sections::usut::UsUt;
 use crate::sections::usva::UsVa;
-use bitstream_io::{BitRead, FromBitStream};
+use bitstream_io::{BitRead, FromBitStream, FromBitStreamWith};
 use num_derive::{FromPrimitive, ToPrimitive};
+use num_traits::FromPrimitive;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use std::collections::BTreeSet;
+use std::fmt;
 use std::io;
+use std::ops::Deref;
 use std::str::FromStr;
 use strum_macros::Display;
 use thiserror::Error;
 
+pub(crate) use context::DecodeContext;
+pub use id_set::{DisplayRanges, IdSetExt, RangeEncodedIdSet};
+pub use validation::{ValidationWarning, validate_consent_metadata};
+pub use warning::{DecodeResult, DecodeWarning};
+
+mod context;
+pub(crate) mod id_set;
 pub mod tcfcav1;
 pub mod tcfeuv1;
 pub mod tcfeuv2;
@@ -81,45 +110,696 @@ pub mod ustn;
 pub mod ustx;
 pub mod usut;
 pub mod usva;
+mod validation;
+mod warning;
+
+/// Declares the [`SectionId`] enum along with the section metadata.
+///
+/// This is the single source of truth for the list of known section ids: every entry is made
+/// of the enum variant, its numeric id, the section name as
+/// defined by the GPP specification (also known as the API prefix), a human-readable display
+/// name, and the jurisdiction the section applies to. Sections this crate can decode are
+/// followed by the `decodable` marker, which lists them in [`supported_sections`].
+macro_rules! section_ids {
+    (@decodable decodable) => { true };
+    (@decodable) => { false };
+    ($(
+        $variant:ident = $id:literal, $name:literal, $display_name:literal, $jurisdiction:expr
+        $(, $decodable:ident)?;
+    )+) => {
+        /// The identifier of a GPP section, as listed in the GPP string header.
+        #[derive(Clone, Copy, Debug, Display, Eq, PartialEq, Hash, FromPrimitive, ToPrimitive)]
+        #[non_exhaustive]
+        pub enum SectionId {
+            $($variant = $id,)+
+        }
+
+        impl SectionId {
+            /// Returns the name of the section as defined by the GPP specification.
+            ///
+            /// # Example
+            ///
+            /// ```
+            /// use iab_gpp::sections::SectionId;
+            ///
+            /// assert_eq!(SectionId::TcfEuV2.name(), "tcfeuv2");
+            /// ```
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $(SectionId::$variant => $name,)+
+                }
+            }
+
+            /// Returns a human-readable name for the section, suitable for display.
+            ///
+            /// # Example
+            ///
+            /// ```
+            /// use iab_gpp::sections::SectionId;
+            ///
+            /// assert_eq!(SectionId::UsNj.display_name(), "US New Jersey");
+            /// ```
+            pub fn display_name(&self) -> &'static str {
+                match self {
+                    $(SectionId::$variant => $display_name,)+
+                }
+            }
+
+            /// Returns the jurisdiction the section applies to.
+            ///
+            /// # Example
+            ///
+            /// ```
+            /// use iab_gpp::sections::{Jurisdiction, SectionId};
+            ///
+            /// assert_eq!(SectionId::TcfEuV2.jurisdiction(), Jurisdiction::Eu);
+            /// assert_eq!(SectionId::UsVa.jurisdiction(), Jurisdiction::UsState("VA"));
+            /// ```
+            pub fn jurisdiction(&self) -> Jurisdiction {
+                use Jurisdiction::*;
+
+                match self {
+                    $(SectionId::$variant => $jurisdiction,)+
+                }
+            }
+        }
 
-#[derive(Clone, Copy, Debug, Display, Eq, PartialEq, Hash, FromPrimitive, ToPrimitive)]
+        /// Every known section id, along with whether it can be decoded, in ascending id order.
+        const SECTION_TABLE: &[(SectionId, bool)] = &[
+            $((SectionId::$variant, section_ids!(@decodable $($decodable)?)),)+
+        ];
+    };
+}
+
+// Adding a new section, such as the next US state section, is done in the following steps:
+// - declare its id here, using the next id registered in the GPP specification, along with its
+//   display name and jurisdiction, and mark it `decodable`;
+// - add a module based on the closest existing section (most US states follow the US National
+//   template: a versioned core segment followed by an optional GPC segment, see `usnj`);
+// - add a variant to `Section`, and the matching arms in `Section::id` and `decode_section`;
+// - add JSON fixtures in `tests/data`, which are picked up automatically as decode tests.
+section_ids! {
+    TcfEuV1 = 1, "tcfeuv1", "TCF EU v1", Eu, decodable;
+    TcfEuV2 = 2, "tcfeuv2", "TCF EU v2", Eu, decodable;
+    GppHeader = 3, "header", "GPP Header", Global;
+    GppSignalIntegrity = 4, "signalIntegrity", "GPP Signal Integrity", Global;
+    TcfCaV1 = 5, "tcfcav1", "TCF Canada v1", Canada, decodable;
+    UspV1 = 6, "uspv1", "US Privacy v1", UsNational, decodable;
+    UsNat = 7, "usnat", "US National", UsNational, decodable;
+    UsCa = 8, "usca", "US California", UsState("CA"), decodable;
+    UsVa = 9, "usva", "US Virginia", UsState("VA"), decodable;
+    UsCo = 10, "usco", "US Colorado", UsState("CO"), decodable;
+    UsUt = 11, "usut", "US Utah", UsState("UT"), decodable;
+    UsCt = 12, "usct", "US Connecticut", UsState("CT"), decodable;
+    UsFl = 13, "usfl", "US Florida", UsState("FL"), decodable;
+    UsMt = 14, "usmt", "US Montana", UsState("MT"), decodable;
+    UsOr = 15, "usor", "US Oregon", UsState("OR"), decodable;
+    UsTx = 16, "ustx", "US Texas", UsState("TX"), decodable;
+    UsDe = 17, "usde", "US Delaware", UsState("DE"), decodable;
+    UsIa = 18, "usia", "US Iowa", UsState("IA"), decodable;
+    UsNe = 19, "usne", "US Nebraska", UsState("NE"), decodable;
+    UsNh = 20, "usnh", "US New Hampshire", UsState("NH"), decodable;
+    UsNj = 21, "usnj", "US New Jersey", UsState("NJ"), decodable;
+    UsTn = 22, "ustn", "US Tennessee", UsState("TN"), decodable;
+    UsMn = 23, "usmn", "US Minnesota", UsState("MN"), decodable;
+    UsMd = 24, "usmd", "US Maryland", UsState("MD"), decodable;
+    UsIn = 25, "usin", "US Indiana", UsState("IN"), decodable;
+    UsKy = 26, "usky", "US Kentucky", UsState("KY"), decodable;
+    UsRi = 27, "usri", "US Rhode Island", UsState("RI"), decodable;
+}
+
+/// The region whose regulation a section implements.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
 #[non_exhaustive]
-pub enum SectionId {
-    TcfEuV1 = 1,
-    TcfEuV2 = 2,
-    GppHeader = 3,
-    GppSignalIntegrity = 4,
-    TcfCaV1 = 5,
-    UspV1 = 6,
-    UsNat = 7,
-    UsCa = 8,
-    UsVa = 9,
-    UsCo = 10,
-    UsUt = 11,
-    UsCt = 12,
-    UsFl = 13,
-    UsMt = 14,
-    UsOr = 15,
-    UsTx = 16,
-    UsDe = 17,
-    UsIa = 18,
-    UsNe = 19,
-    UsNh = 20,
-    UsNj = 21,
-    UsTn = 22,
-    UsMn = 23,
-    UsMd = 24,
-    UsIn = 25,
-    UsKy = 26,
-    UsRi = 27,
-}
-
-pub trait DecodableSection: FromStr<Err = SectionDecodeError> {
+pub enum Jurisdiction {
+    /// Sections which are not tied to a region, such as the GPP header.
+    Global,
+    /// The European Union and the European Economic Area.
+    Eu,
+    /// Canada.
+    Canada,
+    /// The United States as a whole.
+    UsNational,
+    /// A single US state, identified by its two-letter postal code.
+    UsState(&'static str),
+}
+
+/// Returns the name of the section with the given raw id, or [`None`] if the id is unknown.
+///
+/// This is useful to obtain a human-readable name for a section without decoding anything.
+///
+/// # Example
+///
+/// ```
+/// use iab_gpp::sections::section_name;
+/// use iab_gpp::sections::tcfeuv2::TcfEuV2;
+///
+/// assert_eq!(section_name(TcfEuV2::ID as u8), Some("tcfeuv2"));
+/// assert_eq!(section_name(6), Some("uspv1"));
+/// assert_eq!(section_name(0), None);
+/// ```
+pub fn section_name(id: u8) -> Option<&'static str> {
+    SectionId::from_u8(id).map(|id| id.name())
+}
+
+/// Options controlling how GPP strings and their sections are decoded.
+///
+/// The default options follow the specification as strictly as the regular [`FromStr`]
+/// implementations do, which use them. They apply no limits, except
+/// [`max_range_inserts`](DecodeOptions::max_range_inserts), which defaults to
+/// [`DEFAULT_MAX_RANGE_INSERTS`] so that crafted strings cannot make decoding arbitrarily slow.
+///
+/// # Example
+///
+/// ```
+/// use iab_gpp::sections::DecodeOptions;
+/// use iab_gpp::v1::{GPPDecodeError, GPPString};
+///
+/// let options = DecodeOptions::new().with_max_input_len(8);
+/// let r = GPPString::parse_str_with_options("DBABTA~1YNN", &options);
+///
+/// assert!(matches!(r, Err(GPPDecodeError::InputTooLong { len: 11, max: 8 })));
+/// ```
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct DecodeOptions {
+    /// Maximum length of an input string in bytes, or `None` for no limit. Whitespace skipped
+    /// with [`skip_whitespace`](DecodeOptions::skip_whitespace) does not count.
+    pub max_input_len: Option<usize>,
+    /// Maximum number of ranges a section may declare in a single list, such as TCF CA
+    /// publisher restrictions, or `None` for no limit.
+    pub max_ranges: Option<usize>,
+    /// Maximum number of ids the ranges of a TCF EU v2 section's publisher restrictions may
+    /// add up to, or `None` for no limit. Defaults to [`DEFAULT_MAX_RANGE_INSERTS`].
+    pub max_range_inserts: Option<usize>,
+    /// Rejects id 0 in integer ranges instead of keeping it. Ids are 1-based, so id 0 can only
+    /// come from a broken encoder.
+    pub reject_zero_ids: bool,
+    /// Removes ASCII whitespace anywhere in input strings before decoding, instead of
+    /// rejecting it as an invalid character.
+    pub skip_whitespace: bool,
+    /// Rejects lowercase `y` and `n` flags in US Privacy strings instead of reading them as
+    /// their uppercase counterparts.
+    pub reject_lowercase_usp_flags: bool,
+    /// Accepts the `+` and `/` characters of the standard Base64 alphabet in sections, as
+    /// their URL-safe counterparts `-` and `_`.
+    pub accept_standard_base64: bool,
+    /// Ignores optional segments of an unknown type instead of failing to decode the section.
+    pub skip_unknown_segments: bool,
+}
+
+/// The default of [`DecodeOptions::max_range_inserts`]: 16 ranges covering every possible id.
+///
+/// This is far more than real strings need, while bounding the work done on a crafted string,
+/// which could otherwise declare over 268 million ids.
+pub const DEFAULT_MAX_RANGE_INSERTS: usize = 16 * 65535;
+
+impl Default for DecodeOptions {
+    fn default() -> Self {
+        Self {
+            max_input_len: None,
+            max_ranges: None,
+            max_range_inserts: Some(DEFAULT_MAX_RANGE_INSERTS),
+            reject_zero_ids: false,
+            skip_whitespace: false,
+            reject_lowercase_usp_flags: false,
+            accept_standard_base64: false,
+            skip_unknown_segments: false,
+        }
+    }
+}
+
+impl DecodeOptions {
+    /// Returns the default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rejects input strings longer than `max` bytes before any decoding happens.
+    pub fn with_max_input_len(mut self, max: usize) -> Self {
+        self.max_input_len = Some(max);
+        self
+    }
+
+    /// Rejects sections declaring more than `max` ranges in a single list, before reading
+    /// any of them.
+    pub fn with_max_ranges(mut self, max: usize) -> Self {
+        self.max_ranges = Some(max);
+        self
+    }
+
+    /// Fails decoding with a [`SectionDecodeError::SectionTooLarge`] error once the ranges of
+    /// a TCF EU v2 section's publisher restrictions add up to more than `max` ids.
+    ///
+    /// Each restriction may declare up to 4095 ranges of up to 65535 ids, so a short crafted
+    /// string can otherwise make the decoder insert hundreds of millions of ids. Ids are
+    /// counted as they are read, including ones already present in a restriction.
+    ///
+    /// The limit defaults to [`DEFAULT_MAX_RANGE_INSERTS`]. This raises or lowers it, see
+    /// [`without_max_range_inserts`](DecodeOptions::without_max_range_inserts) to remove it.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::DecodeOptions;
+    /// use iab_gpp::sections::tcfeuv2::TcfEuV2;
+    ///
+    /// let s = "CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA";
+    /// let options = DecodeOptions::new().with_max_range_inserts(65535);
+    ///
+    /// assert!(options.decode::<TcfEuV2>(s).is_ok());
+    /// ```
+    pub fn with_max_range_inserts(mut self, max: usize) -> Self {
+        self.max_range_inserts = Some(max);
+        self
+    }
+
+    /// Removes the limit on the number of ids publisher restrictions may add up to, see
+    /// [`with_max_range_inserts`](DecodeOptions::with_max_range_inserts).
+    ///
+    /// Only use this with trusted input.
+    pub fn without_max_range_inserts(mut self) -> Self {
+        self.max_range_inserts = None;
+        self
+    }
+
+    /// Fails decoding with a [`SectionDecodeError::Read`] error if an integer range contains
+    /// id 0.
+    ///
+    /// Without this option, id 0 is kept in the decoded set, and reported as a
+    /// [`DecodeWarning::ZeroId`] by the `*_with_warnings` decode methods.
+    pub fn with_reject_zero_ids(mut self) -> Self {
+        self.reject_zero_ids = true;
+        self
+    }
+
+    /// Removes ASCII whitespace, such as spaces and newlines, anywhere in input strings before
+    /// decoding them.
+    ///
+    /// This helps with strings pasted from logs or emails, which are often wrapped or indented.
+    /// Whitespace is never valid in a GPP string, so without this option it makes decoding fail.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::DecodeOptions;
+    /// use iab_gpp::sections::tcfeuv2::TcfEuV2;
+    /// use iab_gpp::v1::GPPString;
+    ///
+    /// let s = "DBACNY~CPXxRfAPXxRfAAfKABENB-Cg\n    AAAAAAAAAAYgAAAAAAAA~1YNN";
+    ///
+    /// let gpp_str = GPPString::parse_str(s).unwrap();
+    /// assert!(gpp_str.decode::<TcfEuV2>().is_err());
+    ///
+    /// let options = DecodeOptions::new().with_skip_whitespace();
+    /// let gpp_str = GPPString::parse_str_with_options(s, &options).unwrap();
+    /// assert!(gpp_str.decode::<TcfEuV2>().is_ok());
+    /// ```
+    pub fn with_skip_whitespace(mut self) -> Self {
+        self.skip_whitespace = true;
+        self
+    }
+
+    /// Fails decoding of US Privacy strings with a [`SectionDecodeError::InvalidCharacter`]
+    /// error if a flag is a lowercase `y` or `n`.
+    ///
+    /// The US Privacy specification only allows uppercase flags, but some producers emit
+    /// lowercase ones, which are accepted by default.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::DecodeOptions;
+    /// use iab_gpp::sections::uspv1::UspV1;
+    ///
+    /// assert!(DecodeOptions::new().decode::<UspV1>("1yNN").is_ok());
+    ///
+    /// let options = DecodeOptions::new().with_reject_lowercase_usp_flags();
+    /// assert!(options.decode::<UspV1>("1yNN").is_err());
+    /// ```
+    pub fn with_reject_lowercase_usp_flags(mut self) -> Self {
+        self.reject_lowercase_usp_flags = true;
+        self
+    }
+
+    /// Decodes Base64 sections using the standard alphabet, as if they used the URL-safe one.
+    ///
+    /// GPP strings must use the URL-safe alphabet, but some CMPs emit `+` and `/` instead of
+    /// `-` and `_`. With this option, such sections decode, and report a
+    /// [`DecodeWarning::NonStandardBase64Alphabet`] to the `*_with_warnings` decode methods
+    /// so that the faulty producer can be identified.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::{DecodeOptions, DecodeWarning, SectionId};
+    /// use iab_gpp::v1::GPPString;
+    ///
+    /// let s = "DBABM~CPXxRfAPXxRfAAfKABENB+CgAAAAAAAAAAYgAAAAAAAA";
+    ///
+    /// let gpp_str = GPPString::parse_str(s).unwrap();
+    /// assert!(gpp_str.decode_section(SectionId::TcfEuV2).is_err());
+    ///
+    /// let options = DecodeOptions::new().with_accept_standard_base64();
+    /// let gpp_str = GPPString::parse_str_with_options(s, &options).unwrap();
+    /// let r = gpp_str.decode_section_with_warnings(SectionId::TcfEuV2).unwrap();
+    /// assert_eq!(r.warnings, [DecodeWarning::NonStandardBase64Alphabet]);
+    /// ```
+    pub fn with_accept_standard_base64(mut self) -> Self {
+        self.accept_standard_base64 = true;
+        self
+    }
+
+    /// Ignores optional segments whose type is unknown, instead of failing with a
+    /// [`SectionDecodeError::UnknownSegmentType`] error.
+    ///
+    /// This lets strings from CMPs implementing a later revision of a section, which adds
+    /// segment types, still decode the segments known to this crate. Each skipped segment is
+    /// reported as a [`DecodeWarning::UnknownSegmentSkipped`] to the `*_with_warnings` decode
+    /// methods.
+    ///
+    /// Skipping only works because segments are delimited by `.` separators, so the extent of
+    /// a segment is known without understanding its content. This applies to sections whose
+    /// optional segments start with their type, such as TCF EU v2, TCF CA and US sections, but
+    /// not to sections which declare their segments in a header, and never to the core segment.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::{DecodeOptions, DecodeWarning, SectionId};
+    /// use iab_gpp::v1::GPPString;
+    ///
+    /// // a TCF EU v2 section with a segment of type 7, which is not defined
+    /// let s = "DBABM~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA.4AAA";
+    ///
+    /// let gpp_str = GPPString::parse_str(s).unwrap();
+    /// assert!(gpp_str.decode_section(SectionId::TcfEuV2).is_err());
+    ///
+    /// let options = DecodeOptions::new().with_skip_unknown_segments();
+    /// let gpp_str = GPPString::parse_str_with_options(s, &options).unwrap();
+    /// let r = gpp_str.decode_section_with_warnings(SectionId::TcfEuV2).unwrap();
+    /// assert_eq!(r.warnings, [DecodeWarning::UnknownSegmentSkipped { segment_type: 7 }]);
+    /// ```
+    pub fn with_skip_unknown_segments(mut self) -> Self {
+        self.skip_unknown_segments = true;
+        self
+    }
+
+    /// Returns the string to decode, without ASCII whitespace if
+    /// [`skip_whitespace`](DecodeOptions::skip_whitespace) is set.
+    pub(crate) fn prepare<'a>(&self, s: &'a str) -> Cow<'a, str> {
+        if self.skip_whitespace && s.bytes().any(|b| b.is_ascii_whitespace()) {
+            Cow::Owned(s.chars().filter(|c| !c.is_ascii_whitespace()).collect())
+        } else {
+            Cow::Borrowed(s)
+        }
+    }
+
+    /// Decodes a single section string using these options.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::DecodeOptions;
+    /// use iab_gpp::sections::uspv1::UspV1;
+    ///
+    /// let usp: UspV1 = DecodeOptions::new().with_max_ranges(16).decode("1YNN").unwrap();
+    /// ```
+    pub fn decode<S: DecodableSection>(&self, s: &str) -> Result<S, SectionDecodeError> {
+        S::decode_str(&self.prepare(s), &DecodeContext::new(*self))
+    }
+
+    /// Returns [`max_ranges`](DecodeOptions::max_ranges), or `usize::MAX` if unset.
+    pub(crate) fn max_ranges_or_unbounded(&self) -> usize {
+        self.max_ranges.unwrap_or(usize::MAX)
+    }
+
+    /// Returns [`max_range_inserts`](DecodeOptions::max_range_inserts), or `usize::MAX` if
+    /// unset.
+    pub(crate) fn max_range_inserts_or_unbounded(&self) -> usize {
+        self.max_range_inserts.unwrap_or(usize::MAX)
+    }
+}
+
+mod private {
+    use super::{DecodeContext, SectionDecodeError};
+
+    /// Decodes a section string with an explicit context, which carries the decode options and
+    /// collects warnings. It is implemented by every section, and derived along with `FromStr`.
+    pub trait DecodeStr: Sized {
+        fn decode_str(s: &str, ctx: &DecodeContext) -> Result<Self, SectionDecodeError>;
+    }
+}
+
+pub(crate) use private::DecodeStr;
+
+pub trait DecodableSection: FromStr<Err = SectionDecodeError> + private::DecodeStr {
     const ID: SectionId;
 }
 
+/// A set of vendor, purpose or feature ids.
+///
+/// Whether the ids were encoded as a bitfield or as ranges in the source string, decoding
+/// always produces the same ordered set, so equality and hashing only depend on membership.
+///
+/// Iterating over an [`IdSet`], with [`iter`](BTreeSet::iter) or [`IntoIterator`], always
+/// yields ids in ascending numeric order, regardless of the order in which they were inserted.
+/// This is part of the API contract, and also applies to serialized sets.
+///
+/// Being a [`BTreeSet`], it supports the usual set operations. For example, consented vendor ids
+/// can be filtered against the current Global Vendor List with [`BTreeSet::retain`]:
+///
+/// ```
+/// use iab_gpp::sections::IdSet;
+///
+/// let gvl = IdSet::from([1, 2, 755]);
+/// let mut vendor_consents = IdSet::from([2, 3, 755, 1000]);
+///
+/// vendor_consents.retain(|id| gvl.contains(id));
+///
+/// assert_eq!(vendor_consents, IdSet::from([2, 755]));
+/// ```
+///
+/// The standard library also implements the `-`, `&`, `|` and `^` operators on references to
+/// sets, which reads naturally when comparing two versions of a string:
+///
+/// ```
+/// use iab_gpp::sections::IdSet;
+///
+/// let old = IdSet::from([1, 2, 3]);
+/// let new = IdSet::from([2, 3, 4]);
+///
+/// assert_eq!(&new - &old, IdSet::from([4]));
+/// assert_eq!(&new & &old, IdSet::from([2, 3]));
+/// assert_eq!(&new | &old, IdSet::from([1, 2, 3, 4]));
+/// ```
+///
+/// Since [`IdSet`] is an alias, operators on owned sets cannot be added by this crate, so
+/// operands must be borrowed.
 pub type IdSet = BTreeSet<u16>;
 
+/// The ids added to and removed from an [`IdSet`] between two versions of a section.
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct IdSetDiff {
+    pub added: IdSet,
+    pub removed: IdSet,
+}
+
+impl IdSetDiff {
+    /// Computes the ids present in `new` but not in `old`, and the ones present in `old` but
+    /// not in `new`.
+    pub fn between(old: &IdSet, new: &IdSet) -> Self {
+        Self {
+            added: new - old,
+            removed: old - new,
+        }
+    }
+
+    /// Returns `true` if both sets have the same members.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// The previous and current values of a scalar field which changed between two versions of a
+/// section.
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FieldChange<T> {
+    pub old: T,
+    pub new: T,
+}
+
+impl<T: Clone + PartialEq> FieldChange<T> {
+    /// Returns the change between `old` and `new`, or `None` if they are equal.
+    pub fn between(old: &T, new: &T) -> Option<Self> {
+        (old != new).then(|| Self {
+            old: old.clone(),
+            new: new.clone(),
+        })
+    }
+}
+
+/// A two-letter code, such as a language or a country code, stored inline.
+///
+/// It dereferences to [`str`], so it can be used wherever a string slice is expected without
+/// allocating on decode.
+///
+/// Each letter is encoded on 6 bits, so a decoded code may hold characters past `Z` (the
+/// letter index is simply added to `A`). Such codes are kept as is, and reported by the
+/// `validate` methods of the sections holding them.
+///
+/// # Example
+///
+/// ```
+/// use iab_gpp::sections::TwoLetterCode;
+///
+/// let code = TwoLetterCode::try_from("EN").unwrap();
+///
+/// assert_eq!(code, "EN");
+/// assert_eq!(code.to_lowercase(), "en");
+/// assert!(code.is_uppercase_letters());
+/// assert!(TwoLetterCode::try_from("en").is_err());
+/// ```
+#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(into = "String", try_from = "String")
+)]
+pub struct TwoLetterCode {
+    // UTF-8 encoding of both letters, since the last letter index maps to U+0080
+    bytes: [u8; 4],
+    len: u8,
+}
+
+/// Defaults to `AA`, the code made of zero bits.
+#[cfg(feature = "test-util")]
+impl Default for TwoLetterCode {
+    fn default() -> Self {
+        Self::from_letter_indexes(0, 0)
+    }
+}
+
+impl TwoLetterCode {
+    /// Returns the code as a string slice.
+    pub fn as_str(&self) -> &str {
+        // only ever built from whole encoded characters
+        std::str::from_utf8(&self.bytes[..self.len as usize]).unwrap_or_default()
+    }
+
+    /// Returns whether the code is made of two uppercase ASCII letters, as it should be.
+    pub fn is_uppercase_letters(&self) -> bool {
+        self.as_str().len() == 2 && self.as_str().bytes().all(|b| b.is_ascii_uppercase())
+    }
+
+    fn from_letter_indexes(a: u8, b: u8) -> Self {
+        let mut bytes = [0; 4];
+        let mut len = 0;
+        for index in [a, b] {
+            // indexes are 6-bit values, so this never overflows
+            len += char::from(b'A' + index)
+                .encode_utf8(&mut bytes[len..])
+                .len();
+        }
+        Self {
+            bytes,
+            len: len as u8,
+        }
+    }
+}
+
+impl FromBitStream for TwoLetterCode {
+    type Error = SectionDecodeError;
+
+    fn from_reader<R: BitRead + ?Sized>(r: &mut R) -> Result<Self, Self::Error> {
+        let a = r.read_unsigned::<6, u8>()?;
+        let b = r.read_unsigned::<6, u8>()?;
+        Ok(Self::from_letter_indexes(a, b))
+    }
+}
+
+impl FromBitStreamWith<'_> for TwoLetterCode {
+    type Context = DecodeContext;
+    type Error = SectionDecodeError;
+
+    fn from_reader<R: BitRead + ?Sized>(
+        r: &mut R,
+        _ctx: &DecodeContext,
+    ) -> Result<Self, Self::Error> {
+        r.parse()
+    }
+}
+
+impl TryFrom<&str> for TwoLetterCode {
+    type Error = SectionDecodeError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        match s.as_bytes() {
+            &[a, b] if a.is_ascii_uppercase() && b.is_ascii_uppercase() => {
+                Ok(Self::from_letter_indexes(a - b'A', b - b'A'))
+            }
+            _ => Err(SectionDecodeError::InvalidFieldValue {
+                expected: "two uppercase letters".to_string(),
+                found: s.to_string(),
+            }),
+        }
+    }
+}
+
+impl TryFrom<String> for TwoLetterCode {
+    type Error = SectionDecodeError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        Self::try_from(s.as_str())
+    }
+}
+
+impl From<TwoLetterCode> for String {
+    fn from(code: TwoLetterCode) -> Self {
+        code.as_str().to_string()
+    }
+}
+
+impl Deref for TwoLetterCode {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for TwoLetterCode {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl PartialEq<str> for TwoLetterCode {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for TwoLetterCode {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl fmt::Debug for TwoLetterCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Display for TwoLetterCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 #[derive(Error, Debug)]
 #[non_exhaustive]
 pub enum SectionDecodeError {
@@ -140,6 +820,12 @@ pub enum SectionDecodeError {
         kind: &'static str,
         s: String,
     },
+    #[error("unexpected length for {kind} string (expected {expected}, found {found})")]
+    UnexpectedLength {
+        kind: &'static str,
+        expected: usize,
+        found: usize,
+    },
     #[error("invalid section version (expected {expected}, found {found})")]
     InvalidSectionVersion { expected: u8, found: u8 },
     #[error("invalid segment version ({segment_version})")]
@@ -152,8 +838,20 @@ pub enum SectionDecodeError {
     InvalidFieldValue { expected: String, found: String },
     #[error("missing core in header")]
     MissingCoreInHeader,
+    #[error("missing core segment (found optional segment type {segment_type} first)")]
+    MissingCoreSegment { segment_type: u8 },
     #[error("mismatched sub-sections (expected {expected}, found {found})")]
     SubSectionMismatch { expected: u8, found: u8 },
+    /// The section is listed in the GPP string header but has no content, for example in
+    /// `DBACNY~~1YNN`.
+    ///
+    /// This is reported when decoding the section. Checking the same string with
+    /// [`GPPString::validate_structure`](crate::v1::GPPString::validate_structure) fails with
+    /// [`GPPDecodeError::EmptySection`](crate::v1::GPPDecodeError::EmptySection) instead.
+    #[error("empty fragment for section {id}")]
+    EmptyFragment { id: SectionId },
+    #[error("section too large (ranges add up to more than {max} ids)")]
+    SectionTooLarge { max: usize },
 }
 
 #[derive(Debug, Eq, PartialEq)]
@@ -217,53 +915,211 @@ impl Section {
             Section::UsRi(_) => SectionId::UsRi,
         }
     }
+
+    /// Checks the decoded field values of this section for semantic issues.
+    ///
+    /// TCF sections check their timestamps, CMP id, and consent screen and language (see
+    /// [`validate_consent_metadata`]), and US sections check that their MSPA modes are
+    /// consistent with each other and with the covered transaction flag. Other sections have no
+    /// validation yet and always return an empty list.
+    pub fn validate(&self) -> Vec<ValidationWarning> {
+        macro_rules! mspa {
+            ($core:expr) => {
+                validation::validate_mspa(
+                    $core.mspa_covered_transaction,
+                    &$core.mspa_opt_out_option_mode,
+                    &$core.mspa_service_provider_mode,
+                )
+            };
+            (mode $core:expr) => {
+                validation::validate_mspa_mode($core.mspa_covered_transaction, &$core.mspa_mode)
+            };
+        }
+
+        match self {
+            Section::TcfEuV1(s) => s.validate(),
+            Section::TcfEuV2(s) => s.validate(),
+            Section::TcfCaV1(s) => s.validate(),
+            Section::UsNat(s) => match &s.core {
+                usnat::Core::V1(core) => mspa!(core),
+                usnat::Core::V2(core) => mspa!(core),
+            },
+            Section::UsCa(s) => mspa!(s.core),
+            Section::UsVa(s) => mspa!(s.core),
+            Section::UsCo(s) => mspa!(s.core),
+            Section::UsUt(s) => mspa!(s.core),
+            Section::UsCt(s) => mspa!(s.core),
+            Section::UsFl(s) => mspa!(s.core),
+            Section::UsMt(s) => mspa!(s.core),
+            Section::UsOr(s) => mspa!(s.core),
+            Section::UsTx(s) => mspa!(s.core),
+            Section::UsDe(s) => mspa!(s.core),
+            Section::UsIa(s) => mspa!(s.core),
+            Section::UsNe(s) => mspa!(s.core),
+            Section::UsNh(s) => mspa!(s.core),
+            Section::UsNj(s) => mspa!(s.core),
+            Section::UsTn(s) => mspa!(s.core),
+            Section::UsMn(s) => mspa!(s.core),
+            Section::UsMd(s) => mspa!(mode s.core),
+            Section::UsIn(s) => mspa!(mode s.core),
+            Section::UsKy(s) => mspa!(mode s.core),
+            Section::UsRi(s) => mspa!(mode s.core),
+            _ => vec![],
+        }
+    }
+
+    /// Returns the Global Privacy Control flag carried by this section.
+    ///
+    /// Returns `None` for sections which have no GPC segment, and for US sections where the
+    /// segment is absent.
+    pub fn gpc(&self) -> Option<bool> {
+        match self {
+            Section::UsNat(s) => s.gpc,
+            Section::UsCa(s) => s.gpc,
+            Section::UsCo(s) => s.gpc,
+            Section::UsCt(s) => s.gpc,
+            Section::UsMt(s) => s.gpc,
+            Section::UsOr(s) => s.gpc,
+            Section::UsTx(s) => s.gpc,
+            Section::UsDe(s) => s.gpc,
+            Section::UsIa(s) => s.gpc,
+            Section::UsNe(s) => s.gpc,
+            Section::UsNh(s) => s.gpc,
+            Section::UsNj(s) => s.gpc,
+            Section::UsTn(s) => s.gpc,
+            Section::UsMn(s) => s.gpc,
+            Section::UsMd(s) => s.gpc,
+            _ => None,
+        }
+    }
+}
+
+/// The number of sections which can be decoded.
+const SUPPORTED_COUNT: usize = {
+    let mut count = 0;
+    let mut i = 0;
+    while i < SECTION_TABLE.len() {
+        if SECTION_TABLE[i].1 {
+            count += 1;
+        }
+        i += 1;
+    }
+    count
+};
+
+/// The sections which can be decoded, in ascending id order.
+///
+/// This must list exactly the ids handled by `decode_section`, which the tests check.
+const SUPPORTED_SECTIONS: &[SectionId] = &{
+    let mut sections = [SectionId::GppHeader; SUPPORTED_COUNT];
+    let mut count = 0;
+    let mut i = 0;
+    while i < SECTION_TABLE.len() {
+        if SECTION_TABLE[i].1 {
+            sections[count] = SECTION_TABLE[i].0;
+            count += 1;
+        }
+        i += 1;
+    }
+    sections
+};
+
+/// Returns the ids of the sections this version of the crate can decode, in ascending order.
+///
+/// Other known ids, such as the GPP header, are reported as
+/// [`SectionDecodeError::UnsupportedSectionId`] when decoded.
+///
+/// # Example
+///
+/// ```
+/// use iab_gpp::sections::SectionId;
+///
+/// assert!(iab_gpp::supported_sections().contains(&SectionId::UsNat));
+/// assert!(!iab_gpp::supported_sections().contains(&SectionId::GppHeader));
+/// ```
+pub fn supported_sections() -> &'static [SectionId] {
+    SUPPORTED_SECTIONS
 }
 
-pub(crate) fn decode_section(id: SectionId, s: &str) -> Result<Section, SectionDecodeError> {
+pub(crate) fn decode_section(
+    id: SectionId,
+    s: &str,
+    ctx: &DecodeContext,
+) -> Result<Section, SectionDecodeError> {
     Ok(match id {
-        SectionId::TcfEuV1 => Section::TcfEuV1(s.parse()?),
-        SectionId::TcfEuV2 => Section::TcfEuV2(s.parse()?),
-        SectionId::TcfCaV1 => Section::TcfCaV1(s.parse()?),
-        SectionId::UspV1 => Section::UspV1(s.parse()?),
-        SectionId::UsNat => Section::UsNat(s.parse()?),
-        SectionId::UsCa => Section::UsCa(s.parse()?),
-        SectionId::UsVa => Section::UsVa(s.parse()?),
-        SectionId::UsCo => Section::UsCo(s.parse()?),
-        SectionId::UsUt => Section::UsUt(s.parse()?),
-        SectionId::UsCt => Section::UsCt(s.parse()?),
-        SectionId::UsFl => Section::UsFl(s.parse()?),
-        SectionId::UsMt => Section::UsMt(s.parse()?),
-        SectionId::UsOr => Section::UsOr(s.parse()?),
-        SectionId::UsTx => Section::UsTx(s.parse()?),
-        SectionId::UsDe => Section::UsDe(s.parse()?),
-        SectionId::UsIa => Section::UsIa(s.parse()?),
-        SectionId::UsNe => Section::UsNe(s.parse()?),
-        SectionId::UsNh => Section::UsNh(s.parse()?),
-        SectionId::UsNj => Section::UsNj(s.parse()?),
-        SectionId::UsTn => Section::UsTn(s.parse()?),
-        SectionId::UsMn => Section::UsMn(s.parse()?),
-        SectionId::UsMd => Section::UsMd(s.parse()?),
-        SectionId::UsIn => Section::UsIn(s.parse()?),
-        SectionId::UsKy => Section::UsKy(s.parse()?),
-        SectionId::UsRi => Section::UsRi(s.parse()?),
+        SectionId::TcfEuV1 => Section::TcfEuV1(TcfEuV1::decode_str(s, ctx)?),
+        SectionId::TcfEuV2 => Section::TcfEuV2(TcfEuV2::decode_str(s, ctx)?),
+        SectionId::TcfCaV1 => Section::TcfCaV1(TcfCaV1::decode_str(s, ctx)?),
+        SectionId::UspV1 => Section::UspV1(UspV1::decode_str(s, ctx)?),
+        SectionId::UsNat => Section::UsNat(UsNat::decode_str(s, ctx)?),
+        SectionId::UsCa => Section::UsCa(UsCa::decode_str(s, ctx)?),
+        SectionId::UsVa => Section::UsVa(UsVa::decode_str(s, ctx)?),
+        SectionId::UsCo => Section::UsCo(UsCo::decode_str(s, ctx)?),
+        SectionId::UsUt => Section::UsUt(UsUt::decode_str(s, ctx)?),
+        SectionId::UsCt => Section::UsCt(UsCt::decode_str(s, ctx)?),
+        SectionId::UsFl => Section::UsFl(UsFl::decode_str(s, ctx)?),
+        SectionId::UsMt => Section::UsMt(UsMt::decode_str(s, ctx)?),
+        SectionId::UsOr => Section::UsOr(UsOr::decode_str(s, ctx)?),
+        SectionId::UsTx => Section::UsTx(UsTx::decode_str(s, ctx)?),
+        SectionId::UsDe => Section::UsDe(UsDe::decode_str(s, ctx)?),
+        SectionId::UsIa => Section::UsIa(UsIa::decode_str(s, ctx)?),
+        SectionId::UsNe => Section::UsNe(UsNe::decode_str(s, ctx)?),
+        SectionId::UsNh => Section::UsNh(UsNh::decode_str(s, ctx)?),
+        SectionId::UsNj => Section::UsNj(UsNj::decode_str(s, ctx)?),
+        SectionId::UsTn => Section::UsTn(UsTn::decode_str(s, ctx)?),
+        SectionId::UsMn => Section::UsMn(UsMn::decode_str(s, ctx)?),
+        SectionId::UsMd => Section::UsMd(UsMd::decode_str(s, ctx)?),
+        SectionId::UsIn => Section::UsIn(UsIn::decode_str(s, ctx)?),
+        SectionId::UsKy => Section::UsKy(UsKy::decode_str(s, ctx)?),
+        SectionId::UsRi => Section::UsRi(UsRi::decode_str(s, ctx)?),
         id => Err(SectionDecodeError::UnsupportedSectionId(id))?,
     })
 }
 
+/// Decodes a section, or one of its segments, from an existing bit reader.
+///
+/// This bypasses the Base64-URL transport used by GPP strings, which is useful when the bits
+/// come from another source. Only the bit layout of the first segment is read: optional
+/// segments of segmented sections are separated by `.` in the string form, so they are left
+/// as `None` and can be decoded individually.
+///
+/// # Example
+///
+/// ```
+/// use bitstream_io::{BigEndian, BitReader};
+/// use iab_gpp::sections::decode_section_from_bits;
+/// use iab_gpp::sections::usin::UsIn;
+///
+/// let bits = [0b0000_0110, 0b0000_0000, 0b0000_0000];
+/// let mut r = BitReader::endian(&bits[..], BigEndian);
+/// let section: UsIn = decode_section_from_bits(&mut r).unwrap();
+///
+/// assert_eq!(section.core.mspa_version, 1);
+/// assert!(section.core.mspa_covered_transaction);
+/// assert!(section.sensitive_data_consents.is_none());
+/// ```
+pub fn decode_section_from_bits<S>(bits: &mut impl BitRead) -> Result<S, SectionDecodeError>
+where
+    S: FromBitStream,
+    SectionDecodeError: From<S::Error>,
+{
+    Ok(S::from_reader(bits)?)
+}
+
 /// A trait representing an operation to parse segments for a Base64-URL encoded string
 /// using '.' as separators into a type composed of a mandatory core segment and an arbitrary
 /// number of optional segments.
 ///
 /// This guarantees a given segment cannot appear twice.
 pub(crate) trait SegmentedStr<T> {
-    fn parse_segmented_str(&self) -> Result<T, SectionDecodeError>;
+    fn parse_segmented_str(&self, ctx: &DecodeContext) -> Result<T, SectionDecodeError>;
 }
 
 impl<T> SegmentedStr<T> for str
 where
     T: OptionalSegmentParser,
 {
-    fn parse_segmented_str(&self) -> Result<T, SectionDecodeError> {
+    fn parse_segmented_str(&self, ctx: &DecodeContext) -> Result<T, SectionDecodeError> {
         let mut sections_iter = self.split('.');
 
         // first mandatory section is the core segment
@@ -271,16 +1127,33 @@ where
             .next()
             .ok_or_else(|| SectionDecodeError::UnexpectedEndOfString(self.to_string()))?;
 
-        let mut r = base64_bit_reader(core.as_bytes());
-        let mut output = r.parse()?;
+        let mut r = ctx.bit_reader(core);
+        let mut output = match r.parse_with(ctx) {
+            // an optional segment in first position reads as a core segment with an unknown
+            // version, report it as such if its type is a known optional segment type
+            Err(SectionDecodeError::UnknownSegmentVersion { segment_version }) => {
+                let segment_type = T::read_segment_type(&mut ctx.bit_reader(core))?;
+                return Err(if T::is_optional_segment_type(segment_type) {
+                    SectionDecodeError::MissingCoreSegment { segment_type }
+                } else {
+                    SectionDecodeError::UnknownSegmentVersion { segment_version }
+                });
+            }
+            r => r?,
+        };
 
-        // parse each optional segment and fill the output
+        // parse each optional segment and fill the output, the segment type is read from the
+        // segment itself so optional segments may appear in any order
         let mut segments = BTreeSet::new();
         for s in sections_iter {
-            let mut r = base64_bit_reader(s.as_bytes());
+            let mut r = ctx.bit_reader(s);
 
             let segment_type = T::read_segment_type(&mut r)?;
-            T::parse_optional_segment(segment_type, &mut r, &mut output)?;
+            if !T::is_optional_segment_type(segment_type) && ctx.options.skip_unknown_segments {
+                ctx.warn(DecodeWarning::UnknownSegmentSkipped { segment_type });
+                continue;
+            }
+            T::parse_optional_segment(segment_type, &mut r, &mut output, ctx)?;
 
             // already present, duplicate segments is an error
             if !segments.insert(segment_type) {
@@ -294,16 +1167,19 @@ where
 
 /// A trait representing an operation to parse optional segments for a Base64-URL encoded string
 pub(crate) trait OptionalSegmentParser:
-    FromBitStream<Error = SectionDecodeError> + Sized
+    for<'a> FromBitStreamWith<'a, Context = DecodeContext, Error = SectionDecodeError> + Sized
 {
     fn read_segment_type<R: BitRead>(r: &mut R) -> Result<u8, SectionDecodeError> {
-        Ok(r.read_unsigned::<3, u8>()?)
+        Ok(r.read_segment_type()?)
     }
 
+    fn is_optional_segment_type(segment_type: u8) -> bool;
+
     fn parse_optional_segment<R: BitRead>(
         segment_type: u8,
         r: &mut R,
         into: &mut Self,
+        ctx: &DecodeContext,
     ) -> Result<(), SectionDecodeError>;
 }
 
@@ -313,14 +1189,14 @@ pub(crate) trait OptionalSegmentParser:
 ///
 /// This guarantees a given segment cannot appear twice and that proper order is respected.
 pub(crate) trait SegmentedStrWithHeader<T> {
-    fn parse_segmented_str(&self) -> Result<T, SectionDecodeError>;
+    fn parse_segmented_str(&self, ctx: &DecodeContext) -> Result<T, SectionDecodeError>;
 }
 
 impl<T> SegmentedStrWithHeader<T> for str
 where
     T: OptionalSubSectionParser,
 {
-    fn parse_segmented_str(&self) -> Result<T, SectionDecodeError> {
+    fn parse_segmented_str(&self, ctx: &DecodeContext) -> Result<T, SectionDecodeError> {
         let mut sections_iter = self.split('.');
 
         // first comes the header
@@ -328,7 +1204,7 @@ where
             .next()
             .ok_or_else(|| SectionDecodeError::UnexpectedEndOfString(self.to_string()))?;
 
-        let mut r = base64_bit_reader(header.as_bytes());
+        let mut r = ctx.bit_reader(header);
         let _section_id = r.read_unsigned::<6, u8>()?; // not used at the time, as we don't have a reference to our own ID
         let _version = r.read_unsigned::<6, u8>()?; // not used at the time, will be improved to handle multiple versions
         let sub_sections = r.read_fibonacci_range::<u8>()?;
@@ -343,13 +1219,13 @@ where
             .next()
             .ok_or_else(|| SectionDecodeError::UnexpectedEndOfString(self.to_string()))?;
 
-        let mut r = base64_bit_reader(core.as_bytes());
-        let mut output = r.parse()?;
+        let mut r = ctx.bit_reader(core);
+        let mut output = r.parse_with(ctx)?;
 
         // parse each optional segment and fill the output
         let mut segments = BTreeSet::new();
         for (s, &id) in sections_iter.zip(sub_sections[..sub_sections.len() - 1].iter()) {
-            let mut r = base64_bit_reader(s.as_bytes());
+            let mut r = ctx.bit_reader(s);
 
             let sub_section_id = r.read_unsigned::<2, u8>()?;
             if sub_section_id != id {
@@ -359,7 +1235,7 @@ where
                 });
             }
 
-            T::parse_optional_sub_section(sub_section_id, &mut r, &mut output)?;
+            T::parse_optional_sub_section(sub_section_id, &mut r, &mut output, ctx)?;
 
             // already present, duplicate segments is an error
             if !segments.insert(sub_section_id) {
@@ -378,11 +1254,220 @@ where
 /// This trait is used for the new style of GPP sections introduced for US States starting with
 /// Maryland.
 pub(crate) trait OptionalSubSectionParser:
-    FromBitStream<Error = SectionDecodeError> + Sized
+    for<'a> FromBitStreamWith<'a, Context = DecodeContext, Error = SectionDecodeError> + Sized
 {
     fn parse_optional_sub_section<R: BitRead>(
         segment_type: u8,
         r: &mut R,
         into: &mut Self,
+        ctx: &DecodeContext,
     ) -> Result<(), SectionDecodeError>;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::base64_bit_reader;
+    use iab_gpp_derive::FromBitStream;
+    use test_case::test_case;
+
+    #[test_case("EN" => "EN" ; "letters")]
+    #[test_case("Za" => "Z[" ; "past z")]
+    #[test_case("__" => "\u{80}\u{80}" ; "all bits")]
+    fn two_letter_code(s: &str) -> String {
+        let code: TwoLetterCode = crate::core::base64_bit_reader(s.as_bytes())
+            .parse()
+            .unwrap();
+        code.to_string()
+    }
+
+    #[derive(Debug, Eq, PartialEq, FromBitStream)]
+    #[gpp(section_version = 2, version_bits = 3)]
+    struct NarrowVersion {
+        pub flag: bool,
+    }
+
+    #[test_case("QA" => matches Ok(NarrowVersion { flag: false }) ; "version 2")]
+    #[test_case("UA" => matches Ok(NarrowVersion { flag: true }) ; "version 2 with flag")]
+    #[test_case("YA" => matches Err(SectionDecodeError::UnknownSegmentVersion { segment_version: 3 }) ; "version 3")]
+    fn non_default_version_bits(s: &str) -> Result<NarrowVersion, SectionDecodeError> {
+        base64_bit_reader(s.as_bytes()).parse()
+    }
+
+    #[derive(Debug, Eq, PartialEq, FromBitStream)]
+    struct ConditionalField {
+        pub flag: bool,
+        #[gpp(present_if = flag)]
+        pub value: Option<u8>,
+        pub tail: bool,
+    }
+
+    // "iw" is 1 000101 1, "Q" is 0 1
+    #[test_case("iw" => matches Ok(ConditionalField { flag: true, value: Some(5), tail: true }) ; "present")]
+    #[test_case("Q" => matches Ok(ConditionalField { flag: false, value: None, tail: true }) ; "absent")]
+    fn present_if(s: &str) -> Result<ConditionalField, SectionDecodeError> {
+        base64_bit_reader(s.as_bytes()).parse()
+    }
+
+    #[derive(Debug, Eq, PartialEq, FromBitStream)]
+    struct RangeIds {
+        #[gpp(optimized_integer_range)]
+        pub ids: IdSet,
+    }
+
+    // max id 2, then a range entry from 0 to 2
+    const ZERO_RANGE: &str = "AAKADAAAAAg";
+
+    #[test]
+    fn zero_id_in_field_is_kept_with_a_warning() {
+        let ctx = DecodeContext::default();
+        let r: RangeIds = ctx.bit_reader(ZERO_RANGE).parse_with(&ctx).unwrap();
+
+        assert_eq!(r.ids, IdSet::from([0, 1, 2]));
+        assert_eq!(ctx.into_warnings(), [DecodeWarning::ZeroId]);
+    }
+
+    #[test]
+    fn zero_id_in_field_is_rejected() {
+        let ctx = DecodeContext::new(DecodeOptions::new().with_reject_zero_ids());
+        let r: Result<RangeIds, _> = ctx.bit_reader(ZERO_RANGE).parse_with(&ctx);
+
+        assert!(matches!(
+            r,
+            Err(SectionDecodeError::Read { source }) if source.kind() == io::ErrorKind::InvalidData
+        ));
+    }
+
+    #[test_case(SectionId::TcfEuV2 => (Jurisdiction::Eu, "TCF EU v2") ; "tcf eu v2")]
+    #[test_case(SectionId::TcfCaV1 => (Jurisdiction::Canada, "TCF Canada v1") ; "tcf ca v1")]
+    #[test_case(SectionId::GppHeader => (Jurisdiction::Global, "GPP Header") ; "header")]
+    #[test_case(SectionId::UspV1 => (Jurisdiction::UsNational, "US Privacy v1") ; "usp v1")]
+    #[test_case(SectionId::UsNat => (Jurisdiction::UsNational, "US National") ; "us nat")]
+    #[test_case(SectionId::UsRi => (Jurisdiction::UsState("RI"), "US Rhode Island") ; "us ri")]
+    fn section_metadata(id: SectionId) -> (Jurisdiction, &'static str) {
+        (id.jurisdiction(), id.display_name())
+    }
+
+    #[test]
+    fn section_id_primitive_conversions() {
+        for value in 0..=u8::MAX {
+            let id = SectionId::from_u8(value);
+            assert_eq!(id.is_some(), (1..=27).contains(&value), "{value}");
+            assert_eq!(id.map(|id| id as u8), id.map(|_| value));
+        }
+        assert_eq!(
+            SectionId::from_u8(usri::UsRi::ID as u8),
+            Some(SectionId::UsRi)
+        );
+    }
+
+    #[test]
+    fn state_sections_are_named_after_their_state() {
+        for id in (0..=u8::MAX).filter_map(SectionId::from_u8) {
+            if let Jurisdiction::UsState(state) = id.jurisdiction() {
+                assert_eq!(id.name(), format!("us{}", state.to_lowercase()));
+            }
+        }
+    }
+
+    #[test]
+    fn supported_sections_match_decode_dispatch() {
+        assert!(supported_sections().is_sorted_by_key(|&id| id as u8));
+        for id in (0..=u8::MAX).filter_map(SectionId::from_u8) {
+            let unsupported = matches!(
+                decode_section(id, "", &DecodeContext::default()),
+                Err(SectionDecodeError::UnsupportedSectionId(_))
+            );
+            assert_eq!(supported_sections().contains(&id), !unsupported, "{id}");
+        }
+    }
+
+    fn assert_parsable<S>()
+    where
+        S: DecodableSection + for<'a> TryFrom<&'a str, Error = SectionDecodeError>,
+    {
+    }
+
+    #[test]
+    fn every_section_parses_from_str() {
+        assert_parsable::<TcfEuV1>();
+        assert_parsable::<TcfEuV2>();
+        assert_parsable::<TcfCaV1>();
+        assert_parsable::<UspV1>();
+        assert_parsable::<UsNat>();
+        assert_parsable::<UsCa>();
+        assert_parsable::<UsVa>();
+        assert_parsable::<UsCo>();
+        assert_parsable::<UsUt>();
+        assert_parsable::<UsCt>();
+        assert_parsable::<UsFl>();
+        assert_parsable::<UsMt>();
+        assert_parsable::<UsOr>();
+        assert_parsable::<UsTx>();
+        assert_parsable::<UsDe>();
+        assert_parsable::<UsIa>();
+        assert_parsable::<UsNe>();
+        assert_parsable::<UsNh>();
+        assert_parsable::<UsNj>();
+        assert_parsable::<UsTn>();
+        assert_parsable::<UsMn>();
+        assert_parsable::<UsMd>();
+        assert_parsable::<UsIn>();
+        assert_parsable::<UsKy>();
+        assert_parsable::<UsRi>();
+
+        assert_eq!(
+            "BVVVVVVV".parse::<UsVa>().ok(),
+            UsVa::try_from("BVVVVVVV").ok()
+        );
+    }
+
+    fn error_chain(e: &dyn std::error::Error) -> Vec<String> {
+        std::iter::successors(Some(e), |e| e.source())
+            .map(|e| e.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn read_error_source() {
+        let e =
+            decode_section(SectionId::TcfEuV2, "CPXxRf", &DecodeContext::default()).unwrap_err();
+
+        let source = std::error::Error::source(&e)
+            .and_then(|s| s.downcast_ref::<io::Error>())
+            .expect("io error source");
+        assert_eq!(source.kind(), io::ErrorKind::UnexpectedEof);
+        assert_eq!(
+            error_chain(&e),
+            [
+                "unable to read section: failed to fill whole buffer",
+                "failed to fill whole buffer",
+            ]
+        );
+    }
+
+    // US Maryland core segments with the opt-out option mode set, for a transaction not covered
+    // by the MSPA then for a covered one
+    #[test_case("BIAA" => 1 ; "not covered")]
+    #[test_case("BoAA" => 0 ; "covered")]
+    fn validate_us_md_mspa_mode(core: &str) -> usize {
+        let core: usmd::Core = base64_bit_reader(core.as_bytes()).parse().unwrap();
+        assert_eq!(core.mspa_mode, us_common::MspaMode::OptOutOption);
+
+        let warnings = Section::UsMd(usmd::UsMd { core, gpc: None }).validate();
+        assert!(
+            warnings
+                .iter()
+                .all(|w| matches!(w, ValidationWarning::MspaModeWithoutCoveredTransaction))
+        );
+        warnings.len()
+    }
+
+    #[test_case(SectionDecodeError::MissingSection(SectionId::UspV1) ; "missing section")]
+    #[test_case(SectionDecodeError::UnexpectedEndOfString("1Y".to_string()) ; "end of string")]
+    #[test_case(SectionDecodeError::UnknownSegmentType { segment_type: 7 } ; "segment type")]
+    #[test_case(SectionDecodeError::EmptyFragment { id: SectionId::TcfEuV2 } ; "empty fragment")]
+    fn errors_without_source(e: SectionDecodeError) {
+        assert!(std::error::Error::source(&e).is_none());
+    }
+}
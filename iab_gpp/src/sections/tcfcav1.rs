@@ -1,14 +1,19 @@
-use crate::core::{DataRead, GenericRange};
-use crate::sections::{IdSet, SectionDecodeError};
-use bitstream_io::{BitRead, FromBitStream};
+use crate::core::{DataRead, GenericRange, base64_bit_reader, check_range_count};
+use crate::sections::{
+    DecodeContext, DecodeWarning, IdSet, SectionDecodeError, TwoLetterCode, ValidationWarning,
+    validation,
+};
+use bitstream_io::{BitRead, FromBitStream, FromBitStreamWith};
 use iab_gpp_derive::{FromBitStream, GPPSection};
 use num_derive::FromPrimitive;
 use num_traits::FromPrimitive;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 #[derive(Debug, Eq, PartialEq, GPPSection)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "test-util", derive(Default))]
 #[non_exhaustive]
 #[gpp(with_optional_segments)]
 pub struct TcfCaV1 {
@@ -19,8 +24,74 @@ pub struct TcfCaV1 {
     pub publisher_purposes: Option<PublisherPurposes>,
 }
 
+impl TcfCaV1 {
+    /// Decodes a standalone IAB Canada TCF consent string, as emitted outside of GPP by CMPs
+    /// predating it.
+    ///
+    /// Standalone strings share the segment framing of the GPP section, but their core segment
+    /// usually carries version 2 (see [`CoreSegmentVersion::V2`]). Surrounding whitespace, which
+    /// commonly sneaks in when strings are read from cookies or logs, is ignored.
+    ///
+    /// There is no `to_ca_string` counterpart: sections cannot be encoded yet, only their id sets
+    /// (see [`DataWrite`](crate::DataWrite)), and the decoded section does not keep the string it
+    /// was decoded from. Pipelines handling both formats can keep the original string alongside.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::tcfcav1::TcfCaV1;
+    ///
+    /// let tcf = TcfCaV1::from_ca_string("CPuy0IAPuy0IAPoABABGCyCAAAAAAAAAAAAAAAAA").unwrap();
+    /// assert_eq!(tcf.core.cmp_id, 1000);
+    /// ```
+    pub fn from_ca_string(s: &str) -> Result<Self, SectionDecodeError> {
+        s.trim().parse()
+    }
+
+    /// Reads the creation timestamp of a TCF CA string, in seconds since the Unix epoch,
+    /// without decoding the rest of the string.
+    ///
+    /// Only the version of the core segment is checked, the rest of the string may be invalid.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::tcfcav1::TcfCaV1;
+    ///
+    /// let created = TcfCaV1::peek_created("BPuy0IAPuy0IAPoABABGCyCAAAAAAAAAAAAAAAAA").unwrap();
+    /// assert_eq!(created, 1689120000);
+    /// ```
+    pub fn peek_created(s: &str) -> Result<u64, SectionDecodeError> {
+        let core = s.split('.').next().unwrap_or_default();
+        let mut r = base64_bit_reader(core.as_bytes());
+
+        let segment_version = r.read_unsigned::<6, u8>()?;
+        if CoreSegmentVersion::from_u6(segment_version).is_none() {
+            return Err(SectionDecodeError::UnknownSegmentVersion { segment_version });
+        }
+        Ok(r.read_datetime_as_unix_timestamp()?)
+    }
+
+    /// Checks the core segment for semantic issues, see [`Section::validate`].
+    ///
+    /// [`Section::validate`]: crate::sections::Section::validate
+    pub fn validate(&self) -> Vec<ValidationWarning> {
+        let mut warnings = validation::validate_tcf_metadata(
+            self.core.created,
+            self.core.last_updated,
+            self.core.cmp_id,
+        );
+        warnings.extend(validation::validate_consent_metadata(
+            self.core.consent_screen,
+            self.core.consent_language,
+        ));
+        warnings
+    }
+}
+
 #[derive(Debug, Eq, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "test-util", derive(Default))]
 #[non_exhaustive]
 pub struct Core {
     pub segment_version: u8,
@@ -29,7 +100,7 @@ pub struct Core {
     pub cmp_id: u16,
     pub cmp_version: u16,
     pub consent_screen: u8,
-    pub consent_language: String,
+    pub consent_language: TwoLetterCode,
     pub vendor_list_version: u16,
     pub policy_version: u8,
     pub use_non_standard_stacks: bool,
@@ -39,6 +110,52 @@ pub struct Core {
     pub vendor_express_consents: IdSet,
     pub vendor_implied_consents: IdSet,
     pub pub_restrictions: Vec<PublisherRestriction>,
+    /// Revision of the specification the core segment was encoded with, as inferred from the
+    /// presence of publisher restrictions.
+    ///
+    /// Neither the section nor the segment carries the revision, so a v1.1 string is detected
+    /// when 12 more bits, the restriction count, can be read after the vendor implied consents.
+    /// The padding of a v1.0 string can be long enough to hold them: an encoder which pads the
+    /// segment to a whole number of bytes before encoding it to base64 may leave up to 7 padding
+    /// bits, and the last base64 character up to 4 more, which the decoder rounds up to a whole
+    /// byte. Such a v1.0 string is reported as v1.1 with no restrictions.
+    pub sub_version: SubVersion,
+}
+
+impl Core {
+    /// Returns `true` if the core segment carries publisher restrictions, even an empty list,
+    /// as introduced in TCF CA v1.1.
+    ///
+    /// When this returns `false`, [`pub_restrictions`](Core::pub_restrictions) is empty because
+    /// the string predates restrictions, not because the publisher declared none. The opposite
+    /// does not hold: some v1.0 strings have enough padding to be read as v1.1 strings with an
+    /// empty list, see [`sub_version`](Core::sub_version).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::tcfcav1::TcfCaV1;
+    ///
+    /// let v1_0 = TcfCaV1::from_ca_string("BPuy0IAPuy0IAPoABABGCyCAAAAAAAAAAAAAAAAA").unwrap();
+    /// assert!(!v1_0.core.has_publisher_restrictions_support());
+    ///
+    /// let v1_1 = TcfCaV1::from_ca_string("BPuy0IAPuy0IAPoABABGCyCAAAAAAAAAAAAAAAAAAA").unwrap();
+    /// assert!(v1_1.core.has_publisher_restrictions_support());
+    /// ```
+    pub fn has_publisher_restrictions_support(&self) -> bool {
+        self.sub_version >= SubVersion::V1_1
+    }
+
+    /// Returns the publisher restrictions grouped by purpose id, in ascending purpose order.
+    ///
+    /// Restrictions keep the order in which they appear in the string within each purpose.
+    pub fn restrictions_by_purpose(&self) -> BTreeMap<u8, Vec<&PublisherRestriction>> {
+        let mut by_purpose = BTreeMap::<u8, Vec<_>>::new();
+        for r in &self.pub_restrictions {
+            by_purpose.entry(r.purpose_id).or_default().push(r);
+        }
+        by_purpose
+    }
 }
 
 #[derive(Debug, Eq, PartialEq, FromBitStream)]
@@ -51,8 +168,7 @@ struct CoreData {
     pub cmp_id: u16,
     pub cmp_version: u16,
     pub consent_screen: u8,
-    #[gpp(string(2))]
-    pub consent_language: String,
+    pub consent_language: TwoLetterCode,
     pub vendor_list_version: u16,
     pub policy_version: u8,
     pub use_non_standard_stacks: bool,
@@ -70,21 +186,69 @@ struct CoreData {
     pub vendor_implied_consents: IdSet,
     /// Introduced in TCF CA v1.1
     #[gpp(parse_with = parse_publisher_restrictions)]
-    pub pub_restrictions: Vec<PublisherRestriction>,
+    pub pub_restrictions: Option<Vec<PublisherRestriction>>,
+}
+
+/// Core segment versions accepted when decoding a TCF CA string.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CoreSegmentVersion {
+    /// The version defined by the TCF CA v1 specification.
+    V1 = 1,
+    /// Not part of the specification, but strings in the wild (and IAB's own decoder) use
+    /// version 2. The payload layout remains compatible for the fields we decode.
+    V2 = 2,
+}
+
+impl CoreSegmentVersion {
+    /// Maps the 6-bit segment version read from a core segment to a known version.
+    ///
+    /// Returns `None` for any version other than 1 or 2.
+    pub fn from_u6(value: u8) -> Option<Self> {
+        match value {
+            1 => Some(Self::V1),
+            2 => Some(Self::V2),
+            _ => None,
+        }
+    }
+}
+
+/// Revisions of the TCF CA v1 specification, which share the same section and segment versions.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum SubVersion {
+    /// TCF CA v1.0, whose core segment ends after the vendor implied consents.
+    #[default]
+    V1_0,
+    /// TCF CA v1.1, which appends publisher restrictions to the core segment.
+    V1_1,
 }
 
 impl FromBitStream for Core {
     type Error = SectionDecodeError;
 
     fn from_reader<R: BitRead + ?Sized>(r: &mut R) -> Result<Self, Self::Error> {
-        // In the wild (and in IAB's own decoder), TCF CA core appears with segment version 2.
-        // The payload layout remains compatible for the fields we decode.
+        r.parse_with(&DecodeContext::default())
+    }
+}
+
+impl FromBitStreamWith<'_> for Core {
+    type Context = DecodeContext;
+    type Error = SectionDecodeError;
+
+    fn from_reader<R: BitRead + ?Sized>(
+        r: &mut R,
+        ctx: &DecodeContext,
+    ) -> Result<Self, Self::Error> {
         let segment_version = r.read_unsigned::<6, u8>()?;
-        if segment_version != 1 && segment_version != 2 {
-            return Err(SectionDecodeError::UnknownSegmentVersion { segment_version });
+        match CoreSegmentVersion::from_u6(segment_version) {
+            Some(CoreSegmentVersion::V1) => {}
+            Some(CoreSegmentVersion::V2) => {
+                ctx.warn(DecodeWarning::NonStandardSegmentVersion { segment_version })
+            }
+            None => return Err(SectionDecodeError::UnknownSegmentVersion { segment_version }),
         }
 
-        let data: CoreData = r.parse()?;
+        let data: CoreData = r.parse_with(ctx)?;
         Ok(Self {
             segment_version,
             created: data.created,
@@ -101,28 +265,50 @@ impl FromBitStream for Core {
             purpose_implied_consents: data.purpose_implied_consents,
             vendor_express_consents: data.vendor_express_consents,
             vendor_implied_consents: data.vendor_implied_consents,
-            pub_restrictions: data.pub_restrictions,
+            sub_version: if data.pub_restrictions.is_some() {
+                SubVersion::V1_1
+            } else {
+                SubVersion::V1_0
+            },
+            pub_restrictions: data.pub_restrictions.unwrap_or_default(),
         })
     }
 }
 
+/// Publisher restrictions were introduced in TCF CA v1.1, so a string that ends before the
+/// restriction count is a v1.0 string, and decodes to `None`. A v1.0 string whose padding holds
+/// 12 bits decodes to an empty list instead, as the padding cannot be told apart from a count
+/// of 0. Once the count has been read,
+/// any failure to read the declared restrictions is reported rather than dropping them.
+///
+/// The count is checked against [`DecodeOptions::max_ranges`] before reading any restriction.
 fn parse_publisher_restrictions<R: BitRead + ?Sized>(
     mut r: &mut R,
-) -> Result<Vec<PublisherRestriction>, SectionDecodeError> {
-    Ok(r.read_n_array_of_ranges(6, 2)
-        .unwrap_or_default()
-        .into_iter()
-        .map(|r| PublisherRestriction {
-            purpose_id: r.key,
-            restriction_type: RestrictionType::from_u8(r.range_type)
+    ctx: &DecodeContext,
+) -> Result<Option<Vec<PublisherRestriction>>, SectionDecodeError> {
+    let n = match r.read_unsigned::<12, u16>() {
+        Ok(n) => n as usize,
+        Err(source) if source.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(source) => return Err(SectionDecodeError::Read { source }),
+    };
+    check_range_count(n, ctx.options.max_ranges_or_unbounded())?;
+
+    let mut restrictions = Vec::with_capacity(n);
+    for _ in 0..n {
+        restrictions.push(PublisherRestriction {
+            purpose_id: r.read_unsigned::<6, u8>()?,
+            restriction_type: RestrictionType::from_u8(r.read_unsigned::<2, u8>()?)
                 .unwrap_or(RestrictionType::Undefined),
-            restricted_vendor_ids: r.ids,
-        })
-        .collect())
+            restricted_vendor_ids: ctx.check_ids(r.read_optimized_range()?)?,
+        });
+    }
+
+    Ok(Some(restrictions))
 }
 
 #[derive(Debug, Eq, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "test-util", derive(Default))]
 pub struct PublisherRestriction {
     pub purpose_id: u8,
     pub restriction_type: RestrictionType,
@@ -142,15 +328,42 @@ impl From<GenericRange<u8, u8>> for PublisherRestriction {
 
 #[derive(Debug, Eq, PartialEq, FromPrimitive)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "test-util", derive(Default))]
 pub enum RestrictionType {
     NotAllowed = 0,
     RequireExpressConsent = 1,
     RequireImpliedConsent = 2,
+    #[cfg_attr(feature = "test-util", default)]
     Undefined = 3,
 }
 
+impl RestrictionType {
+    /// Returns `true` if processing under the given legal basis survives this restriction.
+    ///
+    /// [`NotAllowed`](RestrictionType::NotAllowed) permits no legal basis, the `Require*`
+    /// restrictions only permit their own kind of consent, and
+    /// [`Undefined`](RestrictionType::Undefined) permits any.
+    pub fn permits(&self, basis: LegalBasis) -> bool {
+        match self {
+            RestrictionType::NotAllowed => false,
+            RestrictionType::RequireExpressConsent => basis == LegalBasis::ExpressConsent,
+            RestrictionType::RequireImpliedConsent => basis == LegalBasis::ImpliedConsent,
+            RestrictionType::Undefined => true,
+        }
+    }
+}
+
+/// The kind of consent under which a vendor processes data for a purpose.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum LegalBasis {
+    ExpressConsent,
+    ImpliedConsent,
+}
+
 #[derive(Debug, Eq, PartialEq, FromBitStream)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "test-util", derive(Default))]
 #[non_exhaustive]
 pub struct PublisherPurposes {
     #[gpp(fixed_bitfield(24))]
@@ -166,15 +379,186 @@ pub struct PublisherPurposes {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::sections::DecodeOptions;
+    use bitstream_io::{BigEndian, BitReader};
     use std::str::FromStr;
     use test_case::test_case;
 
+    #[test_case(0 => RestrictionType::NotAllowed)]
+    #[test_case(1 => RestrictionType::RequireExpressConsent)]
+    #[test_case(2 => RestrictionType::RequireImpliedConsent)]
+    #[test_case(3 => RestrictionType::Undefined)]
+    #[test_case(4 => RestrictionType::Undefined ; "out of range")]
+    #[test_case(u8::MAX => RestrictionType::Undefined ; "max")]
+    fn restriction_type_from_u8(value: u8) -> RestrictionType {
+        RestrictionType::from_u8(value).unwrap_or(RestrictionType::Undefined)
+    }
+
     #[test_case("BPX" => matches SectionDecodeError::Read { .. } ; "decode error")]
     #[test_case("" => matches SectionDecodeError::Read { .. } ; "empty string")]
+    #[test_case("YAAAAAAAAAA" => matches SectionDecodeError::MissingCoreSegment { segment_type: 3 } ; "publisher purposes only")]
     fn error(s: &str) -> SectionDecodeError {
         TcfCaV1::from_str(s).unwrap_err()
     }
 
+    #[test_case("BPuy0IAPuy0IAPoABABGCyCAAAAAAAAAAAAAAAAA" => matches Ok(1689120000) ; "version 1")]
+    #[test_case("CPuy0IAPuy0IAPoABABGCyCAAAAAAAAAAAAAAAAA" => matches Ok(1689120000) ; "version 2")]
+    #[test_case("DPuy0IAPuy0IAPoABABGCyCAAAAAAAAAAAAAAAAA" => matches Err(SectionDecodeError::UnknownSegmentVersion { segment_version: 3 }) ; "version 3")]
+    #[test_case("BPuy" => matches Err(SectionDecodeError::Read { .. }) ; "truncated")]
+    fn peek_created(s: &str) -> Result<u64, SectionDecodeError> {
+        TcfCaV1::peek_created(s)
+    }
+
+    #[test_case(0 => None)]
+    #[test_case(1 => Some(CoreSegmentVersion::V1))]
+    #[test_case(2 => Some(CoreSegmentVersion::V2))]
+    #[test_case(3 => None)]
+    fn core_segment_version(v: u8) -> Option<CoreSegmentVersion> {
+        CoreSegmentVersion::from_u6(v)
+    }
+
+    #[test_case("APuy0IAPuy0IAPoABABGCyCAAAAAAAAAAAAAAAAA" => matches Err(SectionDecodeError::UnknownSegmentVersion { segment_version: 0 }) ; "version 0")]
+    #[test_case("BPuy0IAPuy0IAPoABABGCyCAAAAAAAAAAAAAAAAA" => matches Ok(1) ; "version 1")]
+    #[test_case("CPuy0IAPuy0IAPoABABGCyCAAAAAAAAAAAAAAAAA" => matches Ok(2) ; "version 2")]
+    #[test_case("DPuy0IAPuy0IAPoABABGCyCAAAAAAAAAAAAAAAAA" => matches Err(SectionDecodeError::UnknownSegmentVersion { segment_version: 3 }) ; "version 3")]
+    fn core_segment_version_decode(s: &str) -> Result<u8, SectionDecodeError> {
+        TcfCaV1::from_str(s).map(|s| s.core.segment_version)
+    }
+
+    #[test_case(&[] => Ok(None) ; "absent in v1.0")]
+    #[test_case(&[0b0000_0000] => Ok(None) ; "partial count")]
+    #[test_case(&[0b0000_0000, 0b0000_0000] => Ok(Some(vec![])) ; "zero count")]
+    #[test_case(&[0b0000_0000, 0b0001_0000, 0b0110_0000, 0b0000_0000, 0b0000_1100] => Ok(Some(vec![PublisherRestriction {
+        purpose_id: 1,
+        restriction_type: RestrictionType::RequireImpliedConsent,
+        restricted_vendor_ids: IdSet::from([1]),
+    }])) ; "single restriction")]
+    fn publisher_restrictions(bytes: &[u8]) -> Result<Option<Vec<PublisherRestriction>>, String> {
+        parse_publisher_restrictions(
+            &mut BitReader::endian(bytes, BigEndian),
+            &DecodeContext::default(),
+        )
+        .map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn publisher_restrictions_over_max_ranges() {
+        // declares 4095 restrictions, none of which are present
+        let bytes = [0b1111_1111, 0b1111_0000];
+        let ctx = DecodeContext::new(DecodeOptions::new().with_max_ranges(16));
+        let e = parse_publisher_restrictions(&mut BitReader::endian(&bytes[..], BigEndian), &ctx);
+        assert!(
+            matches!(&e, Err(SectionDecodeError::Read { source }) if source.kind() == std::io::ErrorKind::InvalidData),
+            "got {e:?}"
+        );
+    }
+
+    #[test_case(RestrictionType::NotAllowed, LegalBasis::ExpressConsent => false)]
+    #[test_case(RestrictionType::NotAllowed, LegalBasis::ImpliedConsent => false)]
+    #[test_case(RestrictionType::RequireExpressConsent, LegalBasis::ExpressConsent => true)]
+    #[test_case(RestrictionType::RequireExpressConsent, LegalBasis::ImpliedConsent => false)]
+    #[test_case(RestrictionType::RequireImpliedConsent, LegalBasis::ExpressConsent => false)]
+    #[test_case(RestrictionType::RequireImpliedConsent, LegalBasis::ImpliedConsent => true)]
+    #[test_case(RestrictionType::Undefined, LegalBasis::ExpressConsent => true)]
+    #[test_case(RestrictionType::Undefined, LegalBasis::ImpliedConsent => true)]
+    fn restriction_permits(restriction_type: RestrictionType, basis: LegalBasis) -> bool {
+        restriction_type.permits(basis)
+    }
+
+    #[test]
+    fn publisher_restriction_from_range() {
+        let restriction = PublisherRestriction::from(GenericRange::new(1, 2, IdSet::from([5])));
+        assert_eq!(
+            restriction,
+            PublisherRestriction {
+                purpose_id: 1,
+                restriction_type: RestrictionType::RequireImpliedConsent,
+                restricted_vendor_ids: IdSet::from([5]),
+            }
+        );
+    }
+
+    #[test]
+    fn restrictions_by_purpose() {
+        let mut tcf = TcfCaV1::from_str("BPuy0IAPuy0IAPoABABGCyCAAAAAAAAAAAAAAAAA").unwrap();
+        tcf.core.pub_restrictions = vec![
+            PublisherRestriction::from(GenericRange::new(2, 0, IdSet::from([5]))),
+            PublisherRestriction::from(GenericRange::new(1, 1, IdSet::from([6]))),
+            PublisherRestriction::from(GenericRange::new(2, 2, IdSet::from([7]))),
+        ];
+        let by_purpose = tcf.core.restrictions_by_purpose();
+        let restrictions = &tcf.core.pub_restrictions;
+
+        assert_eq!(by_purpose.len(), 2);
+        assert_eq!(by_purpose[&1], [&restrictions[1]]);
+        assert_eq!(by_purpose[&2], [&restrictions[0], &restrictions[2]]);
+    }
+
+    #[test_case(&[0b0000_0000, 0b0001_0000, 0b0100_0000] ; "truncated vendor ids")]
+    #[test_case(&[0b0000_0000, 0b0010_0000, 0b0110_0000, 0b0000_0000, 0b0000_1100] ; "missing second restriction")]
+    fn truncated_publisher_restrictions(bytes: &[u8]) {
+        let e = parse_publisher_restrictions(
+            &mut BitReader::endian(bytes, BigEndian),
+            &DecodeContext::default(),
+        )
+        .unwrap_err();
+        assert!(matches!(e, SectionDecodeError::Read { .. }), "got {e:?}");
+    }
+
+    // the TCF CA section of a GPP string emitted by a Canadian CMP, with its core and publisher
+    // purposes segments, and the same string with the core segment version 2 of standalone strings
+    const CA_STRING: &str = "CQMC4oAQMC4oAPoABABGB0CYAf8AAf8AAAqdA-AAUABwAFQALQAaABLACgAF0ANoAdwA_QCCAIQARQAnwBWgC3AGUANMAc4A7gCAQElASYAnYBPwDFAGaAM6AZ8A14B_AEngJyAT-Ao8BUQCpQFvALhAXQAvcBf4DBwGYANNAbUA3EBxoDxAHmgPkAgIBCQCNwEpYJgAmCBNUCa4E5gJ-AUmApYBU4FToHwACgAOAAqABaADQAJYAUAAugBtADuAH6AQQBCACKAE-AK0AW4AygBpgDnAHcAQCAkoCTAE7AJ-AYoAzQBnQDPgGvAP4Ak8BOQCfwFHgKiAVKAt4BcIC6AF7gL_AYOAzABpoDagG4gONAeIA80B8gEBAISARuAlLBMAEwQJqgTXAnMBPwCkwFLAKnAAAA.YAAAAAAAAAA";
+    const CA_SECTION: &str = "BQMC4oAQMC4oAPoABABGB0CYAf8AAf8AAAqdA-AAUABwAFQALQAaABLACgAF0ANoAdwA_QCCAIQARQAnwBWgC3AGUANMAc4A7gCAQElASYAnYBPwDFAGaAM6AZ8A14B_AEngJyAT-Ao8BUQCpQFvALhAXQAvcBf4DBwGYANNAbUA3EBxoDxAHmgPkAgIBCQCNwEpYJgAmCBNUCa4E5gJ-AUmApYBU4FToHwACgAOAAqABaADQAJYAUAAugBtADuAH6AQQBCACKAE-AK0AW4AygBpgDnAHcAQCAkoCTAE7AJ-AYoAzQBnQDPgGvAP4Ak8BOQCfwFHgKiAVKAt4BcIC6AF7gL_AYOAzABpoDagG4gONAeIA80B8gEBAISARuAlLBMAEwQJqgTXAnMBPwCkwFLAKnAAAA.YAAAAAAAAAA";
+
+    #[test_case(CA_STRING ; "standalone")]
+    #[test_case(&format!(" {CA_STRING}\n") ; "surrounding whitespace")]
+    #[test_case(CA_SECTION ; "gpp section")]
+    fn from_ca_string(s: &str) {
+        let tcf = TcfCaV1::from_ca_string(s).unwrap();
+
+        assert_eq!(tcf.core.cmp_id, 1000);
+        assert_eq!(tcf.core.created, 1738195200);
+        assert_eq!(tcf.core.consent_language.to_string(), "BG");
+        assert_eq!(tcf.core.vendor_express_consents.len(), 66);
+        assert!(tcf.core.vendor_express_consents.contains(&10));
+        assert!(tcf.publisher_purposes.is_some());
+        assert_eq!(tcf, TcfCaV1::from_str(s.trim()).unwrap());
+    }
+
+    #[test]
+    fn from_ca_string_error() {
+        assert!(matches!(
+            TcfCaV1::from_ca_string("CPuy0IAPuy0IAP"),
+            Err(SectionDecodeError::Read { .. })
+        ));
+    }
+
+    // the core segment ends after the vendor implied consents in v1.0, v1.1 strings carry a
+    // restriction count after them, here 0 then 1 with vendor 1 for purpose 1
+    #[test_case("BPuy0IAPuy0IAPoABABGCyCAAAAAAAAAAAAAAAAA" => (SubVersion::V1_0, 0) ; "v1.0")]
+    #[test_case("BPuy0IAPuy0IAPoABABGCyCAAAAAAAAAAAAAAAAAAA" => (SubVersion::V1_1, 0) ; "v1.1 without restrictions")]
+    #[test_case("BPuy0IAPuy0IAPoABABGCyCAAAAAAAAAAAAAAAAACDAABg" => (SubVersion::V1_1, 1) ; "v1.1 with restrictions")]
+    fn sub_version(s: &str) -> (SubVersion, usize) {
+        let core = TcfCaV1::from_str(s).unwrap().core;
+        assert_eq!(
+            core.has_publisher_restrictions_support(),
+            core.sub_version == SubVersion::V1_1
+        );
+        (core.sub_version, core.pub_restrictions.len())
+    }
+
+    // a v1.0 core segment of 241 bits, with vendor 1 in the vendor express consents: padded to
+    // 246 bits by an encoder aligning on base64 characters, but to 248 bits by one aligning on
+    // bytes, whose last character adds 4 more bits and the decoder another 4 to fill the byte
+    #[test_case("BPuy0IAPuy0IAPoABABGCyCAAAAAAAAAAAAQgAAAA" => SubVersion::V1_0 ; "character aligned")]
+    #[test_case("BPuy0IAPuy0IAPoABABGCyCAAAAAAAAAAAAQgAAAAA" => SubVersion::V1_1 ; "byte aligned")]
+    fn sub_version_of_padded_v1_0(s: &str) -> SubVersion {
+        let core = TcfCaV1::from_str(s).unwrap().core;
+        assert!(core.vendor_express_consents.contains(&1));
+        assert!(core.pub_restrictions.is_empty());
+        core.sub_version
+    }
+
     #[test]
     fn section_version_2_decodes() {
         let section = "CPuy0IAPuy0IAPoABABGCyCAAAAAAAAAAAAAAAAA.YAAAAAAAAAA";
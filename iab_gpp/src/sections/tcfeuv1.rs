@@ -1,5 +1,7 @@
 use crate::core::DataRead;
-use crate::sections::{IdSet, SectionDecodeError};
+use crate::sections::{
+    DecodeContext, IdSet, SectionDecodeError, TwoLetterCode, ValidationWarning, validation,
+};
 use bitstream_io::BitRead;
 use iab_gpp_derive::GPPSection;
 #[cfg(feature = "serde")]
@@ -9,6 +11,7 @@ use std::collections::BTreeSet;
 // See https://github.com/InteractiveAdvertisingBureau/GDPR-Transparency-and-Consent-Framework/blob/master/Consent%20string%20and%20vendor%20list%20formats%20v1.1%20Final.md
 #[derive(Debug, Eq, PartialEq, GPPSection)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "test-util", derive(Default))]
 #[gpp(section_version = 1)]
 pub struct TcfEuV1 {
     #[gpp(datetime_as_unix_timestamp)]
@@ -18,8 +21,7 @@ pub struct TcfEuV1 {
     pub cmp_id: u16,
     pub cmp_version: u16,
     pub consent_screen: u8,
-    #[gpp(string(2))]
-    pub consent_language: String,
+    pub consent_language: TwoLetterCode,
     pub vendor_list_version: u16,
     #[gpp(fixed_bitfield(24))]
     pub purposes_allowed: IdSet,
@@ -27,13 +29,31 @@ pub struct TcfEuV1 {
     pub vendor_consents: IdSet,
 }
 
-fn parse_vendor_consents<R: BitRead + ?Sized>(mut r: &mut R) -> Result<IdSet, SectionDecodeError> {
+impl TcfEuV1 {
+    /// Checks this section for semantic issues, see [`Section::validate`].
+    ///
+    /// [`Section::validate`]: crate::sections::Section::validate
+    pub fn validate(&self) -> Vec<ValidationWarning> {
+        let mut warnings =
+            validation::validate_tcf_metadata(self.created, self.last_updated, self.cmp_id);
+        warnings.extend(validation::validate_consent_metadata(
+            self.consent_screen,
+            self.consent_language,
+        ));
+        warnings
+    }
+}
+
+fn parse_vendor_consents<R: BitRead + ?Sized>(
+    mut r: &mut R,
+    ctx: &DecodeContext,
+) -> Result<IdSet, SectionDecodeError> {
     let max_vendor_id = r.read_unsigned::<16, u16>()?;
     let is_range = r.read_bit()?;
     Ok(if is_range {
         // range section
         let default_consent = r.read_bit()?;
-        let ids = BTreeSet::from_iter(r.read_integer_range()?);
+        let ids = ctx.check_ids(BTreeSet::from_iter(r.read_integer_range()?))?;
 
         // create final vendor list based on the default consent:
         // only return list of vendors who consent
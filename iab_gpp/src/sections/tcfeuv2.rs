@@ -1,14 +1,22 @@
-use crate::core::{DataRead, Range};
-use crate::sections::{IdSet, SectionDecodeError};
+use crate::core::{
+    DataRead, Range, base64_bit_reader, integer_range_bit_len, optimized_integer_range_bit_len,
+};
+use crate::sections::{
+    DecodeContext, DecodeWarning, FieldChange, IdSet, IdSetDiff, IdSetExt, SectionDecodeError,
+    TwoLetterCode, ValidationWarning, validation,
+};
 use bitstream_io::BitRead;
 use iab_gpp_derive::{FromBitStream, GPPSection};
 use num_derive::FromPrimitive;
 use num_traits::FromPrimitive;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 #[derive(Debug, Eq, PartialEq, GPPSection)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde-strict", serde(deny_unknown_fields))]
+#[cfg_attr(feature = "test-util", derive(Default))]
 #[non_exhaustive]
 #[gpp(with_optional_segments)]
 pub struct TcfEuV2 {
@@ -21,8 +29,124 @@ pub struct TcfEuV2 {
     pub publisher_purposes: Option<PublisherPurposes>,
 }
 
+impl TcfEuV2 {
+    /// Checks the core segment for semantic issues, see [`Section::validate`].
+    ///
+    /// [`Section::validate`]: crate::sections::Section::validate
+    pub fn validate(&self) -> Vec<ValidationWarning> {
+        let mut warnings = validation::validate_tcf_metadata(
+            self.core.created,
+            self.core.last_updated,
+            self.core.cmp_id,
+        );
+        warnings.extend(validation::validate_consent_metadata(
+            self.core.consent_screen,
+            self.core.consent_language,
+        ));
+        if !self.core.publisher_country_code.is_uppercase_letters() {
+            warnings.push(ValidationWarning::InvalidPublisherCountryCode {
+                publisher_country_code: self.core.publisher_country_code,
+            });
+        }
+        warnings
+    }
+
+    /// Reads only the CMP id from a TCF EU v2 section string.
+    ///
+    /// This is much cheaper than a full decode: the timestamps are skipped and nothing after
+    /// the CMP id is read, so no id set is built. Only the version of the core segment is
+    /// checked, the rest of the string may be invalid.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::tcfeuv2::TcfEuV2;
+    ///
+    /// let cmp_id = TcfEuV2::peek_cmp_id("CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA").unwrap();
+    ///
+    /// assert_eq!(cmp_id, 31);
+    /// ```
+    pub fn peek_cmp_id(s: &str) -> Result<u16, SectionDecodeError> {
+        let core = s.split('.').next().unwrap_or_default();
+        let mut r = base64_bit_reader(core.as_bytes());
+
+        let segment_version = r.read_unsigned::<6, u8>()?;
+        if segment_version != 2 {
+            return Err(SectionDecodeError::UnknownSegmentVersion { segment_version });
+        }
+        // created and last updated timestamps
+        r.skip(2 * 36)?;
+        Ok(r.read_unsigned::<12, u16>()?)
+    }
+
+    /// Reads the creation timestamp of a TCF EU v2 string, in seconds since the Unix epoch,
+    /// without decoding the rest of the string.
+    ///
+    /// Like [`TcfEuV2::peek_cmp_id`], only the version of the core segment is checked.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::tcfeuv2::TcfEuV2;
+    ///
+    /// let created = TcfEuV2::peek_created("CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA").unwrap();
+    ///
+    /// assert_eq!(created, 1650492000);
+    /// ```
+    pub fn peek_created(s: &str) -> Result<u64, SectionDecodeError> {
+        let core = s.split('.').next().unwrap_or_default();
+        let mut r = base64_bit_reader(core.as_bytes());
+
+        let segment_version = r.read_unsigned::<6, u8>()?;
+        if segment_version != 2 {
+            return Err(SectionDecodeError::UnknownSegmentVersion { segment_version });
+        }
+        Ok(r.read_datetime_as_unix_timestamp()?)
+    }
+
+    /// Returns the number of bits this section occupies when encoded, without encoding it.
+    ///
+    /// This is the sum of the lengths of the core segment and of every optional segment which
+    /// is present. Encoders pad each segment to whole bytes before Base64 encoding it, which is
+    /// not included. Id sets are counted with the shorter of their bitfield and range
+    /// encodings, which is what encoders should pick, so the result is a lower bound for
+    /// strings produced by encoders which do not.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::tcfeuv2::TcfEuV2;
+    /// use std::str::FromStr;
+    ///
+    /// let tcf = TcfEuV2::from_str("CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA").unwrap();
+    ///
+    /// assert_eq!(tcf.encoded_bit_len(), 259);
+    /// ```
+    pub fn encoded_bit_len(&self) -> usize {
+        self.segment_bit_lens().into_iter().sum()
+    }
+
+    /// Returns the number of bits of the core segment and of every optional segment which is
+    /// present, in encoding order.
+    fn segment_bit_lens(&self) -> Vec<usize> {
+        let mut lens = vec![self.core.encoded_bit_len()];
+        if let Some(ids) = &self.disclosed_vendors {
+            lens.push(SEGMENT_TYPE_BITS + optimized_integer_range_bit_len(ids));
+        }
+        if let Some(ids) = &self.allowed_vendors {
+            lens.push(SEGMENT_TYPE_BITS + optimized_integer_range_bit_len(ids));
+        }
+        if let Some(p) = &self.publisher_purposes {
+            lens.push(SEGMENT_TYPE_BITS + p.encoded_bit_len());
+        }
+        lens
+    }
+}
+
 #[derive(Debug, Eq, PartialEq, FromBitStream)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde-strict", serde(deny_unknown_fields))]
+#[cfg_attr(feature = "test-util", derive(Default))]
 #[non_exhaustive]
 #[gpp(section_version = 2)]
 pub struct Core {
@@ -33,8 +157,7 @@ pub struct Core {
     pub cmp_id: u16,
     pub cmp_version: u16,
     pub consent_screen: u8,
-    #[gpp(string(2))]
-    pub consent_language: String,
+    pub consent_language: TwoLetterCode,
     pub vendor_list_version: u16,
     pub policy_version: u8,
     pub is_service_specific: bool,
@@ -46,8 +169,7 @@ pub struct Core {
     #[gpp(fixed_bitfield(24))]
     pub purpose_legitimate_interests: IdSet,
     pub purpose_one_treatment: bool,
-    #[gpp(string(2))]
-    pub publisher_country_code: String,
+    pub publisher_country_code: TwoLetterCode,
     #[gpp(optimized_integer_range)]
     pub vendor_consents: IdSet,
     #[gpp(optimized_integer_range)]
@@ -56,87 +178,403 @@ pub struct Core {
     pub publisher_restrictions: Vec<PublisherRestriction>,
 }
 
+/// Bits of the fixed-width fields of a core segment, from the version to the publisher
+/// country code.
+const CORE_FIXED_BITS: usize = 213;
+/// Bits of the type which prefixes optional segments.
+const SEGMENT_TYPE_BITS: usize = 3;
+
+impl Core {
+    /// Returns the number of bits of the core segment once encoded.
+    fn encoded_bit_len(&self) -> usize {
+        CORE_FIXED_BITS
+            + optimized_integer_range_bit_len(&self.vendor_consents)
+            + optimized_integer_range_bit_len(&self.vendor_legitimate_interests)
+            + 12
+            + self
+                .publisher_restrictions
+                .iter()
+                .map(|r| 6 + 2 + integer_range_bit_len(&r.restricted_vendor_ids))
+                .sum::<usize>()
+    }
+
+    /// Returns the IDs of the purposes the user consented to, in ascending order.
+    pub fn purposes_consented(&self) -> Vec<u8> {
+        self.purpose_consents.iter().map(|&id| id as u8).collect()
+    }
+
+    /// Returns the IDs of the purposes for which legitimate interest was established,
+    /// in ascending order.
+    pub fn purposes_legitimate_interests(&self) -> Vec<u8> {
+        self.purpose_legitimate_interests
+            .iter()
+            .map(|&id| id as u8)
+            .collect()
+    }
+
+    /// Returns the purpose consents as a bitmask, where bit `i` is set if the user consented to
+    /// purpose `i + 1`.
+    ///
+    /// This is the most compact way to pass purpose consents between services. Only the 24
+    /// purposes encoded in the string can be set, so the 8 most significant bits are always
+    /// cleared.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::tcfeuv2::TcfEuV2;
+    ///
+    /// let mut tcf: TcfEuV2 = "CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA".parse().unwrap();
+    /// tcf.core.purpose_consents = [1, 3, 4, 24].into();
+    ///
+    /// assert_eq!(tcf.core.purpose_consents_mask(), 0x80_000d);
+    /// ```
+    pub fn purpose_consents_mask(&self) -> u32 {
+        self.purpose_consents.to_int_mask(24)
+    }
+
+    /// Returns the purposes for which legitimate interest was established as a bitmask, where
+    /// bit `i` is set for purpose `i + 1`.
+    ///
+    /// See [`purpose_consents_mask`](Core::purpose_consents_mask).
+    pub fn purpose_legitimate_interests_mask(&self) -> u32 {
+        self.purpose_legitimate_interests.to_int_mask(24)
+    }
+
+    /// Returns the special features the user opted in to, in ascending id order.
+    ///
+    /// Ids which are not defined by the TCF policy are skipped, they are still available in
+    /// [`special_feature_optins`](Core::special_feature_optins).
+    pub fn special_features(&self) -> Vec<SpecialFeature> {
+        self.special_feature_optins
+            .iter()
+            .filter_map(|&id| SpecialFeature::from_u16(id))
+            .collect()
+    }
+
+    /// Returns `true` if the vendor may process data for the purpose, given the legal basis the
+    /// vendor declared for it in the Global Vendor List.
+    ///
+    /// The effective legal basis is first resolved with
+    /// [`resolve_legal_basis`](Core::resolve_legal_basis). Then, both the purpose and the vendor
+    /// must have the user signal matching that legal basis: consent, or legitimate interest.
+    pub fn is_purpose_allowed_for_vendor(
+        &self,
+        purpose_id: u8,
+        vendor_id: u16,
+        legal_basis: LegalBasis,
+    ) -> bool {
+        let Some(legal_basis) = self.resolve_legal_basis(purpose_id, vendor_id, legal_basis) else {
+            return false;
+        };
+
+        let purpose_id = purpose_id as u16;
+        match legal_basis {
+            LegalBasis::Consent => {
+                self.purpose_consents.contains(&purpose_id)
+                    && self.vendor_consents.contains(&vendor_id)
+            }
+            LegalBasis::LegitimateInterest => {
+                self.purpose_legitimate_interests.contains(&purpose_id)
+                    && self.vendor_legitimate_interests.contains(&vendor_id)
+            }
+        }
+    }
+
+    /// Resolves the legal basis under which the vendor may process data for the purpose, given
+    /// the legal basis the vendor declared for it in the Global Vendor List.
+    ///
+    /// User signals are not checked, only the publisher's settings. The resolution order is:
+    ///
+    /// 1. If `purpose_one_treatment` is set, purpose 1 was not disclosed to the user, so no
+    ///    legal basis can be established for it from this string and `None` is returned.
+    /// 2. A [`RestrictionType::NotAllowed`] publisher restriction for this vendor and purpose
+    ///    returns `None`.
+    /// 3. A [`RestrictionType::RequireConsent`] or [`RestrictionType::RequireLegitimateInterest`]
+    ///    restriction replaces the declared legal basis.
+    /// 4. Otherwise, the declared legal basis is used.
+    ///
+    /// Finally, legitimate interest is never a valid legal basis for purpose 1, in which case
+    /// `None` is returned.
+    pub fn resolve_legal_basis(
+        &self,
+        purpose_id: u8,
+        vendor_id: u16,
+        declared: LegalBasis,
+    ) -> Option<LegalBasis> {
+        if purpose_id == 1 && self.purpose_one_treatment {
+            return None;
+        }
+
+        let legal_basis = match self.restriction_type(purpose_id, vendor_id) {
+            Some(RestrictionType::NotAllowed) => return None,
+            Some(RestrictionType::RequireConsent) => LegalBasis::Consent,
+            Some(RestrictionType::RequireLegitimateInterest) => LegalBasis::LegitimateInterest,
+            Some(RestrictionType::Undefined) | None => declared,
+        };
+
+        if purpose_id == 1 && legal_basis == LegalBasis::LegitimateInterest {
+            return None;
+        }
+
+        Some(legal_basis)
+    }
+
+    /// Returns the publisher restrictions grouped by purpose id, in ascending purpose order.
+    ///
+    /// Restrictions keep the order in which they appear in the string within each purpose.
+    pub fn restrictions_by_purpose(&self) -> BTreeMap<u8, Vec<&PublisherRestriction>> {
+        let mut by_purpose = BTreeMap::<u8, Vec<_>>::new();
+        for r in &self.publisher_restrictions {
+            by_purpose.entry(r.purpose_id).or_default().push(r);
+        }
+        by_purpose
+    }
+
+    /// Returns the type of the publisher restriction applying to a vendor for a purpose.
+    fn restriction_type(&self, purpose_id: u8, vendor_id: u16) -> Option<&RestrictionType> {
+        self.publisher_restrictions
+            .iter()
+            .find(|r| r.purpose_id == purpose_id && r.restricted_vendor_ids.contains(&vendor_id))
+            .map(|r| &r.restriction_type)
+    }
+
+    /// Reports which fields changed between this core segment and a more recent one.
+    ///
+    /// Id sets report the ids which were added and removed, scalar fields report both values
+    /// when they differ.
+    pub fn diff(&self, other: &Core) -> CoreDiff {
+        CoreDiff {
+            created: FieldChange::between(&self.created, &other.created),
+            last_updated: FieldChange::between(&self.last_updated, &other.last_updated),
+            cmp_id: FieldChange::between(&self.cmp_id, &other.cmp_id),
+            cmp_version: FieldChange::between(&self.cmp_version, &other.cmp_version),
+            consent_screen: FieldChange::between(&self.consent_screen, &other.consent_screen),
+            consent_language: FieldChange::between(&self.consent_language, &other.consent_language),
+            vendor_list_version: FieldChange::between(
+                &self.vendor_list_version,
+                &other.vendor_list_version,
+            ),
+            policy_version: FieldChange::between(&self.policy_version, &other.policy_version),
+            is_service_specific: FieldChange::between(
+                &self.is_service_specific,
+                &other.is_service_specific,
+            ),
+            use_non_standard_stacks: FieldChange::between(
+                &self.use_non_standard_stacks,
+                &other.use_non_standard_stacks,
+            ),
+            special_feature_optins: IdSetDiff::between(
+                &self.special_feature_optins,
+                &other.special_feature_optins,
+            ),
+            purpose_consents: IdSetDiff::between(&self.purpose_consents, &other.purpose_consents),
+            purpose_legitimate_interests: IdSetDiff::between(
+                &self.purpose_legitimate_interests,
+                &other.purpose_legitimate_interests,
+            ),
+            purpose_one_treatment: FieldChange::between(
+                &self.purpose_one_treatment,
+                &other.purpose_one_treatment,
+            ),
+            publisher_country_code: FieldChange::between(
+                &self.publisher_country_code,
+                &other.publisher_country_code,
+            ),
+            vendor_consents: IdSetDiff::between(&self.vendor_consents, &other.vendor_consents),
+            vendor_legitimate_interests: IdSetDiff::between(
+                &self.vendor_legitimate_interests,
+                &other.vendor_legitimate_interests,
+            ),
+            publisher_restrictions_changed: self.publisher_restrictions
+                != other.publisher_restrictions,
+        }
+    }
+
+    /// Returns a compact copy of the fields of this core segment most consumers need.
+    ///
+    /// See [`CoreSummary`] for the fields which are dropped.
+    pub fn summarize(&self) -> CoreSummary {
+        CoreSummary {
+            created: self.created,
+            cmp_id: self.cmp_id,
+            vendor_list_version: self.vendor_list_version,
+            purpose_consents: self.purpose_consents_mask(),
+        }
+    }
+}
+
+/// Field-level differences between two TCF EU v2 core segments, see [`Core::diff`].
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[non_exhaustive]
+pub struct CoreDiff {
+    pub created: Option<FieldChange<u64>>,
+    pub last_updated: Option<FieldChange<u64>>,
+    pub cmp_id: Option<FieldChange<u16>>,
+    pub cmp_version: Option<FieldChange<u16>>,
+    pub consent_screen: Option<FieldChange<u8>>,
+    pub consent_language: Option<FieldChange<TwoLetterCode>>,
+    pub vendor_list_version: Option<FieldChange<u16>>,
+    pub policy_version: Option<FieldChange<u8>>,
+    pub is_service_specific: Option<FieldChange<bool>>,
+    pub use_non_standard_stacks: Option<FieldChange<bool>>,
+    pub special_feature_optins: IdSetDiff,
+    pub purpose_consents: IdSetDiff,
+    pub purpose_legitimate_interests: IdSetDiff,
+    pub purpose_one_treatment: Option<FieldChange<bool>>,
+    pub publisher_country_code: Option<FieldChange<TwoLetterCode>>,
+    pub vendor_consents: IdSetDiff,
+    pub vendor_legitimate_interests: IdSetDiff,
+    pub publisher_restrictions_changed: bool,
+}
+
+impl CoreDiff {
+    /// Returns `true` if no field changed.
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+/// A lossy, fixed-size projection of a TCF EU v2 core segment, see [`Core::summarize`].
+///
+/// It is meant to keep large numbers of consents in memory, and only retains the creation
+/// timestamp, the CMP, the vendor list version and the purpose consents. Everything else is
+/// dropped: the last update timestamp, CMP version, consent screen and language, policy
+/// version, the service specific, non-standard stacks and purpose one treatment flags, special
+/// feature opt-ins, purpose legitimate interests, the publisher country code, vendor consents
+/// and legitimate interests, and publisher restrictions.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[non_exhaustive]
+pub struct CoreSummary {
+    pub created: u64,
+    pub cmp_id: u16,
+    pub vendor_list_version: u16,
+    /// Purposes the user consented to, as a bitmask where bit `n - 1` is set for purpose `n`.
+    pub purpose_consents: u32,
+}
+
+impl CoreSummary {
+    /// Returns `true` if the user consented to the purpose.
+    pub fn has_purpose_consent(&self, purpose_id: u8) -> bool {
+        (1..=24).contains(&purpose_id) && self.purpose_consents & 1 << (purpose_id - 1) != 0
+    }
+}
+
 fn parse_publisher_restrictions<R: BitRead + ?Sized>(
     r: &mut R,
+    ctx: &DecodeContext,
 ) -> Result<Vec<PublisherRestriction>, SectionDecodeError> {
     let num_restrictions = r.read_unsigned::<12, u16>()?;
     let mut restrictions = Vec::with_capacity(num_restrictions as usize);
+    let mut budget = InsertBudget::new(ctx.options.max_range_inserts_or_unbounded());
 
-    for _ in 0..num_restrictions {
-        let purpose_id = match r.read_unsigned::<6, u8>() {
-            Ok(purpose_id) => purpose_id,
-            Err(source) if source.kind() == std::io::ErrorKind::UnexpectedEof => break,
-            Err(source) => return Err(SectionDecodeError::Read { source }),
-        };
-        let restriction_type = match r.read_unsigned::<2, u8>() {
-            Ok(restriction_type) => restriction_type,
-            Err(source) if source.kind() == std::io::ErrorKind::UnexpectedEof => break,
-            Err(source) => return Err(SectionDecodeError::Read { source }),
-        };
-        let restricted_vendor_ids =
-            match read_publisher_restriction_integer_range_compat(r, restrictions.len())? {
-                Some(ids) => ids,
-                None => break,
-            };
-
-        restrictions.push(PublisherRestriction {
-            purpose_id,
-            restriction_type: RestrictionType::from_u8(restriction_type)
-                .unwrap_or(RestrictionType::Undefined),
-            restricted_vendor_ids,
-        });
+    while restrictions.len() < num_restrictions as usize {
+        match read_publisher_restriction(r, restrictions.len(), &mut budget)? {
+            Some(restriction) => restrictions.push(restriction),
+            None => {
+                ctx.warn(DecodeWarning::TruncatedPublisherRestrictions {
+                    declared: num_restrictions as usize,
+                    decoded: restrictions.len(),
+                });
+                break;
+            }
+        }
     }
 
     Ok(restrictions)
 }
 
+/// Counts the ids inserted while reading publisher restrictions, against the maximum allowed
+/// by the decode options.
+struct InsertBudget {
+    inserted: usize,
+    max: usize,
+}
+
+impl InsertBudget {
+    fn new(max: usize) -> Self {
+        Self { inserted: 0, max }
+    }
+
+    fn spend(&mut self, n: usize) -> Result<(), SectionDecodeError> {
+        self.inserted = self.inserted.saturating_add(n);
+        if self.inserted > self.max {
+            return Err(SectionDecodeError::SectionTooLarge { max: self.max });
+        }
+        Ok(())
+    }
+}
+
+/// Maps an unexpected end of stream to `None`, other errors are returned as is.
+fn eof_as_none<T>(r: std::io::Result<T>) -> Result<Option<T>, SectionDecodeError> {
+    match r {
+        Ok(value) => Ok(Some(value)),
+        Err(source) if source.kind() == std::io::ErrorKind::UnexpectedEof => Ok(None),
+        Err(source) => Err(SectionDecodeError::Read { source }),
+    }
+}
+
+/// Reads a single publisher restriction, or returns `None` if the stream ends before the
+/// restriction is complete.
+fn read_publisher_restriction<R: BitRead + ?Sized>(
+    r: &mut R,
+    restriction_idx: usize,
+    budget: &mut InsertBudget,
+) -> Result<Option<PublisherRestriction>, SectionDecodeError> {
+    let Some(purpose_id) = eof_as_none(r.read_unsigned::<6, u8>())? else {
+        return Ok(None);
+    };
+    let Some(restriction_type) = eof_as_none(r.read_unsigned::<2, u8>())? else {
+        return Ok(None);
+    };
+    let Some(restricted_vendor_ids) =
+        read_publisher_restriction_integer_range_compat(r, restriction_idx, budget)?
+    else {
+        return Ok(None);
+    };
+
+    Ok(Some(PublisherRestriction {
+        purpose_id,
+        restriction_type: RestrictionType::from_u8(restriction_type)
+            .unwrap_or(RestrictionType::Undefined),
+        restricted_vendor_ids,
+    }))
+}
+
 fn read_publisher_restriction_integer_range_compat<R: BitRead + ?Sized>(
     r: &mut R,
     restriction_idx: usize,
+    budget: &mut InsertBudget,
 ) -> Result<Option<IdSet>, SectionDecodeError> {
-    let n = match r.read_unsigned::<12, u16>() {
-        Ok(n) => n,
-        Err(source) if source.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
-        Err(source) => return Err(SectionDecodeError::Read { source }),
+    // the ids of the first restriction must be complete, later ones may be cut off
+    let read_id = |r: &mut R| match r.read_unsigned::<16, u16>() {
+        Err(source) if restriction_idx == 0 => Err(SectionDecodeError::Read { source }),
+        id => eof_as_none(id),
+    };
+
+    let Some(n) = eof_as_none(r.read_unsigned::<12, u16>())? else {
+        return Ok(None);
     };
 
     let mut ids = IdSet::new();
     for _entry_idx in 0..n {
-        let is_group = match r.read_bit() {
-            Ok(is_group) => is_group,
-            Err(source) if source.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
-            Err(source) => return Err(SectionDecodeError::Read { source }),
+        let Some(is_group) = eof_as_none(r.read_bit())? else {
+            return Ok(None);
         };
-
-        let start = match r.read_unsigned::<16, u16>() {
-            Ok(start) => start,
-            Err(source) if source.kind() == std::io::ErrorKind::UnexpectedEof => {
-                return if restriction_idx > 0 {
-                    Ok(None)
-                } else {
-                    Err(SectionDecodeError::Read { source })
-                };
-            }
-            Err(source) => return Err(SectionDecodeError::Read { source }),
+        let Some(start) = read_id(r)? else {
+            return Ok(None);
         };
 
         if is_group {
-            let end = match r.read_unsigned::<16, u16>() {
-                Ok(end) => end,
-                Err(source) if source.kind() == std::io::ErrorKind::UnexpectedEof => {
-                    return if restriction_idx > 0 {
-                        Ok(None)
-                    } else {
-                        Err(SectionDecodeError::Read { source })
-                    };
-                }
-                Err(source) => return Err(SectionDecodeError::Read { source }),
+            let Some(end) = read_id(r)? else {
+                return Ok(None);
             };
-
-            for id in start..=end {
-                ids.insert(id);
-            }
+            budget.spend((start..=end).len())?;
+            ids.extend(start..=end);
         } else {
+            budget.spend(1)?;
             ids.insert(start);
         }
     }
@@ -146,6 +584,8 @@ fn read_publisher_restriction_integer_range_compat<R: BitRead + ?Sized>(
 
 #[derive(Debug, Eq, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde-strict", serde(deny_unknown_fields))]
+#[cfg_attr(feature = "test-util", derive(Default))]
 pub struct PublisherRestriction {
     pub purpose_id: u8,
     pub restriction_type: RestrictionType,
@@ -165,15 +605,54 @@ impl From<Range> for PublisherRestriction {
 
 #[derive(Debug, Eq, PartialEq, FromPrimitive)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "test-util", derive(Default))]
 pub enum RestrictionType {
     NotAllowed = 0,
     RequireConsent = 1,
     RequireLegitimateInterest = 2,
+    #[cfg_attr(feature = "test-util", default)]
     Undefined = 3,
 }
 
+impl RestrictionType {
+    /// Returns `true` if processing under the given legal basis survives this restriction.
+    ///
+    /// [`NotAllowed`](RestrictionType::NotAllowed) permits no legal basis, the `Require*`
+    /// restrictions only permit their own legal basis, and
+    /// [`Undefined`](RestrictionType::Undefined) permits any.
+    pub fn permits(&self, basis: LegalBasis) -> bool {
+        match self {
+            RestrictionType::NotAllowed => false,
+            RestrictionType::RequireConsent => basis == LegalBasis::Consent,
+            RestrictionType::RequireLegitimateInterest => basis == LegalBasis::LegitimateInterest,
+            RestrictionType::Undefined => true,
+        }
+    }
+}
+
+/// A special feature defined by the TCF policy, which requires the user to opt in.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, FromPrimitive)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[non_exhaustive]
+pub enum SpecialFeature {
+    /// Use precise geolocation data.
+    PreciseGeolocation = 1,
+    /// Actively scan device characteristics for identification.
+    DeviceScanning = 2,
+}
+
+/// The legal basis under which a vendor processes data for a purpose.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum LegalBasis {
+    Consent,
+    LegitimateInterest,
+}
+
 #[derive(Debug, Eq, PartialEq, FromBitStream)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde-strict", serde(deny_unknown_fields))]
+#[cfg_attr(feature = "test-util", derive(Default))]
 #[non_exhaustive]
 pub struct PublisherPurposes {
     #[gpp(fixed_bitfield(24))]
@@ -182,32 +661,924 @@ pub struct PublisherPurposes {
     pub legitimate_interests: IdSet,
     #[gpp(fixed_bitfield(n as usize), where(n = unsigned_var(6)))]
     pub custom_consents: IdSet,
+    // same number of custom purposes as the consents above
     #[gpp(fixed_bitfield(n as usize))]
     pub custom_legitimate_interests: IdSet,
 }
 
+impl PublisherPurposes {
+    /// Returns the number of bits of the publisher purposes segment once encoded, without its
+    /// segment type.
+    fn encoded_bit_len(&self) -> usize {
+        let custom_purposes = self
+            .custom_consents
+            .last()
+            .max(self.custom_legitimate_interests.last())
+            .copied()
+            .unwrap_or(0) as usize;
+        24 + 24 + 6 + 2 * custom_purposes
+    }
+}
+
+/// Highest purpose id defined by TCF v2.2.
+#[cfg(feature = "serde")]
+const TCDATA_MAX_PURPOSE_ID: u16 = 11;
+/// Highest special feature id defined by TCF v2.2.
+#[cfg(feature = "serde")]
+const TCDATA_MAX_SPECIAL_FEATURE_ID: u16 = 2;
+
+/// The consent data of a TC string, shaped like the `TCData` object returned by the `__tcfapi`
+/// CMP API.
+///
+/// Serializing it, for instance with `serde_json`, produces the exact nested layout expected by
+/// front-end consent logic. Fields which describe the state of a CMP at runtime rather than the
+/// string (`tcString`, `gdprApplies`, `eventStatus`, `cmpStatus` and `listenerId`) are not part
+/// of it.
+///
+/// Id-keyed maps list every id from 1 to a maximum, with `false` for ids absent from the string:
+///
+/// | Map                                 | Maximum id                                        |
+/// |-------------------------------------|---------------------------------------------------|
+/// | `purpose`, `publisher`              | 11, or the highest id present if greater          |
+/// | `specialFeatureOptins`              | 2, or the highest id present if greater           |
+/// | `vendor`                            | the highest vendor id present in either vendor map |
+/// | `publisher.customPurpose`           | the highest custom purpose id present             |
+#[cfg(feature = "serde")]
+#[derive(Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TcData {
+    pub tcf_policy_version: u8,
+    pub cmp_id: u16,
+    pub cmp_version: u16,
+    pub is_service_specific: bool,
+    pub use_non_standard_texts: bool,
+    #[serde(rename = "publisherCC")]
+    pub publisher_cc: TwoLetterCode,
+    pub purpose_one_treatment: bool,
+    pub purpose: TcDataConsents,
+    pub vendor: TcDataConsents,
+    pub special_feature_optins: BTreeMap<u16, bool>,
+    pub publisher: TcDataPublisher,
+}
+
+/// Consents and legitimate interests of a [`TcData`], keyed by id.
+#[cfg(feature = "serde")]
+#[derive(Debug, Default, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TcDataConsents {
+    pub consents: BTreeMap<u16, bool>,
+    pub legitimate_interests: BTreeMap<u16, bool>,
+}
+
+/// Publisher signals of a [`TcData`].
+///
+/// Restrictions map purpose ids to vendor ids to the restriction type: 0 for not allowed, 1 for
+/// require consent and 2 for require legitimate interest.
+#[cfg(feature = "serde")]
+#[derive(Debug, Default, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TcDataPublisher {
+    pub consents: BTreeMap<u16, bool>,
+    pub legitimate_interests: BTreeMap<u16, bool>,
+    pub custom_purpose: TcDataConsents,
+    pub restrictions: BTreeMap<u8, BTreeMap<u16, u8>>,
+}
+
+#[cfg(feature = "serde")]
+impl Core {
+    /// Converts this core segment to the `TCData` shape of the `__tcfapi` CMP API.
+    ///
+    /// Publisher consents and legitimate interests are not part of the core segment and are
+    /// left empty, use [`TcfEuV2::to_tcdata`] to include them.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::tcfeuv2::TcfEuV2;
+    /// use std::str::FromStr;
+    ///
+    /// let tcf = TcfEuV2::from_str("CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA").unwrap();
+    /// let tcdata = serde_json::to_value(tcf.core.to_tcdata()).unwrap();
+    ///
+    /// assert_eq!(tcdata["cmpId"], 31);
+    /// assert_eq!(tcdata["publisherCC"], "DE");
+    /// assert_eq!(tcdata["purpose"]["consents"]["1"], false);
+    /// ```
+    pub fn to_tcdata(&self) -> TcData {
+        let vendor_max = self
+            .vendor_consents
+            .last()
+            .max(self.vendor_legitimate_interests.last())
+            .copied()
+            .unwrap_or(0);
+
+        let mut restrictions = BTreeMap::<u8, BTreeMap<u16, u8>>::new();
+        for r in &self.publisher_restrictions {
+            let restriction_type = match r.restriction_type {
+                RestrictionType::NotAllowed => 0,
+                RestrictionType::RequireConsent => 1,
+                RestrictionType::RequireLegitimateInterest => 2,
+                RestrictionType::Undefined => continue,
+            };
+            let vendors = restrictions.entry(r.purpose_id).or_default();
+            for &vendor_id in &r.restricted_vendor_ids {
+                vendors.insert(vendor_id, restriction_type);
+            }
+        }
+
+        TcData {
+            tcf_policy_version: self.policy_version,
+            cmp_id: self.cmp_id,
+            cmp_version: self.cmp_version,
+            is_service_specific: self.is_service_specific,
+            use_non_standard_texts: self.use_non_standard_stacks,
+            publisher_cc: self.publisher_country_code,
+            purpose_one_treatment: self.purpose_one_treatment,
+            purpose: TcDataConsents {
+                consents: id_map(&self.purpose_consents, TCDATA_MAX_PURPOSE_ID),
+                legitimate_interests: id_map(
+                    &self.purpose_legitimate_interests,
+                    TCDATA_MAX_PURPOSE_ID,
+                ),
+            },
+            vendor: TcDataConsents {
+                consents: id_map(&self.vendor_consents, vendor_max),
+                legitimate_interests: id_map(&self.vendor_legitimate_interests, vendor_max),
+            },
+            special_feature_optins: id_map(
+                &self.special_feature_optins,
+                TCDATA_MAX_SPECIAL_FEATURE_ID,
+            ),
+            publisher: TcDataPublisher {
+                restrictions,
+                ..Default::default()
+            },
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TcfEuV2 {
+    /// Converts this section to the `TCData` shape of the `__tcfapi` CMP API, including the
+    /// publisher purposes segment if present.
+    ///
+    /// See [`Core::to_tcdata`].
+    pub fn to_tcdata(&self) -> TcData {
+        let mut tcdata = self.core.to_tcdata();
+        if let Some(p) = &self.publisher_purposes {
+            tcdata.publisher.consents = id_map(&p.consents, TCDATA_MAX_PURPOSE_ID);
+            tcdata.publisher.legitimate_interests =
+                id_map(&p.legitimate_interests, TCDATA_MAX_PURPOSE_ID);
+
+            let custom_max = p
+                .custom_consents
+                .last()
+                .max(p.custom_legitimate_interests.last())
+                .copied()
+                .unwrap_or(0);
+            tcdata.publisher.custom_purpose = TcDataConsents {
+                consents: id_map(&p.custom_consents, custom_max),
+                legitimate_interests: id_map(&p.custom_legitimate_interests, custom_max),
+            };
+        }
+        tcdata
+    }
+}
+
+/// Maps every id from 1 to `max`, or to the highest id of the set if greater, to its presence
+/// in the set.
+#[cfg(feature = "serde")]
+fn id_map(set: &IdSet, max: u16) -> BTreeMap<u16, bool> {
+    let max = set.last().copied().unwrap_or(0).max(max);
+    (1..=max).map(|id| (id, set.contains(&id))).collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::sections::{DecodeOptions, DecodeStr};
     use std::str::FromStr;
     use test_case::test_case;
 
+    const LEGACY_SAMPLE: &str = "CQaXJQAQaXJQAAGABCENCCFsAP_gAEPgAAiQKmNR_G_fbXlj8TZ36ftkeYxf99hjrsQxBgaJk24FyJvW7JwW32EzNAzapqYKmRIAu1BBAQNlGIDURUCgKIgVqTDMaESEoTNKJ6BEgBMRA2JYCFxvmwBDWQCY5tp9dld5mB-N7dr8ydzyy4BHn3I5XsS1WBAAAAAAAAAAAAAAAQAAgAAAgAAAAAAAAAAAABAAEAAAIAAAAAACAAAAAAAAAAAAAAAAAACAAAAAQSNgfgAKgAcAB4AFwAVAAuAB-AF0ANAAfABCACKAEcAMsAc4A7gCAQEHAQgAiMBGQEaAI4ASIAn4BUACxAF6AMUAa8A6QB2wD_gIQAR6AlYBMUCZAJlATbApACkQFJgKyAV2AsIBagC4AFxALmAXRAvIC8wF9AMQAYsAyEBkYDRgGmgNTAa8A2gBtgDbgG6AN-AgmBI0BQJA5AAXABQAFQALgAcAA8ACAAF8AMgA1AB4AEwAKoAbwA_QCGAIkATQArQBgADDgGUAZYA2YB3AHfAPYA-IB9gH6AQAAikBFwEYgJEAkwBQYCoAKuAXMAvQBigDaAG4AOIAe0BDoCRAE0gJ2AUOAo8BSIC2AFwALkAXYAu8BhoDJAGTgMuAZmAzmBq4GsgNvAbmFABgCKAXQBI0IAQAA2ACQAjgBKQCdgGiAP6AmUBNgCkAFiALcAX-AwIBtQDhAwAIBNgDahAAMAEgCbAG1CgAQCbAG1DAAQCbAG1DoIQAC4AKAAqABwAEEALgAvgBkAGoAPAAmABTACqAFwAMQAbwA_QCGAIgATQAowBWgDAAGGAMoAaIA2QB3wD2APiAfYB-wEUARiAjoCTAFBgKiAq4BYgC5gF5AMUAbQA3ABxAD2gH2AQ6Ai8BIgCaQE7AKHAUeAqwBYoC2AFugLgAXJAuwC7QF3gMNAY9AyMDJAGTgMqgZYBlwDMwGcwNXA1gBt4D-wI7DwAwAPwBFAERAIyAugCRo4AiACQAKAAfAByAEcAJSATsAzIB_QE2ALEAWyAtwBf4DaoG5gboA4QhAeAAWABQAFwANQAqgBcADEAG8APwAwIB3AHeARQAlIBQYCogKuAXMAxQBtAEOgJpAVYAsUBaIC4AFyALsAZGAycBnID-yIAIAjICYiAAkAB4A5ACOAGZATYAsQBngDagG6EoEQACwAKAAcAB4AEwAKoAXAAxQCGAIkAUYArQBgADKAGiANkAd8A_AD9AIsARgAjoBJQCgwFRAVcAuYBeQDaAG4AOIAe0A-wCHQEXgJEATSAnYBQ4CkwFNAKsAWKAtgBcAC5IF2AXaAw2BkYGSAMngZYBlwDOYGsAayA28B_YEdioAMABQCZQF0FAB4AJAAZABQAC2AOQAfYBBwCOAEpAQgAmwBUgC3AGeQNzA3QtALABqAMAAdwBegD7AKHAU0AqwBcAC7AGZgAAA.f_wAAAAAAAAA";
+
+    #[test_case(0 => RestrictionType::NotAllowed)]
+    #[test_case(1 => RestrictionType::RequireConsent)]
+    #[test_case(2 => RestrictionType::RequireLegitimateInterest)]
+    #[test_case(3 => RestrictionType::Undefined)]
+    #[test_case(4 => RestrictionType::Undefined ; "out of range")]
+    #[test_case(u8::MAX => RestrictionType::Undefined ; "max")]
+    fn restriction_type_from_u8(value: u8) -> RestrictionType {
+        RestrictionType::from_u8(value).unwrap_or(RestrictionType::Undefined)
+    }
+
     #[test_case("CPX" => matches SectionDecodeError::Read { .. } ; "decode error")]
     #[test_case("" => matches SectionDecodeError::Read { .. } ; "empty string")]
-    #[test_case("IFoEUQQgAIQwgIwQABAEAAAAOIAACAIAAAAQAIAgEAACEAAAAAgAQBAAAAAAAGBAAgAAAAAAAFAAECAAAgAAQARAEQAAAAAJAAIAAgAAAYQEAAAQmAgBC3ZAYzUw" => matches SectionDecodeError::UnknownSegmentVersion { .. } ; "disclosed vendors only")]
-    #[test_case("ZAAgH9794ulA" => matches SectionDecodeError::UnknownSegmentVersion { .. } ; "publisher purposes only")]
-    #[test_case("IFoEUQQgAIQwgIwQABAEAAAAOIAACAIAAAAQAIAgEAACEAAAAAgAQBAAAAAAAGBAAgAAAAAAAFAAECAAAgAAQARAEQAAAAAJAAIAAgAAAYQEAAAQmAgBC3ZAYzUw.ZAAgH9794ulA" => matches SectionDecodeError::UnknownSegmentVersion { .. } ; "disclosed vendors and publisher purposes")]
-    #[test_case("ZAAgH9794ulA.IFoEUQQgAIQwgIwQABAEAAAAOIAACAIAAAAQAIAgEAACEAAAAAgAQBAAAAAAAGBAAgAAAAAAAFAAECAAAgAAQARAEQAAAAAJAAIAAgAAAYQEAAAQmAgBC3ZAYzUw" => matches SectionDecodeError::UnknownSegmentVersion { .. } ; "publisher purposes and disclosed vendors")]
+    #[test_case("IFoEUQQgAIQwgIwQABAEAAAAOIAACAIAAAAQAIAgEAACEAAAAAgAQBAAAAAAAGBAAgAAAAAAAFAAECAAAgAAQARAEQAAAAAJAAIAAgAAAYQEAAAQmAgBC3ZAYzUw" => matches SectionDecodeError::MissingCoreSegment { segment_type: 1 } ; "disclosed vendors only")]
+    #[test_case("ZAAgH9794ulA" => matches SectionDecodeError::MissingCoreSegment { segment_type: 3 } ; "publisher purposes only")]
+    #[test_case("IFoEUQQgAIQwgIwQABAEAAAAOIAACAIAAAAQAIAgEAACEAAAAAgAQBAAAAAAAGBAAgAAAAAAAFAAECAAAgAAQARAEQAAAAAJAAIAAgAAAYQEAAAQmAgBC3ZAYzUw.ZAAgH9794ulA" => matches SectionDecodeError::MissingCoreSegment { segment_type: 1 } ; "disclosed vendors and publisher purposes")]
+    #[test_case("ZAAgH9794ulA.IFoEUQQgAIQwgIwQABAEAAAAOIAACAIAAAAQAIAgEAACEAAAAAgAQBAAAAAAAGBAAgAAAAAAAFAAECAAAgAAQARAEQAAAAAJAAIAAgAAAYQEAAAQmAgBC3ZAYzUw" => matches SectionDecodeError::MissingCoreSegment { segment_type: 3 } ; "publisher purposes and disclosed vendors")]
+    #[test_case("DPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA" => matches SectionDecodeError::UnknownSegmentVersion { segment_version: 3 } ; "unknown core version")]
     fn error(s: &str) -> SectionDecodeError {
         TcfEuV2::from_str(s).unwrap_err()
     }
 
+    #[test_case("CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA" => matches Ok(31) ; "core only")]
+    #[test_case("COvFyGBOvFyGBAbAAAENAPCAAOAAAAAAAAAAAEEUACCKAAA.ZAAgH9794ulA" => matches Ok(27) ; "with optional segment")]
+    #[test_case("CPXxRfAPXxRfAAfK" => matches Ok(31) ; "truncated after cmp id")]
+    #[test_case("CPXxRfAPXxRfAA" => matches Err(SectionDecodeError::Read { .. }) ; "truncated before cmp id")]
+    #[test_case("DPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA" => matches Err(SectionDecodeError::UnknownSegmentVersion { segment_version: 3 }) ; "unknown core version")]
+    #[test_case("" => matches Err(SectionDecodeError::Read { .. }) ; "empty string")]
+    fn peek_cmp_id(s: &str) -> Result<u16, SectionDecodeError> {
+        TcfEuV2::peek_cmp_id(s)
+    }
+
+    // "4AAA" is a segment of type 7, which is not defined
+    #[test_case("CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA.4AAA" => (None, 1) ; "unknown only")]
+    #[test_case("CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA.4AAA.IAAA" => (Some(IdSet::new()), 1) ; "before known")]
+    #[test_case("CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA.IAAA.4AAA.4AAA" => (Some(IdSet::new()), 2) ; "repeated")]
+    fn skip_unknown_segments(s: &str) -> (Option<IdSet>, usize) {
+        assert!(matches!(
+            TcfEuV2::from_str(s),
+            Err(SectionDecodeError::UnknownSegmentType { segment_type: 7 })
+        ));
+
+        let ctx = DecodeContext::new(DecodeOptions::new().with_skip_unknown_segments());
+        let r = TcfEuV2::decode_str(s, &ctx);
+        let warnings = ctx.into_warnings();
+        assert!(
+            warnings
+                .iter()
+                .all(|w| *w == DecodeWarning::UnknownSegmentSkipped { segment_type: 7 })
+        );
+        (r.unwrap().disclosed_vendors, warnings.len())
+    }
+
+    #[test_case("CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA" ; "core only")]
+    #[test_case("CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA.IAAA.QAAA" ; "empty vendor segments")]
+    #[test_case("COvFyGBOvFyGBAbAAAENAPCAAOAAAAAAAAAAAEEUACCKAAA.ZAAgH9794ulA" ; "publisher purposes")]
+    #[test_case(&format!("{CORE}.{DISCLOSED_VENDORS}.{ALLOWED_VENDORS}.{PUBLISHER_PURPOSES}") ; "all segments")]
+    fn encoded_bit_len(s: &str) {
+        let tcf = TcfEuV2::from_str(s).unwrap();
+        let lens = tcf.segment_bit_lens();
+
+        // encoders pad every segment to whole bytes, then to whole Base64 characters
+        let segments = s.split('.').map(str::len).collect::<Vec<_>>();
+        let chars = lens
+            .iter()
+            .map(|bits| (bits.div_ceil(8) * 8).div_ceil(6))
+            .collect::<Vec<_>>();
+        assert_eq!(chars, segments);
+        assert_eq!(tcf.encoded_bit_len(), lens.iter().sum::<usize>());
+    }
+
+    #[test_case("CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA" => matches Ok(1650492000) ; "core only")]
+    #[test_case("CPXxRfAPXxRfA" => matches Ok(1650492000) ; "truncated after created")]
+    #[test_case("CPXxRf" => matches Err(SectionDecodeError::Read { .. }) ; "truncated before end of created")]
+    #[test_case("DPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA" => matches Err(SectionDecodeError::UnknownSegmentVersion { segment_version: 3 }) ; "unknown core version")]
+    fn peek_created(s: &str) -> Result<u64, SectionDecodeError> {
+        TcfEuV2::peek_created(s)
+    }
+
+    const CORE: &str = "COvFyGBOvFyGBAbAAAENAPCAAOAAAAAAAAAAAEEUACCKAAA";
+    const DISCLOSED_VENDORS: &str = "IFoEUQQgAIQwgIwQABAEAAAAOIAACAIAAAAQAIAgEAACEAAAAAgAQBAAAAAAAGBAAgAAAAAAAFAAECAAAgAAQARAEQAAAAAJAAIAAgAAAYQEAAAQmAgBC3ZAYzUw";
+    const ALLOWED_VENDORS: &str = "QFoEUQQgAIQwgIwQABAEAAAAOIAACAIAAAAQAIAgEAACEAAAAAgAQBAAAAAAAGBAAgAAAAAAAFAAECAAAgAAQARAEQAAAAAJAAIAAgAAAYQEAAAQmAgBC3ZAYzUw";
+    const PUBLISHER_PURPOSES: &str = "ZAAgH9794ulA";
+
+    #[test_case(&[1] ; "1")]
+    #[test_case(&[2] ; "2")]
+    #[test_case(&[3] ; "3")]
+    #[test_case(&[1, 2] ; "1 2")]
+    #[test_case(&[1, 3] ; "1 3")]
+    #[test_case(&[2, 1] ; "2 1")]
+    #[test_case(&[2, 3] ; "2 3")]
+    #[test_case(&[3, 1] ; "3 1")]
+    #[test_case(&[3, 2] ; "3 2")]
+    #[test_case(&[1, 2, 3] ; "1 2 3")]
+    #[test_case(&[1, 3, 2] ; "1 3 2")]
+    #[test_case(&[2, 1, 3] ; "2 1 3")]
+    #[test_case(&[2, 3, 1] ; "2 3 1")]
+    #[test_case(&[3, 1, 2] ; "3 1 2")]
+    #[test_case(&[3, 2, 1] ; "3 2 1")]
+    fn optional_segments_in_any_order(segment_types: &[u8]) {
+        let segments = segment_types.iter().map(|t| match t {
+            1 => DISCLOSED_VENDORS,
+            2 => ALLOWED_VENDORS,
+            _ => PUBLISHER_PURPOSES,
+        });
+        let s = std::iter::once(CORE)
+            .chain(segments)
+            .collect::<Vec<_>>()
+            .join(".");
+        let tcf = TcfEuV2::from_str(&s).unwrap();
+        assert_eq!(tcf.disclosed_vendors.is_some(), segment_types.contains(&1));
+        assert_eq!(tcf.allowed_vendors.is_some(), segment_types.contains(&2));
+        assert_eq!(tcf.publisher_purposes.is_some(), segment_types.contains(&3));
+
+        let segment = |t: u8, s: &str| {
+            segment_types
+                .contains(&t)
+                .then(|| TcfEuV2::from_str(&format!("{CORE}.{s}")).unwrap())
+        };
+        assert_eq!(
+            tcf.disclosed_vendors,
+            segment(1, DISCLOSED_VENDORS).and_then(|t| t.disclosed_vendors)
+        );
+        assert_eq!(
+            tcf.allowed_vendors,
+            segment(2, ALLOWED_VENDORS).and_then(|t| t.allowed_vendors)
+        );
+        assert_eq!(
+            tcf.publisher_purposes,
+            segment(3, PUBLISHER_PURPOSES).and_then(|t| t.publisher_purposes)
+        );
+    }
+
     #[test]
     fn decode_eu_v2_legacy_sample() {
-        let _ = TcfEuV2::from_str(
-            "CQaXJQAQaXJQAAGABCENCCFsAP_gAEPgAAiQKmNR_G_fbXlj8TZ36ftkeYxf99hjrsQxBgaJk24FyJvW7JwW32EzNAzapqYKmRIAu1BBAQNlGIDURUCgKIgVqTDMaESEoTNKJ6BEgBMRA2JYCFxvmwBDWQCY5tp9dld5mB-N7dr8ydzyy4BHn3I5XsS1WBAAAAAAAAAAAAAAAQAAgAAAgAAAAAAAAAAAABAAEAAAIAAAAAACAAAAAAAAAAAAAAAAAACAAAAAQSNgfgAKgAcAB4AFwAVAAuAB-AF0ANAAfABCACKAEcAMsAc4A7gCAQEHAQgAiMBGQEaAI4ASIAn4BUACxAF6AMUAa8A6QB2wD_gIQAR6AlYBMUCZAJlATbApACkQFJgKyAV2AsIBagC4AFxALmAXRAvIC8wF9AMQAYsAyEBkYDRgGmgNTAa8A2gBtgDbgG6AN-AgmBI0BQJA5AAXABQAFQALgAcAA8ACAAF8AMgA1AB4AEwAKoAbwA_QCGAIkATQArQBgADDgGUAZYA2YB3AHfAPYA-IB9gH6AQAAikBFwEYgJEAkwBQYCoAKuAXMAvQBigDaAG4AOIAe0BDoCRAE0gJ2AUOAo8BSIC2AFwALkAXYAu8BhoDJAGTgMuAZmAzmBq4GsgNvAbmFABgCKAXQBI0IAQAA2ACQAjgBKQCdgGiAP6AmUBNgCkAFiALcAX-AwIBtQDhAwAIBNgDahAAMAEgCbAG1CgAQCbAG1DAAQCbAG1DoIQAC4AKAAqABwAEEALgAvgBkAGoAPAAmABTACqAFwAMQAbwA_QCGAIgATQAowBWgDAAGGAMoAaIA2QB3wD2APiAfYB-wEUARiAjoCTAFBgKiAq4BYgC5gF5AMUAbQA3ABxAD2gH2AQ6Ai8BIgCaQE7AKHAUeAqwBYoC2AFugLgAXJAuwC7QF3gMNAY9AyMDJAGTgMqgZYBlwDMwGcwNXA1gBt4D-wI7DwAwAPwBFAERAIyAugCRo4AiACQAKAAfAByAEcAJSATsAzIB_QE2ALEAWyAtwBf4DaoG5gboA4QhAeAAWABQAFwANQAqgBcADEAG8APwAwIB3AHeARQAlIBQYCogKuAXMAxQBtAEOgJpAVYAsUBaIC4AFyALsAZGAycBnID-yIAIAjICYiAAkAB4A5ACOAGZATYAsQBngDagG6EoEQACwAKAAcAB4AEwAKoAXAAxQCGAIkAUYArQBgADKAGiANkAd8A_AD9AIsARgAjoBJQCgwFRAVcAuYBeQDaAG4AOIAe0A-wCHQEXgJEATSAnYBQ4CkwFNAKsAWKAtgBcAC5IF2AXaAw2BkYGSAMngZYBlwDOYGsAayA28B_YEdioAMABQCZQF0FAB4AJAAZABQAC2AOQAfYBBwCOAEpAQgAmwBUgC3AGeQNzA3QtALABqAMAAdwBegD7AKHAU0AqwBcAC7AGZgAAA.f_wAAAAAAAAA",
-        )
-        .unwrap();
+        let _ = TcfEuV2::from_str(LEGACY_SAMPLE).unwrap();
+    }
+
+    #[test]
+    fn legacy_sample_warnings() {
+        let ctx = DecodeContext::default();
+        let r = TcfEuV2::decode_str(LEGACY_SAMPLE, &ctx);
+        let warnings = ctx.into_warnings();
+
+        assert_eq!(r.unwrap().core.publisher_restrictions.len(), 14);
+        assert_eq!(
+            warnings,
+            [DecodeWarning::TruncatedPublisherRestrictions {
+                declared: 20,
+                decoded: 14,
+            }]
+        );
+    }
+
+    // 12-bit restriction count, with no restriction after it
+    #[test_case("AA" => (0, vec![]) ; "none declared")]
+    #[test_case("AD" => (0, vec![DecodeWarning::TruncatedPublisherRestrictions { declared: 3, decoded: 0 }]) ; "three declared")]
+    #[test_case("_-" => (0, vec![DecodeWarning::TruncatedPublisherRestrictions { declared: 4094, decoded: 0 }]) ; "many declared")]
+    fn publisher_restrictions_count_only(s: &str) -> (usize, Vec<DecodeWarning>) {
+        let ctx = DecodeContext::default();
+        let r =
+            parse_publisher_restrictions(&mut crate::core::base64_bit_reader(s.as_bytes()), &ctx);
+        (r.unwrap().len(), ctx.into_warnings())
+    }
+
+    // two restrictions declared, the first one is complete (purpose 1, type 1, vendor 5) and
+    // the bits end at various points of the second one
+    #[test_case("" => Some(1) ; "before purpose id")]
+    #[test_case("000010" => Some(1) ; "after purpose id")]
+    #[test_case("000010 01" => Some(1) ; "after restriction type")]
+    #[test_case("000010 01 000000000001" => Some(1) ; "after entry count")]
+    #[test_case("000010 01 000000000001 1" => Some(1) ; "after group flag")]
+    #[test_case("000010 01 000000000001 1 0000000000000011" => Some(1) ; "after group start")]
+    #[test_case("000010 01 000000000001 1 0000000000000011 0000000000000100" => Some(2) ; "complete")]
+    fn publisher_restrictions_truncated(second: &str) -> Option<usize> {
+        let bits = format!("000000000010 000001 01 000000000001 0 0000000000000101 {second}");
+        let ctx = DecodeContext::default();
+        let restrictions = parse_bits(&bits, &ctx).ok()?;
+        let warnings = ctx.into_warnings();
+
+        assert_eq!(restrictions[0].restricted_vendor_ids, IdSet::from([5]));
+        if restrictions.len() < 2 {
+            assert_eq!(
+                warnings,
+                [DecodeWarning::TruncatedPublisherRestrictions {
+                    declared: 2,
+                    decoded: 1,
+                }]
+            );
+        } else {
+            assert_eq!(restrictions[1].restricted_vendor_ids, IdSet::from([3, 4]));
+            assert!(warnings.is_empty());
+        }
+        Some(restrictions.len())
+    }
+
+    #[test_case("000000000001 000001 01 000000000001 0 00000000" ; "in single id")]
+    #[test_case("000000000001 000001 01 000000000001 1 0000000000000011" ; "after group start")]
+    fn first_publisher_restriction_truncated(bits: &str) {
+        let e = parse_bits(bits, &DecodeContext::default()).unwrap_err();
+        assert!(matches!(e, SectionDecodeError::Read { .. }));
+    }
+
+    // one restriction of `count` ranges of ids 1 to 65535, up to 4095 amplifies a short
+    // string into over 268 million inserts
+    #[test_case(1, 65535 => matches Ok(_) ; "single range")]
+    #[test_case(2, 131070 => matches Ok(_) ; "two ranges")]
+    #[test_case(2, 131069 => matches Err(SectionDecodeError::SectionTooLarge { max: 131069 }) ; "just over")]
+    #[test_case(4095, 1_000_000 => matches Err(SectionDecodeError::SectionTooLarge { max: 1_000_000 }) ; "amplification")]
+    fn publisher_restrictions_max_range_inserts(
+        count: usize,
+        max: usize,
+    ) -> Result<Vec<PublisherRestriction>, SectionDecodeError> {
+        let bits = format!(
+            "000000000001 000001 01 {count:012b} {}",
+            "1 0000000000000001 1111111111111111 ".repeat(count)
+        );
+        let ctx = DecodeContext::new(DecodeOptions::new().with_max_range_inserts(max));
+        parse_bits(&bits, &ctx)
+    }
+
+    #[test_case(4095, None => matches Err(SectionDecodeError::SectionTooLarge { max: crate::sections::DEFAULT_MAX_RANGE_INSERTS }) ; "amplification")]
+    #[test_case(17, None => matches Err(SectionDecodeError::SectionTooLarge { .. }) ; "just over")]
+    #[test_case(16, None => matches Ok(_) ; "at the default")]
+    #[test_case(17, Some(17 * 65535) => matches Ok(_) ; "raised")]
+    fn max_range_inserts_default(
+        count: usize,
+        max: Option<usize>,
+    ) -> Result<TcfEuV2, SectionDecodeError> {
+        // the sample core without its empty publisher restrictions, then one restriction of
+        // `count` ranges of ids 1 to 65535
+        let core = TcfEuV2::from_str(CORE).unwrap().core;
+        let mut r = base64_bit_reader(CORE.as_bytes());
+        let mut bits = (0..core.encoded_bit_len() - 12)
+            .map(|_| if r.read_bit().unwrap() { '1' } else { '0' })
+            .collect::<String>();
+        bits += &format!("000000000001 000001 01 {count:012b} ");
+        bits += &"1 0000000000000001 1111111111111111 ".repeat(count);
+        let s = base64_from_bits(&bits);
+
+        match max {
+            None => TcfEuV2::from_str(&s),
+            Some(max) => DecodeOptions::new().with_max_range_inserts(max).decode(&s),
+        }
+    }
+
+    /// Encodes a string of `0` and `1` digits, ignoring spaces, as URL-safe Base64 with the
+    /// last character padded with zero bits.
+    fn base64_from_bits(bits: &str) -> String {
+        const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+        let mut digits = bits.replace(' ', "");
+        while !digits.len().is_multiple_of(6) {
+            digits.push('0');
+        }
+        digits
+            .as_bytes()
+            .chunks(6)
+            .map(|c| {
+                ALPHABET[usize::from_str_radix(std::str::from_utf8(c).unwrap(), 2).unwrap()] as char
+            })
+            .collect()
+    }
+
+    #[test]
+    fn max_range_inserts_is_cumulative() {
+        // two restrictions with a single range of 3 ids each
+        let restriction = "000001 01 000000000001 1 0000000000000001 0000000000000011";
+        let bits = format!("000000000010 {restriction} {restriction}");
+        let decode = |max| {
+            let ctx = DecodeContext::new(DecodeOptions::new().with_max_range_inserts(max));
+            parse_bits(&bits, &ctx)
+        };
+
+        assert_eq!(decode(6).unwrap().len(), 2);
+        assert!(matches!(
+            decode(5),
+            Err(SectionDecodeError::SectionTooLarge { max: 5 })
+        ));
+    }
+
+    /// Returns a serialized TCF EU v2 section with all segments, and a misspelled field added
+    /// to the object at `pointer`.
+    #[cfg(feature = "serde")]
+    fn tcf_json_with_typo(pointer: &str) -> serde_json::Value {
+        let mut json = tcf_json();
+        let o = json.pointer_mut(pointer).unwrap().as_object_mut().unwrap();
+        o.insert("vendr_consents".to_string(), serde_json::Value::Bool(true));
+        json
+    }
+
+    #[cfg(feature = "serde")]
+    fn tcf_json() -> serde_json::Value {
+        let tcf =
+            TcfEuV2::from_str("CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA.YAAAAAAAAA").unwrap();
+        serde_json::to_value(&tcf).unwrap()
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn deserialize_known_fields() {
+        let json = tcf_json();
+        let tcf: TcfEuV2 = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&tcf).unwrap(), json);
+    }
+
+    #[cfg(feature = "serde-strict")]
+    #[test_case("" ; "section")]
+    #[test_case("/core" ; "core")]
+    #[test_case("/publisher_purposes" ; "publisher purposes")]
+    fn deserialize_unknown_field_rejected(pointer: &str) {
+        let e = serde_json::from_value::<TcfEuV2>(tcf_json_with_typo(pointer)).unwrap_err();
+        assert!(
+            e.to_string().contains("unknown field `vendr_consents`"),
+            "{e}"
+        );
+    }
+
+    #[cfg(all(feature = "serde", not(feature = "serde-strict")))]
+    #[test_case("" ; "section")]
+    #[test_case("/core" ; "core")]
+    #[test_case("/publisher_purposes" ; "publisher purposes")]
+    fn deserialize_unknown_field_ignored(pointer: &str) {
+        assert!(serde_json::from_value::<TcfEuV2>(tcf_json_with_typo(pointer)).is_ok());
     }
 
+    /// Parses publisher restrictions from literal binary digits. Since readers work on whole
+    /// bytes, zero bits are prepended and skipped so that the input ends exactly after the
+    /// last digit.
+    fn parse_bits(
+        bits: &str,
+        ctx: &DecodeContext,
+    ) -> Result<Vec<PublisherRestriction>, SectionDecodeError> {
+        use bitstream_io::{BigEndian, BitReader};
+
+        let digits = bits.replace(' ', "");
+        let skipped = digits.len().next_multiple_of(8) - digits.len();
+        let digits = "0".repeat(skipped) + &digits;
+        let bytes = digits
+            .as_bytes()
+            .chunks(8)
+            .map(|byte| u8::from_str_radix(std::str::from_utf8(byte).unwrap(), 2).unwrap())
+            .collect::<Vec<_>>();
+
+        let mut r = BitReader::endian(&bytes[..], BigEndian);
+        r.skip(skipped as u32)?;
+        parse_publisher_restrictions(&mut r, ctx)
+    }
+
+    // consents {1}, legitimate interests {2}, then the custom purpose count, custom bitfields,
+    // and a 6-bit sentinel of ones which must be the next thing read
+    #[test_case("gAAAQAAADr_" => (IdSet::from([1, 3]), IdSet::from([2, 3])) ; "3 custom purposes")]
+    #[test_case("gAAAQAAAA_" => (IdSet::new(), IdSet::new()) ; "no custom purposes")]
+    fn publisher_purposes_custom_count(s: &str) -> (IdSet, IdSet) {
+        let mut r = crate::core::base64_bit_reader(s.as_bytes());
+        let p: PublisherPurposes = r.parse().unwrap();
+
+        assert_eq!(p.consents, IdSet::from([1]));
+        assert_eq!(p.legitimate_interests, IdSet::from([2]));
+        // both custom bitfields share the same count, so they end right before the sentinel
+        assert_eq!(r.read_unsigned::<6, u8>().unwrap(), 0b11_1111);
+
+        (p.custom_consents, p.custom_legitimate_interests)
+    }
+
+    #[test]
+    fn decode_from_bits() {
+        let s = "CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA";
+        let mut r = crate::core::base64_bit_reader(s.as_bytes());
+        let tcf: TcfEuV2 = crate::sections::decode_section_from_bits(&mut r).unwrap();
+        assert_eq!(tcf, TcfEuV2::from_str(s).unwrap());
+    }
+
+    #[cfg(feature = "bitfield-debug")]
+    #[test]
+    fn core_bitfield_debug() {
+        let dump = Core::as_bitfield_debug("CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA").unwrap();
+        let lines = dump.lines().collect::<Vec<_>>();
+
+        assert_eq!(lines.len(), 19);
+        assert_eq!(lines[0], "     0     6  version                       2");
+        assert_eq!(lines[3], "    78    12  cmp_id                        31");
+        assert_eq!(
+            lines[6],
+            "   108    12  consent_language              \"EN\""
+        );
+        assert_eq!(lines[18], "   247    12  publisher_restrictions        []");
+    }
+
+    #[cfg(feature = "provenance")]
+    #[test]
+    fn core_decode_traced() {
+        let s = "CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA";
+        let (core, fields) = Core::decode_traced(s).unwrap();
+
+        assert_eq!(core, TcfEuV2::from_str(s).unwrap().core);
+        assert_eq!(fields.len(), 19);
+        assert_eq!(fields[0].name, "version");
+        let cmp_id = fields[3];
+        assert_eq!(
+            (cmp_id.name, cmp_id.offset, cmp_id.width),
+            ("cmp_id", 78, 12)
+        );
+        for w in fields.windows(2) {
+            assert_eq!(w[0].offset + w[0].width, w[1].offset);
+        }
+        assert_eq!(
+            fields.iter().map(|f| f.width).sum::<u64>() as usize,
+            TcfEuV2::from_str(s).unwrap().encoded_bit_len()
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn to_tcdata() {
+        let tcf = TcfEuV2::from_str(LEGACY_SAMPLE).unwrap();
+        let tcdata = serde_json::to_value(tcf.to_tcdata()).unwrap();
+
+        assert_eq!(tcdata["tcfPolicyVersion"], tcf.core.policy_version);
+        assert_eq!(tcdata["useNonStandardTexts"], false);
+        assert_eq!(tcdata["purpose"]["consents"].as_object().unwrap().len(), 11);
+        assert_eq!(tcdata["purpose"]["consents"]["1"], true);
+        assert_eq!(tcdata["purpose"]["legitimateInterests"]["1"], false);
+        assert_eq!(tcdata["specialFeatureOptins"].as_object().unwrap().len(), 2);
+
+        let vendor_consents = tcdata["vendor"]["consents"].as_object().unwrap();
+        let max_vendor_id = tcf
+            .core
+            .vendor_consents
+            .last()
+            .max(tcf.core.vendor_legitimate_interests.last())
+            .copied()
+            .unwrap();
+        assert_eq!(vendor_consents.len(), max_vendor_id as usize);
+        assert_eq!(
+            vendor_consents.values().filter(|v| v == &true).count(),
+            tcf.core.vendor_consents.len()
+        );
+
+        let restriction = &tcf.core.publisher_restrictions[0];
+        let vendor_id = restriction.restricted_vendor_ids.first().unwrap();
+        let vendors = &tcdata["publisher"]["restrictions"][restriction.purpose_id.to_string()];
+        let restriction_type = vendors[vendor_id.to_string()].as_u64().unwrap();
+        assert_eq!(
+            RestrictionType::from_u64(restriction_type).as_ref(),
+            Some(&restriction.restriction_type)
+        );
+        assert!(tcdata["publisher"]["consents"].is_object());
+    }
+
+    #[test]
+    fn two_letter_codes() {
+        let tcf = TcfEuV2::from_str("CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA").unwrap();
+        assert_eq!(tcf.core.consent_language, "EN");
+        assert_eq!(tcf.core.publisher_country_code, "DE");
+        assert_eq!(tcf.core.consent_language.to_string(), "EN");
+    }
+
+    #[test]
+    fn invalid_publisher_country_code_is_kept() {
+        let tcf = TcfEuV2::from_str("CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAADX4AAAAAAAA").unwrap();
+        assert_eq!(tcf.core.publisher_country_code, "[\u{80}");
+        assert!(matches!(
+            tcf.validate()[..],
+            [ValidationWarning::InvalidPublisherCountryCode { .. }]
+        ));
+    }
+
+    #[test]
+    fn purposes_consented() {
+        let tcf = TcfEuV2::from_str(LEGACY_SAMPLE).unwrap();
+        assert_eq!(
+            tcf.core.purposes_consented(),
+            vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11]
+        );
+        assert_eq!(
+            tcf.core.purposes_legitimate_interests(),
+            vec![2, 7, 8, 9, 10, 11]
+        );
+    }
+
+    #[test_case(&[] => Vec::<SpecialFeature>::new() ; "none")]
+    #[test_case(&[1] => vec![SpecialFeature::PreciseGeolocation] ; "geolocation")]
+    #[test_case(&[2, 1] => vec![SpecialFeature::PreciseGeolocation, SpecialFeature::DeviceScanning] ; "both")]
+    #[test_case(&[2, 3, 12] => vec![SpecialFeature::DeviceScanning] ; "undefined ids")]
+    fn special_features(ids: &[u16]) -> Vec<SpecialFeature> {
+        let mut core = TcfEuV2::from_str("CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA")
+            .unwrap()
+            .core;
+        core.special_feature_optins = IdSet::from_iter(ids.iter().copied());
+        core.special_features()
+    }
+
+    #[test]
+    fn summarize() {
+        let tcf = TcfEuV2::from_str(LEGACY_SAMPLE).unwrap();
+        let summary = tcf.core.summarize();
+        assert_eq!(summary.created, tcf.core.created);
+        assert_eq!(summary.cmp_id, tcf.core.cmp_id);
+        assert_eq!(summary.vendor_list_version, tcf.core.vendor_list_version);
+        assert_eq!(summary.purpose_consents, 0b111_1111_1111);
+        assert!(summary.has_purpose_consent(1));
+        assert!(summary.has_purpose_consent(11));
+        assert!(!summary.has_purpose_consent(12));
+        assert!(!summary.has_purpose_consent(0));
+    }
+
+    #[test]
+    fn purpose_masks() {
+        let tcf = TcfEuV2::from_str(LEGACY_SAMPLE).unwrap();
+        assert_eq!(tcf.core.purpose_consents_mask(), 0b111_1111_1111);
+        assert_eq!(
+            tcf.core.purpose_legitimate_interests_mask(),
+            0b111_1100_0010
+        );
+
+        // the bitfields start at bit 152 of the core segment with purpose 1, so they read as
+        // the masks with their 24 bits reversed
+        let mut r = base64_bit_reader(LEGACY_SAMPLE.as_bytes());
+        r.skip(152).unwrap();
+        let consents = r.read_unsigned::<24, u32>().unwrap();
+        let legitimate_interests = r.read_unsigned::<24, u32>().unwrap();
+        assert_eq!(
+            tcf.core.purpose_consents_mask(),
+            consents.reverse_bits() >> 8
+        );
+        assert_eq!(
+            tcf.core.purpose_legitimate_interests_mask(),
+            legitimate_interests.reverse_bits() >> 8
+        );
+    }
+
+    #[test]
+    fn purpose_masks_ignore_out_of_range_ids() {
+        let mut core = TcfEuV2::from_str("CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA")
+            .unwrap()
+            .core;
+        core.purpose_consents = IdSet::from([0, 1, 24, 25]);
+        core.purpose_legitimate_interests = IdSet::new();
+
+        assert_eq!(core.purpose_consents_mask(), 1 | 1 << 23);
+        assert_eq!(core.purpose_legitimate_interests_mask(), 0);
+    }
+
+    #[test_case("CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA" => None ; "absent")]
+    #[test_case("CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA.IAAA" => Some(IdSet::new()) ; "present but empty")]
+    #[test_case("CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA.IAAA.QAAA" => Some(IdSet::new()) ; "present but empty with allowed vendors")]
+    fn disclosed_vendors(s: &str) -> Option<IdSet> {
+        TcfEuV2::from_str(s).unwrap().disclosed_vendors
+    }
+
+    fn core_with_signals(restrictions: Vec<PublisherRestriction>) -> Core {
+        let mut core = TcfEuV2::from_str("CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA")
+            .unwrap()
+            .core;
+        core.purpose_consents = IdSet::from([1, 2, 3]);
+        core.purpose_legitimate_interests = IdSet::from([2, 7]);
+        core.vendor_consents = IdSet::from([10, 20]);
+        core.vendor_legitimate_interests = IdSet::from([20, 30]);
+        core.publisher_restrictions = restrictions;
+        core
+    }
+
+    fn restriction(
+        purpose_id: u8,
+        restriction_type: RestrictionType,
+        vendors: &[u16],
+    ) -> PublisherRestriction {
+        PublisherRestriction {
+            purpose_id,
+            restriction_type,
+            restricted_vendor_ids: IdSet::from_iter(vendors.iter().copied()),
+        }
+    }
+
+    #[test]
+    fn restrictions_by_purpose() {
+        let core = core_with_signals(vec![
+            restriction(3, RestrictionType::NotAllowed, &[10]),
+            restriction(1, RestrictionType::RequireConsent, &[20]),
+            restriction(3, RestrictionType::RequireLegitimateInterest, &[30]),
+        ]);
+        let by_purpose = core.restrictions_by_purpose();
+
+        assert_eq!(by_purpose.keys().copied().collect::<Vec<_>>(), [1, 3]);
+        assert_eq!(by_purpose[&1], [&core.publisher_restrictions[1]]);
+        assert_eq!(
+            by_purpose[&3],
+            [
+                &core.publisher_restrictions[0],
+                &core.publisher_restrictions[2]
+            ]
+        );
+    }
+
+    #[test_case(2, 10, LegalBasis::Consent => true ; "consent")]
+    #[test_case(2, 30, LegalBasis::Consent => false ; "no vendor consent")]
+    #[test_case(4, 10, LegalBasis::Consent => false ; "no purpose consent")]
+    #[test_case(2, 20, LegalBasis::LegitimateInterest => true ; "legitimate interest")]
+    #[test_case(2, 10, LegalBasis::LegitimateInterest => false ; "no vendor legitimate interest")]
+    #[test_case(3, 20, LegalBasis::LegitimateInterest => false ; "no purpose legitimate interest")]
+    fn purpose_allowed_without_restrictions(
+        purpose_id: u8,
+        vendor_id: u16,
+        legal_basis: LegalBasis,
+    ) -> bool {
+        core_with_signals(vec![]).is_purpose_allowed_for_vendor(purpose_id, vendor_id, legal_basis)
+    }
+
+    #[test_case(RestrictionType::NotAllowed, 2, 20, LegalBasis::Consent => false ; "not allowed")]
+    #[test_case(RestrictionType::NotAllowed, 2, 10, LegalBasis::Consent => true ; "not allowed for another vendor")]
+    #[test_case(RestrictionType::RequireConsent, 2, 20, LegalBasis::LegitimateInterest => true ; "require consent with consent")]
+    #[test_case(RestrictionType::RequireConsent, 7, 20, LegalBasis::LegitimateInterest => false ; "require consent without purpose consent")]
+    #[test_case(RestrictionType::RequireLegitimateInterest, 2, 20, LegalBasis::Consent => true ; "require legitimate interest with legitimate interest")]
+    #[test_case(RestrictionType::RequireLegitimateInterest, 3, 20, LegalBasis::Consent => false ; "require legitimate interest without purpose legitimate interest")]
+    #[test_case(RestrictionType::Undefined, 2, 20, LegalBasis::Consent => true ; "undefined")]
+    fn purpose_allowed_with_restriction(
+        restriction_type: RestrictionType,
+        purpose_id: u8,
+        vendor_id: u16,
+        legal_basis: LegalBasis,
+    ) -> bool {
+        core_with_signals(vec![restriction(purpose_id, restriction_type, &[20])])
+            .is_purpose_allowed_for_vendor(purpose_id, vendor_id, legal_basis)
+    }
+
+    #[test_case(RestrictionType::NotAllowed, LegalBasis::Consent => false)]
+    #[test_case(RestrictionType::NotAllowed, LegalBasis::LegitimateInterest => false)]
+    #[test_case(RestrictionType::RequireConsent, LegalBasis::Consent => true)]
+    #[test_case(RestrictionType::RequireConsent, LegalBasis::LegitimateInterest => false)]
+    #[test_case(RestrictionType::RequireLegitimateInterest, LegalBasis::Consent => false)]
+    #[test_case(RestrictionType::RequireLegitimateInterest, LegalBasis::LegitimateInterest => true)]
+    #[test_case(RestrictionType::Undefined, LegalBasis::Consent => true)]
+    #[test_case(RestrictionType::Undefined, LegalBasis::LegitimateInterest => true)]
+    fn restriction_permits(restriction_type: RestrictionType, basis: LegalBasis) -> bool {
+        restriction_type.permits(basis)
+    }
+
+    #[test_case(None, 2, LegalBasis::Consent => Some(LegalBasis::Consent) ; "declared consent")]
+    #[test_case(None, 2, LegalBasis::LegitimateInterest => Some(LegalBasis::LegitimateInterest) ; "declared legitimate interest")]
+    #[test_case(Some(RestrictionType::NotAllowed), 2, LegalBasis::Consent => None ; "not allowed")]
+    #[test_case(Some(RestrictionType::RequireConsent), 2, LegalBasis::LegitimateInterest => Some(LegalBasis::Consent) ; "require consent")]
+    #[test_case(Some(RestrictionType::RequireLegitimateInterest), 2, LegalBasis::Consent => Some(LegalBasis::LegitimateInterest) ; "require legitimate interest")]
+    #[test_case(Some(RestrictionType::Undefined), 2, LegalBasis::Consent => Some(LegalBasis::Consent) ; "undefined")]
+    #[test_case(None, 1, LegalBasis::LegitimateInterest => None ; "purpose 1 legitimate interest")]
+    #[test_case(Some(RestrictionType::RequireLegitimateInterest), 1, LegalBasis::Consent => None ; "purpose 1 require legitimate interest")]
+    #[test_case(Some(RestrictionType::RequireConsent), 1, LegalBasis::LegitimateInterest => Some(LegalBasis::Consent) ; "purpose 1 require consent")]
+    fn resolve_legal_basis(
+        restriction_type: Option<RestrictionType>,
+        purpose_id: u8,
+        declared: LegalBasis,
+    ) -> Option<LegalBasis> {
+        let restrictions = restriction_type
+            .map(|t| vec![restriction(purpose_id, t, &[20])])
+            .unwrap_or_default();
+        core_with_signals(restrictions).resolve_legal_basis(purpose_id, 20, declared)
+    }
+
+    #[test]
+    fn purpose_one_treatment() {
+        let mut core = core_with_signals(vec![]);
+        assert_eq!(
+            core.resolve_legal_basis(1, 10, LegalBasis::Consent),
+            Some(LegalBasis::Consent)
+        );
+        assert!(core.is_purpose_allowed_for_vendor(1, 10, LegalBasis::Consent));
+
+        core.purpose_one_treatment = true;
+        assert_eq!(core.resolve_legal_basis(1, 10, LegalBasis::Consent), None);
+        assert!(!core.is_purpose_allowed_for_vendor(1, 10, LegalBasis::Consent));
+        assert!(core.is_purpose_allowed_for_vendor(2, 10, LegalBasis::Consent));
+    }
+
+    #[test]
+    fn restriction_for_another_purpose_does_not_apply() {
+        let core = core_with_signals(vec![restriction(3, RestrictionType::NotAllowed, &[20])]);
+        assert!(core.is_purpose_allowed_for_vendor(2, 20, LegalBasis::Consent));
+        assert!(!core.is_purpose_allowed_for_vendor(3, 20, LegalBasis::Consent));
+    }
+
+    #[test]
+    fn core_diff() {
+        let old = TcfEuV2::from_str(LEGACY_SAMPLE).unwrap().core;
+        let mut new = TcfEuV2::from_str(LEGACY_SAMPLE).unwrap().core;
+        assert!(old.diff(&new).is_empty());
+
+        new.purpose_consents.remove(&3);
+        let diff = old.diff(&new);
+        assert_eq!(
+            diff.purpose_consents,
+            IdSetDiff {
+                added: IdSet::new(),
+                removed: IdSet::from([3]),
+            }
+        );
+        assert_eq!(
+            diff,
+            CoreDiff {
+                purpose_consents: diff.purpose_consents.clone(),
+                ..Default::default()
+            }
+        );
+
+        new.policy_version += 1;
+        assert_eq!(
+            old.diff(&new).policy_version,
+            Some(FieldChange {
+                old: old.policy_version,
+                new: old.policy_version + 1,
+            })
+        );
+    }
+
+    #[test]
+    fn empty_optional_segments_are_distinct_from_absent_ones() {
+        let tcf = TcfEuV2::from_str("CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA.QAAA").unwrap();
+        assert_eq!(tcf.disclosed_vendors, None);
+        assert_eq!(tcf.allowed_vendors, Some(IdSet::new()));
+        assert_eq!(tcf.publisher_purposes, None);
+    }
 }
@@ -1,4 +1,4 @@
-use crate::sections::SectionDecodeError;
+use crate::sections::{DecodeContext, SectionDecodeError};
 use bitstream_io::BitRead;
 use iab_gpp_derive::FromBitStream;
 use num_derive::{FromPrimitive, ToPrimitive};
@@ -53,6 +53,7 @@ pub enum MspaMode {
 
 pub(crate) fn parse_mspa_covered_transaction<R: BitRead + ?Sized>(
     r: &mut R,
+    _ctx: &DecodeContext,
 ) -> Result<bool, SectionDecodeError> {
     let val = r.read_unsigned::<2, u8>()?;
     match val {
@@ -64,3 +65,61 @@ pub(crate) fn parse_mspa_covered_transaction<R: BitRead + ?Sized>(
         }),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitstream_io::read::FromBitStream;
+    use bitstream_io::{BigEndian, BitReader};
+    use test_case::test_case;
+
+    fn decode<T>(value: u8) -> T
+    where
+        T: FromBitStream,
+        T::Error: std::fmt::Debug + From<std::io::Error>,
+    {
+        BitReader::endian(&[value << 6][..], BigEndian)
+            .parse()
+            .unwrap()
+    }
+
+    #[test_case(0 => Notice::NotApplicable)]
+    #[test_case(1 => Notice::Provided)]
+    #[test_case(2 => Notice::NotProvided)]
+    #[test_case(3 => Notice::NotApplicable ; "out of range")]
+    fn notice(value: u8) -> Notice {
+        decode(value)
+    }
+
+    #[test_case(0 => OptOut::NotApplicable)]
+    #[test_case(1 => OptOut::OptedOut)]
+    #[test_case(2 => OptOut::DidNotOptOut)]
+    #[test_case(3 => OptOut::NotApplicable ; "out of range")]
+    fn opt_out(value: u8) -> OptOut {
+        decode(value)
+    }
+
+    #[test_case(0 => Consent::NotApplicable)]
+    #[test_case(1 => Consent::NoConsent)]
+    #[test_case(2 => Consent::Consent)]
+    #[test_case(3 => Consent::NotApplicable ; "out of range")]
+    fn consent(value: u8) -> Consent {
+        decode(value)
+    }
+
+    #[test_case(0 => MspaSupport::NotApplicable)]
+    #[test_case(1 => MspaSupport::Yes)]
+    #[test_case(2 => MspaSupport::No)]
+    #[test_case(3 => MspaSupport::NotApplicable ; "out of range")]
+    fn mspa_support(value: u8) -> MspaSupport {
+        decode(value)
+    }
+
+    #[test_case(0 => MspaMode::NotApplicable)]
+    #[test_case(1 => MspaMode::OptOutOption)]
+    #[test_case(2 => MspaMode::ServiceProvider)]
+    #[test_case(3 => MspaMode::NotApplicable ; "out of range")]
+    fn mspa_mode(value: u8) -> MspaMode {
+        decode(value)
+    }
+}
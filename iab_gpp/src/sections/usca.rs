@@ -7,6 +7,7 @@ use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Eq, PartialEq, GPPSection)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "test-util", derive(Default))]
 #[non_exhaustive]
 #[gpp(with_optional_segments(bits = 2))]
 pub struct UsCa {
@@ -17,6 +18,7 @@ pub struct UsCa {
 
 #[derive(Debug, Eq, PartialEq, FromBitStream)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "test-util", derive(Default))]
 #[non_exhaustive]
 #[gpp(section_version = 1)]
 pub struct Core {
@@ -36,6 +38,7 @@ pub struct Core {
 
 #[derive(Debug, Eq, PartialEq, FromBitStream)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "test-util", derive(Default))]
 #[non_exhaustive]
 pub struct SensitiveDataProcessing {
     pub identification_documents: OptOut,
@@ -51,6 +54,7 @@ pub struct SensitiveDataProcessing {
 
 #[derive(Debug, Eq, PartialEq, FromBitStream)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "test-util", derive(Default))]
 #[non_exhaustive]
 pub struct KnownChildSensitiveDataConsents {
     pub sell_personal_information: Consent,
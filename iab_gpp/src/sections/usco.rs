@@ -7,6 +7,7 @@ use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Eq, PartialEq, GPPSection)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "test-util", derive(Default))]
 #[non_exhaustive]
 #[gpp(with_optional_segments(bits = 2))]
 pub struct UsCo {
@@ -17,6 +18,7 @@ pub struct UsCo {
 
 #[derive(Debug, Eq, PartialEq, FromBitStream)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "test-util", derive(Default))]
 #[non_exhaustive]
 #[gpp(section_version = 1)]
 pub struct Core {
@@ -35,6 +37,7 @@ pub struct Core {
 
 #[derive(Debug, Eq, PartialEq, FromBitStream)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "test-util", derive(Default))]
 #[non_exhaustive]
 pub struct SensitiveDataProcessing {
     pub racial_or_ethnic_origin: Consent,
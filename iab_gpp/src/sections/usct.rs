@@ -7,6 +7,7 @@ use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Eq, PartialEq, GPPSection)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "test-util", derive(Default))]
 #[non_exhaustive]
 #[gpp(with_optional_segments(bits = 2))]
 pub struct UsCt {
@@ -17,6 +18,7 @@ pub struct UsCt {
 
 #[derive(Debug, Eq, PartialEq, FromBitStream)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "test-util", derive(Default))]
 #[non_exhaustive]
 #[gpp(section_version = 1)]
 pub struct Core {
@@ -35,6 +37,7 @@ pub struct Core {
 
 #[derive(Debug, Eq, PartialEq, FromBitStream)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "test-util", derive(Default))]
 #[non_exhaustive]
 pub struct SensitiveDataProcessing {
     pub racial_or_ethnic_origin: Consent,
@@ -49,6 +52,7 @@ pub struct SensitiveDataProcessing {
 
 #[derive(Debug, Eq, PartialEq, FromBitStream)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "test-util", derive(Default))]
 #[non_exhaustive]
 pub struct KnownChildSensitiveDataConsents {
     pub process_sensitive_data_from_known_child: Consent,
@@ -68,6 +72,7 @@ mod tests {
     #[test_case("CVVVVVVVVWA.YA" => matches SectionDecodeError::UnknownSegmentVersion { .. } ; "unknown segment version 1")]
     #[test_case("gqgkgAAAAEA" => matches SectionDecodeError::UnknownSegmentVersion { .. } ; "unknown segment version 2")]
     #[test_case("BVVVVVVVVWA.AA" => matches SectionDecodeError::UnknownSegmentType { .. } ; "unknown segment type")]
+    #[test_case("YA" => matches SectionDecodeError::MissingCoreSegment { segment_type: 1 } ; "gpc only")]
     fn error(s: &str) -> SectionDecodeError {
         UsCt::from_str(s).unwrap_err()
     }
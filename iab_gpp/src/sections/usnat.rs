@@ -7,6 +7,7 @@ use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Eq, PartialEq, GPPSection)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "test-util", derive(Default))]
 #[non_exhaustive]
 #[gpp(with_optional_segments(bits = 2))]
 pub struct UsNat {
@@ -15,6 +16,64 @@ pub struct UsNat {
     pub gpc: Option<bool>,
 }
 
+impl UsNat {
+    /// Returns whether the Global Privacy Control signal applies.
+    ///
+    /// Returns `None` if the GPC segment is absent, which means the CMP did not report it,
+    /// as opposed to `Some(false)` which means it reported no GPC signal.
+    pub fn gpc_applies(&self) -> Option<bool> {
+        self.gpc
+    }
+
+    /// Returns the known child sensitive data consent, summarized across age bands.
+    pub fn known_child(&self) -> KnownChild {
+        let (from_13_to_16, under_13) = match &self.core {
+            Core::V1(core) => (
+                &core.known_child_sensitive_data_consents.from_13_to_16,
+                &core.known_child_sensitive_data_consents.under_13,
+            ),
+            Core::V2(core) => (
+                &core
+                    .known_child_sensitive_data_consents
+                    .process_sensitive_data_from_13_to_16,
+                &core
+                    .known_child_sensitive_data_consents
+                    .process_sensitive_data_under_13,
+            ),
+        };
+
+        KnownChild::from_consents(from_13_to_16, under_13)
+    }
+}
+
+/// Consent to process sensitive data from known children, by age band.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum KnownChild {
+    /// Neither age band is applicable.
+    NotApplicable,
+    /// No consent was given for any age band.
+    No,
+    /// Consent was given for children from 13 to 16 only.
+    YesFrom13To16,
+    /// Consent was given for children under 13 only.
+    YesUnder13,
+    /// Consent was given for both children under 13 and from 13 to 16.
+    YesUnder16,
+}
+
+impl KnownChild {
+    fn from_consents(from_13_to_16: &Consent, under_13: &Consent) -> Self {
+        match (from_13_to_16, under_13) {
+            (Consent::NotApplicable, Consent::NotApplicable) => Self::NotApplicable,
+            (Consent::Consent, Consent::Consent) => Self::YesUnder16,
+            (Consent::Consent, _) => Self::YesFrom13To16,
+            (_, Consent::Consent) => Self::YesUnder13,
+            _ => Self::No,
+        }
+    }
+}
+
 #[derive(Debug, Eq, PartialEq, FromBitStream)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[non_exhaustive]
@@ -25,8 +84,17 @@ pub enum Core {
     V2(CoreV2),
 }
 
+/// Defaults to the latest core version.
+#[cfg(feature = "test-util")]
+impl Default for Core {
+    fn default() -> Self {
+        Self::V2(CoreV2::default())
+    }
+}
+
 #[derive(Debug, Eq, PartialEq, FromBitStream)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "test-util", derive(Default))]
 #[non_exhaustive]
 pub struct CoreV1 {
     pub sharing_notice: Notice,
@@ -49,6 +117,7 @@ pub struct CoreV1 {
 
 #[derive(Debug, Eq, PartialEq, FromBitStream)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "test-util", derive(Default))]
 #[non_exhaustive]
 pub struct SensitiveDataProcessingV1 {
     pub racial_or_ethnic_origin: Consent,
@@ -67,6 +136,7 @@ pub struct SensitiveDataProcessingV1 {
 
 #[derive(Debug, Eq, PartialEq, FromBitStream)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "test-util", derive(Default))]
 #[non_exhaustive]
 pub struct KnownChildSensitiveDataConsentsV1 {
     pub from_13_to_16: Consent,
@@ -75,6 +145,7 @@ pub struct KnownChildSensitiveDataConsentsV1 {
 
 #[derive(Debug, Eq, PartialEq, FromBitStream)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "test-util", derive(Default))]
 #[non_exhaustive]
 pub struct CoreV2 {
     pub sharing_notice: Notice,
@@ -97,6 +168,7 @@ pub struct CoreV2 {
 
 #[derive(Debug, Eq, PartialEq, FromBitStream)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "test-util", derive(Default))]
 #[non_exhaustive]
 pub struct SensitiveDataProcessingV2 {
     pub racial_or_ethnic_origin: Consent,
@@ -119,6 +191,7 @@ pub struct SensitiveDataProcessingV2 {
 
 #[derive(Debug, Eq, PartialEq, FromBitStream)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "test-util", derive(Default))]
 #[non_exhaustive]
 pub struct KnownChildSensitiveDataConsentsV2 {
     pub process_sensitive_data_from_13_to_16: Consent,
@@ -140,4 +213,27 @@ mod tests {
     fn error(s: &str) -> SectionDecodeError {
         UsNat::from_str(s).unwrap_err()
     }
+
+    #[test_case("BVVVVVVVVWA" => matches Ok(Core::V1(_)) ; "version 1")]
+    #[test_case("CAAAAAAAAAWA" => matches Ok(Core::V2(_)) ; "version 2")]
+    #[test_case("DAAAAAAAAAWA" => matches Err(SectionDecodeError::UnknownSegmentVersion { segment_version: 3 }) ; "version 3")]
+    fn core_version(s: &str) -> Result<Core, SectionDecodeError> {
+        UsNat::from_str(s).map(|s| s.core)
+    }
+
+    #[test_case("BVVVVVVVVWA" => None ; "absent")]
+    #[test_case("BVVVVVVVVWA.QA" => Some(false) ; "not applying")]
+    #[test_case("BVVVVVVVVWA.YA" => Some(true) ; "applying")]
+    fn gpc_applies(s: &str) -> Option<bool> {
+        UsNat::from_str(s).unwrap().gpc_applies()
+    }
+
+    #[test_case("BVVVVVVVVWA" => KnownChild::No ; "v1 no consent")]
+    #[test_case("BVVVVVVVlWA" => KnownChild::YesFrom13To16 ; "v1 from 13 to 16")]
+    #[test_case("BVVVVVVVZWA" => KnownChild::YesUnder13 ; "v1 under 13")]
+    #[test_case("BVVVVVVVpWA" => KnownChild::YesUnder16 ; "v1 under 16")]
+    #[test_case("CAAAAAAAAAWA" => KnownChild::NotApplicable ; "v2 not applicable")]
+    fn known_child(s: &str) -> KnownChild {
+        UsNat::from_str(s).unwrap().known_child()
+    }
 }
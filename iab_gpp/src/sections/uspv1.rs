@@ -1,47 +1,91 @@
-use crate::sections::{DecodableSection, SectionDecodeError, SectionId};
+use crate::sections::{DecodableSection, DecodeContext, DecodeStr, SectionDecodeError, SectionId};
+use crate::v1::GPPString;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 use std::str::{Chars, FromStr};
 
 const USP_V1_VERSION: u8 = 1;
+const USP_V1_LEN: usize = 4;
 const KIND: &str = "uspv1";
 
 #[derive(Debug, Eq, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "test-util", derive(Default))]
 pub enum Flag {
     Yes,
     No,
+    #[cfg_attr(feature = "test-util", default)]
     NotApplicable,
 }
 
 impl Flag {
-    fn from_char(c: char) -> Option<Self> {
+    /// Maps a flag character to a flag, reading lowercase `y` and `n` as their uppercase
+    /// counterparts if `lenient` is set.
+    fn from_char(c: char, lenient: bool) -> Option<Self> {
         match c {
             'Y' => Some(Self::Yes),
             'N' => Some(Self::No),
             '-' => Some(Self::NotApplicable),
+            'y' if lenient => Some(Self::Yes),
+            'n' if lenient => Some(Self::No),
             _ => None,
         }
     }
 }
 
 // See https://github.com/InteractiveAdvertisingBureau/USPrivacy/blob/master/CCPA/US%20Privacy%20String.md#us-privacy-string-format
+/// The deprecated US Privacy section.
+///
+/// The GPP specification has only ever registered this section under id 6
+/// ([`UspV1::ID`], named `uspv1`), including in strings produced before
+/// the section was deprecated in favor of the US National and state sections, so stored
+/// strings listing id 6 always decode to this type. The section content is the standalone
+/// US Privacy string (as stored in the `usprivacy` cookie), which can be decoded directly with
+/// [`FromStr`].
 #[derive(Debug, Eq, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "test-util", derive(Default))]
 pub struct UspV1 {
     pub opt_out_notice: Flag,
     pub opt_out_sale: Flag,
     pub lspa_covered_transaction: Flag,
 }
 
+impl UspV1 {
+    /// The id of the section.
+    pub const ID: SectionId = SectionId::UspV1;
+}
+
 impl DecodableSection for UspV1 {
-    const ID: SectionId = SectionId::UspV1;
+    const ID: SectionId = Self::ID;
+}
+
+impl TryFrom<&GPPString> for UspV1 {
+    type Error = SectionDecodeError;
+
+    fn try_from(gpp: &GPPString) -> Result<Self, Self::Error> {
+        gpp.decode()
+    }
+}
+
+impl TryFrom<&str> for UspV1 {
+    type Error = SectionDecodeError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        s.parse()
+    }
 }
 
 impl FromStr for UspV1 {
     type Err = SectionDecodeError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::decode_str(s, &DecodeContext::default())
+    }
+}
+
+impl DecodeStr for UspV1 {
+    fn decode_str(s: &str, ctx: &DecodeContext) -> Result<Self, SectionDecodeError> {
         let mut chars = s.chars();
 
         let version = chars
@@ -61,22 +105,39 @@ impl FromStr for UspV1 {
             });
         }
 
-        Ok(Self {
-            opt_out_notice: parse_next_char(&mut chars, s)?,
-            opt_out_sale: parse_next_char(&mut chars, s)?,
-            lspa_covered_transaction: parse_next_char(&mut chars, s)?,
-        })
+        let lenient = !ctx.options.reject_lowercase_usp_flags;
+        let usp = Self {
+            opt_out_notice: parse_next_char(&mut chars, s, lenient)?,
+            opt_out_sale: parse_next_char(&mut chars, s, lenient)?,
+            lspa_covered_transaction: parse_next_char(&mut chars, s, lenient)?,
+        };
+
+        // the string has a fixed size, trailing characters mean it is corrupted
+        let len = USP_V1_LEN + chars.count();
+        if len != USP_V1_LEN {
+            return Err(SectionDecodeError::UnexpectedLength {
+                kind: KIND,
+                expected: USP_V1_LEN,
+                found: len,
+            });
+        }
+
+        Ok(usp)
     }
 }
 
-fn parse_next_char(chars: &mut Chars, original_str: &str) -> Result<Flag, SectionDecodeError> {
+fn parse_next_char(
+    chars: &mut Chars,
+    original_str: &str,
+    lenient: bool,
+) -> Result<Flag, SectionDecodeError> {
     let char = chars
         .next()
         .ok_or(SectionDecodeError::UnexpectedEndOfString(
             original_str.to_string(),
         ))?;
 
-    Flag::from_char(char).ok_or(SectionDecodeError::InvalidCharacter {
+    Flag::from_char(char, lenient).ok_or(SectionDecodeError::InvalidCharacter {
         character: char,
         kind: KIND,
         s: original_str.to_string(),
@@ -86,6 +147,7 @@ fn parse_next_char(chars: &mut Chars, original_str: &str) -> Result<Flag, Sectio
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::sections::DecodeOptions;
     use test_case::test_case;
 
     #[test_case("1YN-" => UspV1 {
@@ -107,6 +169,45 @@ mod tests {
         UspV1::from_str(s).unwrap()
     }
 
+    #[test_case("1yNN" ; "lowercase yes")]
+    #[test_case("1ynn" ; "all lowercase")]
+    #[test_case("1Yn-" ; "mixed case")]
+    fn parse_lowercase(s: &str) {
+        assert_eq!(
+            UspV1::from_str(s).unwrap(),
+            UspV1::from_str(&s.to_uppercase()).unwrap()
+        );
+    }
+
+    #[test]
+    fn reject_lowercase() {
+        let options = DecodeOptions::new().with_reject_lowercase_usp_flags();
+
+        assert!(options.decode::<UspV1>("1YNN").is_ok());
+        assert!(matches!(
+            options.decode::<UspV1>("1yNN"),
+            Err(SectionDecodeError::InvalidCharacter { character: 'y', .. })
+        ));
+    }
+
+    #[test_case(UspV1::ID as u8 => Some(SectionId::UspV1) ; "uspv1")]
+    #[test_case(5 => Some(SectionId::TcfCaV1) ; "tcfcav1")]
+    #[test_case(7 => Some(SectionId::UsNat) ; "usnat")]
+    fn section_id(id: u8) -> Option<SectionId> {
+        <SectionId as num_traits::FromPrimitive>::from_u8(id)
+    }
+
+    #[test_case("DBABTA~1YNN" ; "only section")]
+    #[test_case("DBACNY~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN" ; "after tcf eu v2")]
+    fn decode_from_gpp_string(s: &str) {
+        let gpp = GPPString::from_str(s).unwrap();
+
+        assert_eq!(
+            gpp.decode_section(SectionId::UspV1).unwrap(),
+            crate::sections::Section::UspV1(UspV1::from_str("1YNN").unwrap())
+        );
+    }
+
     #[test_case("ZYN-" => matches SectionDecodeError::InvalidCharacter { character: 'Z', .. } ; "invalid version character")]
     #[test_case("2YN-" => matches SectionDecodeError::InvalidSectionVersion {
         expected: USP_V1_VERSION,
@@ -116,6 +217,8 @@ mod tests {
     #[test_case("1" => matches SectionDecodeError::UnexpectedEndOfString(_) ; "header only")]
     #[test_case("1N" => matches SectionDecodeError::UnexpectedEndOfString(_) ; "missing characters")]
     #[test_case("1A" => matches SectionDecodeError::InvalidCharacter { character: 'A', .. } ; "invalid consent character")]
+    #[test_case("1YNNN" => matches SectionDecodeError::UnexpectedLength { expected: 4, found: 5, .. } ; "trailing character")]
+    #[test_case("1YNN~1YNN" => matches SectionDecodeError::UnexpectedLength { expected: 4, found: 9, .. } ; "appended string")]
     fn error(s: &str) -> SectionDecodeError {
         UspV1::from_str(s).unwrap_err()
     }
@@ -0,0 +1,220 @@
+use crate::sections::us_common::{MspaMode, MspaSupport};
+use crate::sections::{SectionDecodeError, TwoLetterCode};
+use std::fmt;
+
+/// A semantic issue found in a section which decoded successfully.
+///
+/// Decoding only checks that a string follows the binary layout of its section. Validation
+/// goes further and reports field values which are allowed by the layout, but contradict the
+/// specification or each other, and usually point to a broken CMP.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ValidationWarning {
+    /// The section could not be decoded, so it could not be validated.
+    DecodeFailed(SectionDecodeError),
+    /// The last update timestamp is earlier than the creation timestamp.
+    LastUpdatedBeforeCreated { created: u64, last_updated: u64 },
+    /// The CMP id is 0 or 1, which are never assigned to a registered CMP.
+    ReservedCmpId { cmp_id: u16 },
+    /// The consent screen has all of its bits set, which is what garbage such as a run of `_`
+    /// characters decodes to.
+    ImplausibleConsentScreen { consent_screen: u8 },
+    /// The consent language is not an ISO 639-1 language code.
+    UnknownConsentLanguage { consent_language: TwoLetterCode },
+    /// The publisher country code is not made of two letters.
+    InvalidPublisherCountryCode {
+        publisher_country_code: TwoLetterCode,
+    },
+    /// The MSPA opt-out option mode and service provider mode are both enabled, while they are
+    /// mutually exclusive.
+    ConflictingMspaModes,
+    /// An MSPA mode is set, but the transaction is not covered by the MSPA, in which case both
+    /// modes must be not applicable.
+    MspaModeWithoutCoveredTransaction,
+}
+
+impl fmt::Display for ValidationWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationWarning::DecodeFailed(e) => write!(f, "decode failed: {e}"),
+            ValidationWarning::LastUpdatedBeforeCreated {
+                created,
+                last_updated,
+            } => write!(
+                f,
+                "last updated ({last_updated}) before created ({created})"
+            ),
+            ValidationWarning::ReservedCmpId { cmp_id } => write!(f, "reserved CMP id {cmp_id}"),
+            ValidationWarning::ImplausibleConsentScreen { consent_screen } => {
+                write!(f, "implausible consent screen {consent_screen}")
+            }
+            ValidationWarning::UnknownConsentLanguage { consent_language } => {
+                write!(f, "unknown consent language {consent_language}")
+            }
+            ValidationWarning::InvalidPublisherCountryCode {
+                publisher_country_code,
+            } => write!(
+                f,
+                "invalid publisher country code {publisher_country_code:?}"
+            ),
+            ValidationWarning::ConflictingMspaModes => {
+                f.write_str("both MSPA opt-out option and service provider modes are set")
+            }
+            ValidationWarning::MspaModeWithoutCoveredTransaction => {
+                f.write_str("MSPA mode set for a transaction not covered by the MSPA")
+            }
+        }
+    }
+}
+
+/// Checks the creation and last update timestamps, and the CMP id, shared by TCF sections.
+pub(crate) fn validate_tcf_metadata(
+    created: u64,
+    last_updated: u64,
+    cmp_id: u16,
+) -> Vec<ValidationWarning> {
+    let mut warnings = vec![];
+    if last_updated < created {
+        warnings.push(ValidationWarning::LastUpdatedBeforeCreated {
+            created,
+            last_updated,
+        });
+    }
+    if cmp_id < 2 {
+        warnings.push(ValidationWarning::ReservedCmpId { cmp_id });
+    }
+    warnings
+}
+
+/// ISO 639-1 language codes, in uppercase as encoded in TCF strings, sorted.
+const LANGUAGE_CODES: [&str; 183] = [
+    "AA", "AB", "AE", "AF", "AK", "AM", "AN", "AR", "AS", "AV", "AY", "AZ", "BA", "BE", "BG", "BI",
+    "BM", "BN", "BO", "BR", "BS", "CA", "CE", "CH", "CO", "CR", "CS", "CU", "CV", "CY", "DA", "DE",
+    "DV", "DZ", "EE", "EL", "EN", "EO", "ES", "ET", "EU", "FA", "FF", "FI", "FJ", "FO", "FR", "FY",
+    "GA", "GD", "GL", "GN", "GU", "GV", "HA", "HE", "HI", "HO", "HR", "HT", "HU", "HY", "HZ", "IA",
+    "ID", "IE", "IG", "II", "IK", "IO", "IS", "IT", "IU", "JA", "JV", "KA", "KG", "KI", "KJ", "KK",
+    "KL", "KM", "KN", "KO", "KR", "KS", "KU", "KV", "KW", "KY", "LA", "LB", "LG", "LI", "LN", "LO",
+    "LT", "LU", "LV", "MG", "MH", "MI", "MK", "ML", "MN", "MR", "MS", "MT", "MY", "NA", "NB", "ND",
+    "NE", "NG", "NL", "NN", "NO", "NR", "NV", "NY", "OC", "OJ", "OM", "OR", "OS", "PA", "PI", "PL",
+    "PS", "PT", "QU", "RM", "RN", "RO", "RU", "RW", "SA", "SC", "SD", "SE", "SG", "SI", "SK", "SL",
+    "SM", "SN", "SO", "SQ", "SR", "SS", "ST", "SU", "SV", "SW", "TA", "TE", "TG", "TH", "TI", "TK",
+    "TL", "TN", "TO", "TR", "TS", "TT", "TW", "TY", "UG", "UK", "UR", "UZ", "VE", "VI", "VO", "WA",
+    "WO", "XH", "YI", "YO", "ZA", "ZH", "ZU",
+];
+
+/// The value of a 6-bit consent screen with every bit set.
+const ALL_BITS_CONSENT_SCREEN: u8 = 0b11_1111;
+
+/// Checks the consent screen and consent language of a TCF section.
+///
+/// Both fields are only informative, and no decision depends on them, but garbage values
+/// usually come from a broken CMP. This check is part of the `validate` methods of TCF
+/// sections, and can also be run on its own.
+///
+/// # Example
+///
+/// ```
+/// use iab_gpp::sections::{TwoLetterCode, ValidationWarning, validate_consent_metadata};
+///
+/// let language = TwoLetterCode::try_from("EN").unwrap();
+/// assert!(validate_consent_metadata(1, language).is_empty());
+///
+/// let language = TwoLetterCode::try_from("XX").unwrap();
+/// assert!(matches!(
+///     validate_consent_metadata(63, language)[..],
+///     [
+///         ValidationWarning::ImplausibleConsentScreen { consent_screen: 63 },
+///         ValidationWarning::UnknownConsentLanguage { .. },
+///     ]
+/// ));
+/// ```
+pub fn validate_consent_metadata(
+    consent_screen: u8,
+    consent_language: TwoLetterCode,
+) -> Vec<ValidationWarning> {
+    let mut warnings = vec![];
+    if consent_screen == ALL_BITS_CONSENT_SCREEN {
+        warnings.push(ValidationWarning::ImplausibleConsentScreen { consent_screen });
+    }
+    if LANGUAGE_CODES.binary_search(&&*consent_language).is_err() {
+        warnings.push(ValidationWarning::UnknownConsentLanguage { consent_language });
+    }
+    warnings
+}
+
+/// Checks the MSPA fields shared by US sections.
+pub(crate) fn validate_mspa(
+    covered_transaction: bool,
+    opt_out_option_mode: &MspaSupport,
+    service_provider_mode: &MspaSupport,
+) -> Vec<ValidationWarning> {
+    let mut warnings = vec![];
+    if *opt_out_option_mode == MspaSupport::Yes && *service_provider_mode == MspaSupport::Yes {
+        warnings.push(ValidationWarning::ConflictingMspaModes);
+    }
+    if !covered_transaction
+        && (*opt_out_option_mode != MspaSupport::NotApplicable
+            || *service_provider_mode != MspaSupport::NotApplicable)
+    {
+        warnings.push(ValidationWarning::MspaModeWithoutCoveredTransaction);
+    }
+    warnings
+}
+
+/// Checks the single MSPA mode field of US sections which merge the opt-out option and
+/// service provider modes, such as US Maryland.
+pub(crate) fn validate_mspa_mode(
+    covered_transaction: bool,
+    mode: &MspaMode,
+) -> Vec<ValidationWarning> {
+    if !covered_transaction && *mode != MspaMode::NotApplicable {
+        return vec![ValidationWarning::MspaModeWithoutCoveredTransaction];
+    }
+    vec![]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(10, 20, 31 => 0 ; "valid")]
+    #[test_case(20, 10, 31 => 1 ; "updated before created")]
+    #[test_case(10, 10, 1 => 1 ; "reserved cmp id")]
+    #[test_case(20, 10, 0 => 2 ; "both")]
+    fn tcf_metadata(created: u64, last_updated: u64, cmp_id: u16) -> usize {
+        validate_tcf_metadata(created, last_updated, cmp_id).len()
+    }
+
+    #[test_case(0, "EN" => 0 ; "valid")]
+    #[test_case(62, "BG" => 0 ; "high screen")]
+    #[test_case(63, "FR" => 1 ; "all bits screen")]
+    #[test_case(1, "ZZ" => 1 ; "unknown language")]
+    #[test_case(63, "ZZ" => 2 ; "both")]
+    fn consent_metadata(consent_screen: u8, consent_language: &str) -> usize {
+        let consent_language = TwoLetterCode::try_from(consent_language).unwrap();
+        validate_consent_metadata(consent_screen, consent_language).len()
+    }
+
+    #[test]
+    fn language_codes_are_sorted() {
+        assert!(LANGUAGE_CODES.is_sorted());
+    }
+
+    #[test_case(true, MspaSupport::Yes, MspaSupport::No => 0 ; "opt out option mode")]
+    #[test_case(true, MspaSupport::Yes, MspaSupport::Yes => 1 ; "conflicting modes")]
+    #[test_case(false, MspaSupport::NotApplicable, MspaSupport::NotApplicable => 0 ; "not covered")]
+    #[test_case(false, MspaSupport::No, MspaSupport::NotApplicable => 1 ; "not covered with mode")]
+    #[test_case(false, MspaSupport::Yes, MspaSupport::Yes => 2 ; "not covered with both modes")]
+    fn mspa(covered: bool, opt_out: MspaSupport, service_provider: MspaSupport) -> usize {
+        validate_mspa(covered, &opt_out, &service_provider).len()
+    }
+
+    #[test_case(true, MspaMode::ServiceProvider => 0 ; "covered")]
+    #[test_case(false, MspaMode::NotApplicable => 0 ; "not covered")]
+    #[test_case(false, MspaMode::OptOutOption => 1 ; "not covered with opt out option mode")]
+    #[test_case(false, MspaMode::ServiceProvider => 1 ; "not covered with service provider mode")]
+    fn mspa_mode(covered: bool, mode: MspaMode) -> usize {
+        validate_mspa_mode(covered, &mode).len()
+    }
+}
@@ -0,0 +1,60 @@
+use std::fmt;
+
+/// A non-fatal oddity found while decoding a section.
+///
+/// Warnings are only collected by the `*_with_warnings` decode methods, such as
+/// [`GPPString::decode_section_with_warnings`](crate::v1::GPPString::decode_section_with_warnings).
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum DecodeWarning {
+    /// The string ended in the middle of the publisher restrictions, and only the restrictions
+    /// read before the end were kept.
+    TruncatedPublisherRestrictions { declared: usize, decoded: usize },
+    /// The core segment carries a version which is not part of the specification, but is
+    /// accepted because its layout is known to be compatible.
+    NonStandardSegmentVersion { segment_version: u8 },
+    /// An integer range contains id 0, which is not a valid id since ids are 1-based.
+    ZeroId,
+    /// A Base64 string uses the `+` or `/` characters of the standard alphabet instead of the
+    /// URL-safe `-` and `_`, which is only accepted with
+    /// [`DecodeOptions::accept_standard_base64`](crate::sections::DecodeOptions::accept_standard_base64).
+    NonStandardBase64Alphabet,
+    /// An optional segment of an unknown type was ignored, which is only done with
+    /// [`DecodeOptions::skip_unknown_segments`](crate::sections::DecodeOptions::skip_unknown_segments).
+    UnknownSegmentSkipped { segment_type: u8 },
+}
+
+impl fmt::Display for DecodeWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeWarning::TruncatedPublisherRestrictions { declared, decoded } => write!(
+                f,
+                "truncated publisher restrictions (declared {declared}, decoded {decoded})"
+            ),
+            DecodeWarning::NonStandardSegmentVersion { segment_version } => {
+                write!(f, "non-standard segment version ({segment_version})")
+            }
+            DecodeWarning::ZeroId => f.write_str("id 0 in integer range"),
+            DecodeWarning::NonStandardBase64Alphabet => {
+                f.write_str("standard Base64 alphabet instead of URL-safe")
+            }
+            DecodeWarning::UnknownSegmentSkipped { segment_type } => {
+                write!(f, "skipped unknown segment type {segment_type}")
+            }
+        }
+    }
+}
+
+/// A successfully decoded value, along with the warnings raised while decoding it.
+#[derive(Debug, Eq, PartialEq)]
+pub struct DecodeResult<T> {
+    pub value: T,
+    pub warnings: Vec<DecodeWarning>,
+}
+
+impl<T> DecodeResult<T> {
+    /// Returns `true` if the value was decoded without any warning.
+    pub fn is_clean(&self) -> bool {
+        self.warnings.is_empty()
+    }
+}
@@ -62,11 +62,17 @@
 //!
 //! If parsing fails, a [`GPPDecodeError`] is returned instead.
 //!
-use crate::core::{DataRead, base64_bit_reader};
-use crate::sections::{DecodableSection, Section, SectionDecodeError, SectionId, decode_section};
+use crate::core::{DataRead, base64_bit_reader, base64_decode, find_invalid_base64_byte};
+use crate::sections::tcfcav1::TcfCaV1;
+use crate::sections::tcfeuv2::TcfEuV2;
+use crate::sections::{
+    DecodableSection, DecodeContext, DecodeOptions, DecodeResult, Section, SectionDecodeError,
+    SectionId, ValidationWarning, decode_section,
+};
 use bitstream_io::BitRead;
 use num_traits::FromPrimitive;
 use std::io;
+use std::io::{BufRead, Lines};
 use std::iter::FusedIterator;
 use std::ops::Range;
 use std::slice::Iter;
@@ -105,8 +111,33 @@ pub enum GPPDecodeError {
     UnsupportedSectionId(u8),
     /// The number of sections listed in the header does not match the number of actual sections
     /// present in the string.
-    #[error("ids do not match sections (number of ids {ids}, number of sections {sections}")]
+    ///
+    /// This is checked when the string is parsed, before any section is decoded, and usually
+    /// means that the string was truncated or corrupted.
+    #[error("ids do not match sections (number of ids {ids}, number of sections {sections})")]
     IdSectionMismatch { ids: usize, sections: usize },
+    /// The input string is longer than the limit set in [`DecodeOptions::max_input_len`].
+    #[error("input too long ({len} bytes, maximum {max})")]
+    InputTooLong { len: usize, max: usize },
+    /// A section id is listed more than once in the header.
+    #[error("duplicate section id {id}")]
+    DuplicateSection { id: SectionId },
+    /// The section ids listed in the header are not in ascending order.
+    #[error("section id {id} listed after {previous}")]
+    SectionsOutOfOrder { previous: SectionId, id: SectionId },
+    /// A section listed in the header has no content, for example in `DBACNY~~1YNN`.
+    ///
+    /// This is only reported by [`GPPString::validate_structure`], which checks the whole
+    /// string up front. Parsing accepts such a string, and decoding the empty section then fails
+    /// with [`SectionDecodeError::EmptyFragment`] instead.
+    #[error("empty section {id}")]
+    EmptySection { id: SectionId },
+    /// A byte of the string is not part of the URL-safe Base64 alphabet. The offset is counted
+    /// from the start of the whole string.
+    ///
+    /// This is only reported by [`GPPString::validate_structure`].
+    #[error("invalid byte {byte:#04x} at offset {offset}")]
+    InvalidByte { offset: usize, byte: u8 },
 }
 
 /// The representation of a parsed GPP consent string.
@@ -121,6 +152,7 @@ pub struct GPPString {
     section_ids: Vec<SectionId>,
     source: Box<str>,
     section_ranges: Vec<Range<usize>>,
+    options: DecodeOptions,
 }
 
 impl GPPString {
@@ -145,6 +177,320 @@ impl GPPString {
         s.parse()
     }
 
+    /// Parses a string using the given [`DecodeOptions`] and returns a [`GPPString`] if
+    /// successful.
+    ///
+    /// The options are kept with the returned string and apply to every section it decodes.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`GPPDecodeError`] if unable to parse the string, or if the string does not
+    /// satisfy the limits set in the options.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::DecodeOptions;
+    /// use iab_gpp::v1::GPPString;
+    ///
+    /// let options = DecodeOptions::new().with_max_input_len(4096);
+    /// let r = GPPString::parse_str_with_options("DBABTA~1YNN", &options);
+    ///
+    /// assert!(matches!(r, Ok(gpp_str)));
+    /// ```
+    ///
+    pub fn parse_str_with_options(
+        s: &str,
+        options: &DecodeOptions,
+    ) -> Result<Self, GPPDecodeError> {
+        let s = options.prepare(s);
+        if let Some(max) = options.max_input_len
+            && s.len() > max
+        {
+            return Err(GPPDecodeError::InputTooLong { len: s.len(), max });
+        }
+
+        let mut gpp_str: Self = s.parse()?;
+        gpp_str.options = *options;
+        Ok(gpp_str)
+    }
+
+    /// Checks that a string is a well-formed GPP string, without decoding any section.
+    ///
+    /// The header is parsed and the number of sections is checked against it, as with
+    /// [`parse_str`](GPPString::parse_str). Then every section must be non-empty and made only of
+    /// URL-safe Base64 characters, or of `.` segment separators. This is cheaper than parsing and
+    /// decoding all sections, but a string which passes may still fail to decode, for example if
+    /// a section is truncated.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first structural problem found as a [`GPPDecodeError`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::v1::{GPPDecodeError, GPPString};
+    ///
+    /// assert!(GPPString::validate_structure("DBACNY~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN").is_ok());
+    ///
+    /// let r = GPPString::validate_structure("DBABTA~1Y N");
+    /// assert!(matches!(r, Err(GPPDecodeError::InvalidByte { offset: 9, byte: b' ' })));
+    /// ```
+    ///
+    pub fn validate_structure(s: &str) -> Result<(), GPPDecodeError> {
+        let (section_ids, section_ranges) = extract_gpp_sections_from_str(s)?;
+        let invalid_byte = |offset: usize| GPPDecodeError::InvalidByte {
+            offset,
+            byte: s.as_bytes()[offset],
+        };
+
+        let header_len = s.find('~').unwrap_or(s.len());
+        if let Some(offset) = find_invalid_base64_byte(&s.as_bytes()[..header_len], &[]) {
+            return Err(invalid_byte(offset));
+        }
+
+        for (&id, range) in section_ids.iter().zip(section_ranges) {
+            if range.is_empty() {
+                return Err(GPPDecodeError::EmptySection { id });
+            }
+            if let Some(offset) = find_invalid_base64_byte(&s.as_bytes()[range.clone()], b".") {
+                return Err(invalid_byte(range.start + offset));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Parses raw bytes and returns a [`GPPString`] if successful.
+    ///
+    /// GPP strings are plain ASCII, so this only checks that every byte is ASCII instead of
+    /// running a full UTF-8 validation. This is useful when the string is read directly from
+    /// a request header or body.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`GPPDecodeError::Read`] error if a byte is not ASCII, or any other
+    /// [`GPPDecodeError`] if unable to parse the string, exactly like
+    /// [`parse_str`](GPPString::parse_str).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::SectionId;
+    /// use iab_gpp::v1::GPPString;
+    /// use iab_gpp::v1::GPPDecodeError;
+    ///
+    /// fn main() -> Result<(), GPPDecodeError> {
+    ///     let gpp_str = GPPString::from_bytes(b"DBABTA~1YNN")?;
+    ///
+    ///     assert_eq!(gpp_str.section(SectionId::UspV1), Some("1YNN"));
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    ///
+    pub fn from_bytes(b: &[u8]) -> Result<Self, GPPDecodeError> {
+        if !b.is_ascii() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "non-ASCII byte").into());
+        }
+
+        std::str::from_utf8(b)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+            .parse()
+    }
+
+    /// Parses the value of a URL query parameter, such as `gpp` in `?gpp=...`, and returns a
+    /// [`GPPString`] if successful.
+    ///
+    /// GPP strings only use URL-safe characters, but some intermediaries still percent-encode
+    /// them, for example `~` as `%7E`. Percent-encoded bytes are decoded first, then the result
+    /// is parsed like [`from_bytes`](GPPString::from_bytes). Nothing else is decoded, in
+    /// particular `+` is kept as is.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`GPPDecodeError::Read`] error if a `%` is not followed by two hexadecimal
+    /// digits, or any error returned by [`from_bytes`](GPPString::from_bytes).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::SectionId;
+    /// use iab_gpp::v1::GPPString;
+    /// use iab_gpp::v1::GPPDecodeError;
+    ///
+    /// fn main() -> Result<(), GPPDecodeError> {
+    ///     let gpp_str = GPPString::from_query_param("DBABTA%7E1YNN")?;
+    ///
+    ///     assert_eq!(gpp_str.as_str(), "DBABTA~1YNN");
+    ///     assert_eq!(gpp_str.section(SectionId::UspV1), Some("1YNN"));
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    ///
+    pub fn from_query_param(value: &str) -> Result<Self, GPPDecodeError> {
+        if !value.contains('%') {
+            return value.parse();
+        }
+
+        let mut bytes = Vec::with_capacity(value.len());
+        let mut rest = value.as_bytes();
+        while let Some((&b, tail)) = rest.split_first() {
+            if b == b'%' {
+                let hex = |i: usize| tail.get(i).and_then(|&d| (d as char).to_digit(16));
+                let byte = hex(0)
+                    .zip(hex(1))
+                    .map(|(high, low)| (high * 16 + low) as u8)
+                    .ok_or_else(|| {
+                        io::Error::new(io::ErrorKind::InvalidData, "invalid percent-encoding")
+                    })?;
+                bytes.push(byte);
+                rest = &tail[2..];
+            } else {
+                bytes.push(b);
+                rest = tail;
+            }
+        }
+
+        Self::from_bytes(&bytes)
+    }
+
+    /// Wraps a bare section string into a [`GPPString`] containing only that section.
+    ///
+    /// This is **not** standard GPP input: the string has no header and no `~` separator, so the
+    /// section type cannot be read from it and must be provided by the caller. It exists for
+    /// integrations which pass raw section bodies (e.g. a TCF EU v2 string on its own).
+    ///
+    /// The section itself is not decoded until requested, exactly like sections of a full
+    /// GPP string.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::SectionId;
+    /// use iab_gpp::sections::uspv1::UspV1;
+    /// use iab_gpp::v1::GPPString;
+    ///
+    /// let gpp_str = GPPString::from_section_str(SectionId::UspV1, "1YNN");
+    ///
+    /// assert_eq!(gpp_str.section(SectionId::UspV1), Some("1YNN"));
+    /// assert!(matches!(gpp_str.decode::<UspV1>(), Ok(UspV1 { .. })));
+    /// ```
+    ///
+    pub fn from_section_str(id: SectionId, s: &str) -> Self {
+        Self {
+            section_ids: vec![id],
+            source: s.into(),
+            section_ranges: vec![Range {
+                start: 0,
+                end: s.len(),
+            }],
+            options: DecodeOptions::default(),
+        }
+    }
+
+    /// Returns the header of this GPP string, as found in the original string.
+    ///
+    /// Returns `None` for a value created with
+    /// [`from_section_str`](GPPString::from_section_str), which has no header.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::v1::GPPString;
+    /// use iab_gpp::v1::GPPDecodeError;
+    ///
+    /// fn main() -> Result<(), GPPDecodeError> {
+    ///     let gpp_str = GPPString::parse_str("DBABTA~1YNN")?;
+    ///
+    ///     assert_eq!(gpp_str.header(), Some("DBABTA"));
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    ///
+    pub fn header(&self) -> Option<&str> {
+        match self.section_ranges.first() {
+            // a bare section starts at the beginning of the string
+            Some(range) if range.start == 0 => None,
+            // the header is followed by a `~` separator
+            Some(range) => Some(&self.source[..range.start - 1]),
+            None => Some(&self.source),
+        }
+    }
+
+    /// Returns the header of this GPP string decoded from base64 into raw bytes, before any
+    /// field is interpreted.
+    ///
+    /// This is useful for debugging, or to check the integrity of a header, for example against
+    /// an expected hash. The bits are laid out as follows, and the last byte is padded with
+    /// zero bits:
+    ///
+    /// | Bits | Field                                                           |
+    /// |------|-----------------------------------------------------------------|
+    /// | 6    | header type, always `3`                                         |
+    /// | 6    | GPP version, always `1`                                         |
+    /// | 12   | number of section id entries                                    |
+    /// | 1    | for each entry: `0` for a single id, `1` for a group of ids     |
+    /// | var. | Fibonacci encoded offset from the previous id, and for a group, |
+    /// |      | Fibonacci encoded count of additional ids                       |
+    ///
+    /// The header is decoded on each call, so it returns an owned buffer.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`GPPDecodeError::NoHeaderFound`] for a value created with
+    /// [`from_section_str`](GPPString::from_section_str), or [`GPPDecodeError::Read`] if the
+    /// header contains a character which is not valid base64, after the part which was
+    /// parsed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::v1::GPPString;
+    /// use iab_gpp::v1::GPPDecodeError;
+    ///
+    /// fn main() -> Result<(), GPPDecodeError> {
+    ///     let gpp_str = GPPString::parse_str("DBABTA~1YNN")?;
+    ///
+    ///     // 000011 000001 000000000001 0 10011 000000
+    ///     assert_eq!(gpp_str.header_bytes()?, [0x0C, 0x10, 0x01, 0x4C, 0x00]);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    ///
+    pub fn header_bytes(&self) -> Result<Vec<u8>, GPPDecodeError> {
+        let header = self.header().ok_or(GPPDecodeError::NoHeaderFound)?;
+        Ok(base64_decode(header.as_bytes())?)
+    }
+
+    /// Returns the original string this [`GPPString`] was parsed from.
+    ///
+    /// This is the exact input, not a re-encoding of the decoded data, so it can be forwarded
+    /// unchanged. For a value created with [`from_section_str`](GPPString::from_section_str),
+    /// this is the bare section string.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::v1::GPPString;
+    /// use iab_gpp::v1::GPPDecodeError;
+    ///
+    /// fn main() -> Result<(), GPPDecodeError> {
+    ///     let gpp_str = GPPString::parse_str("DBABTA~1YNN")?;
+    ///
+    ///     assert_eq!(gpp_str.as_str(), "DBABTA~1YNN");
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
     /// Returns a reference to a raw section contained in this GPP string.
     ///
     /// The method takes the section ID as parameter, and returns the reference
@@ -196,6 +542,28 @@ impl GPPString {
         SectionIds(self.section_ids.iter())
     }
 
+    /// Returns the number of sections declared in the header of this GPP string.
+    ///
+    /// Sections are not decoded.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::v1::GPPString;
+    /// use iab_gpp::v1::GPPDecodeError;
+    ///
+    /// fn main() -> Result<(), GPPDecodeError> {
+    ///     let gpp_str = GPPString::parse_str("DBACNY~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN")?;
+    ///
+    ///     assert_eq!(gpp_str.section_count(), 2);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn section_count(&self) -> usize {
+        self.section_ids.len()
+    }
+
     /// Returns an iterator that yields the list of raw section strings present in this GPP string.
     ///
     /// # Example
@@ -254,11 +622,13 @@ impl GPPString {
     /// # Errors
     ///
     /// Returns a [`SectionDecodeError`] if decoding the section fails or if the section is not
-    /// present in the string.
+    /// present in the string. A section listed in the header but whose fragment is empty, as
+    /// in `DBACNY~~1YNN`, fails with [`SectionDecodeError::EmptyFragment`].
     ///
     pub fn decode_section(&self, id: SectionId) -> Result<Section, SectionDecodeError> {
         let idx = self.section_index(id).ok_or(SectionDecodeError::MissingSection(id))?;
-        decode_section(id, self.section_at(idx))
+        let ctx = DecodeContext::new(self.options);
+        traced(&ctx, id, self.section_at(idx), decode_section)
     }
 
     /// Decodes and returns a single section of this GPP string.
@@ -267,7 +637,8 @@ impl GPPString {
     ///
     /// As opposed to [`decode_section`](GPPString::decode_section), the returned section is
     /// returned directly. This is the easiest method to use if you know which section you expect to
-    /// be present in the string.
+    /// be present in the string. Every section type also implements `TryFrom<&GPPString>` using
+    /// this method, so `let usp: UspV1 = (&gpp_str).try_into()?;` works as well.
     ///
     /// # Example
     ///
@@ -300,7 +671,50 @@ impl GPPString {
         let idx = self
             .section_index(T::ID)
             .ok_or(SectionDecodeError::MissingSection(T::ID))?;
-        self.section_at(idx).parse()
+        let ctx = DecodeContext::new(self.options);
+        traced(&ctx, T::ID, self.section_at(idx), |_, s, ctx| {
+            T::decode_str(s, ctx)
+        })
+    }
+
+    /// Decodes and returns a single section of this GPP string, or `None` if it is not present.
+    ///
+    /// Same as [`decode`](GPPString::decode), but a missing section is not an error, which
+    /// makes optional sections easier to handle. The section is located with the
+    /// [`ID`](DecodableSection::ID) of its type, and if its id is listed more than once in the
+    /// header, the first one is decoded.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::tcfeuv2::TcfEuV2;
+    /// use iab_gpp::sections::uspv1::UspV1;
+    /// use iab_gpp::v1::GPPString;
+    /// use iab_gpp::v1::GPPDecodeError;
+    ///
+    /// fn main() -> Result<(), GPPDecodeError> {
+    ///     let gpp_str = GPPString::parse_str("DBABTA~1YNN")?;
+    ///
+    ///     assert!(matches!(gpp_str.decode_first::<UspV1>(), Some(Ok(UspV1 { .. }))));
+    ///     assert!(gpp_str.decode_first::<TcfEuV2>().is_none());
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `Some` with a [`SectionDecodeError`] if the section is present, but decoding it
+    /// fails.
+    ///
+    pub fn decode_first<T>(&self) -> Option<Result<T, SectionDecodeError>>
+    where
+        T: DecodableSection,
+    {
+        let idx = self.section_index(T::ID)?;
+        let s = self.section_at(idx);
+        let ctx = DecodeContext::new(self.options);
+        Some(traced(&ctx, T::ID, s, |_, s, ctx| T::decode_str(s, ctx)))
     }
 
     /// Decodes and returns all sections present in this GPP string.
@@ -335,35 +749,467 @@ impl GPPString {
         self.section_ids
             .iter()
             .enumerate()
-            .map(|(idx, id)| decode_section(*id, self.section_at(idx)))
+            .map(|(idx, &id)| {
+                let ctx = DecodeContext::new(self.options);
+                traced(&ctx, id, self.section_at(idx), decode_section)
+            })
             .collect()
     }
 
-    #[inline]
-    fn section_index(&self, id: SectionId) -> Option<usize> {
-        self.section_ids.iter().position(|&section_id| section_id == id)
+    /// Decodes and returns a single section of this GPP string, along with the non-fatal
+    /// oddities found while decoding it.
+    ///
+    /// This behaves exactly like [`decode_section`](GPPString::decode_section), but also
+    /// reports what was tolerated, such as truncated publisher restrictions, so that callers
+    /// can tell whether a string decoded with caveats.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::sections::{DecodeWarning, SectionId};
+    /// use iab_gpp::v1::GPPString;
+    ///
+    /// let gpp_str = GPPString::parse_str("DBABDA~CPuy0IAPuy0IAPoABABGCyCAAAAAAAAAAAAAAAAA").unwrap();
+    /// let r = gpp_str.decode_section_with_warnings(SectionId::TcfCaV1).unwrap();
+    ///
+    /// assert_eq!(
+    ///     r.warnings,
+    ///     [DecodeWarning::NonStandardSegmentVersion { segment_version: 2 }]
+    /// );
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as [`decode_section`](GPPString::decode_section).
+    ///
+    pub fn decode_section_with_warnings(
+        &self,
+        id: SectionId,
+    ) -> Result<DecodeResult<Section>, SectionDecodeError> {
+        let idx = self
+            .section_index(id)
+            .ok_or(SectionDecodeError::MissingSection(id))?;
+        self.decode_section_at_with_warnings(idx, id)
     }
 
-    #[inline]
-    fn section_at(&self, idx: usize) -> &str {
-        let range = &self.section_ranges[idx];
-        &self.source[range.start..range.end]
+    /// Decodes and returns all sections present in this GPP string, along with the non-fatal
+    /// oddities found while decoding each of them.
+    ///
+    /// See [`decode_section_with_warnings`](GPPString::decode_section_with_warnings).
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`SectionDecodeError`] for each section which fails to decode.
+    ///
+    pub fn decode_all_sections_with_warnings(
+        &self,
+    ) -> Vec<Result<DecodeResult<Section>, SectionDecodeError>> {
+        self.section_ids
+            .iter()
+            .enumerate()
+            .map(|(idx, &id)| self.decode_section_at_with_warnings(idx, id))
+            .collect()
     }
-}
-
-impl FromStr for GPPString {
-    type Err = GPPDecodeError;
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (section_ids, section_ranges) = extract_gpp_sections_from_str(s)?;
-
-        Ok(Self {
-            section_ids,
-            source: s.into(),
-            section_ranges,
+    fn decode_section_at_with_warnings(
+        &self,
+        idx: usize,
+        id: SectionId,
+    ) -> Result<DecodeResult<Section>, SectionDecodeError> {
+        let ctx = DecodeContext::new(self.options);
+        let value = traced(&ctx, id, self.section_at(idx), decode_section)?;
+        Ok(DecodeResult {
+            value,
+            warnings: ctx.into_warnings(),
         })
     }
-}
+
+    /// Decodes and validates every section of this GPP string, returning all issues found
+    /// along with the id of the section they were found in.
+    ///
+    /// Sections which fail to decode contribute a [`ValidationWarning::DecodeFailed`] entry.
+    /// See [`Section::validate`] for the checks applied to decoded sections.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::v1::GPPString;
+    ///
+    /// let gpp_str = GPPString::parse_str("DBACNY~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN").unwrap();
+    ///
+    /// assert!(gpp_str.validate_all().is_empty());
+    /// ```
+    pub fn validate_all(&self) -> Vec<(SectionId, ValidationWarning)> {
+        self.section_ids
+            .iter()
+            .zip(self.decode_all_sections())
+            .flat_map(|(&id, r)| {
+                let warnings = match r {
+                    Ok(section) => section.validate(),
+                    Err(e) => vec![ValidationWarning::DecodeFailed(e)],
+                };
+                warnings.into_iter().map(move |w| (id, w))
+            })
+            .collect()
+    }
+
+    /// Returns whether the Global Privacy Control signal applies, according to the first US
+    /// section which carries a GPC segment.
+    ///
+    /// Returns `None` if no US section carries a GPC segment. Sections which fail to decode
+    /// are ignored, use [`decode_section`](GPPString::decode_section) to obtain their errors.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::v1::GPPString;
+    /// use iab_gpp::v1::GPPDecodeError;
+    ///
+    /// fn main() -> Result<(), GPPDecodeError> {
+    ///     let gpp_str = GPPString::parse_str("DBABVg~BVVVVVVg.YA")?;
+    ///
+    ///     assert_eq!(gpp_str.gpc_applies(), Some(true));
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn gpc_applies(&self) -> Option<bool> {
+        self.section_ids
+            .iter()
+            .enumerate()
+            // TCF sections never carry GPC, don't pay for decoding them
+            .filter(|(_, id)| {
+                !matches!(
+                    id,
+                    SectionId::TcfEuV1 | SectionId::TcfEuV2 | SectionId::TcfCaV1
+                )
+            })
+            .filter_map(|(idx, &id)| {
+                let ctx = DecodeContext::new(self.options);
+                traced(&ctx, id, self.section_at(idx), decode_section).ok()
+            })
+            .find_map(|section| section.gpc())
+    }
+
+    /// Returns the creation timestamp of the first TCF EU v2 or TCF CA section, in seconds
+    /// since the Unix epoch, without decoding the rest of the section.
+    ///
+    /// This is the field checked to decide whether consent is stale and must be refreshed.
+    /// Returns `None` if no such section is present, or if its core segment cannot be read.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::v1::GPPString;
+    /// use iab_gpp::v1::GPPDecodeError;
+    ///
+    /// fn main() -> Result<(), GPPDecodeError> {
+    ///     let gpp_str =
+    ///         GPPString::parse_str("DBACNY~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN")?;
+    ///
+    ///     assert_eq!(gpp_str.tcf_created(), Some(1650492000));
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn tcf_created(&self) -> Option<u64> {
+        let (idx, id) = self
+            .section_ids
+            .iter()
+            .enumerate()
+            .find(|(_, id)| matches!(id, SectionId::TcfEuV2 | SectionId::TcfCaV1))?;
+        let s = self.section_at(idx);
+        match id {
+            SectionId::TcfEuV2 => TcfEuV2::peek_created(s).ok(),
+            _ => TcfCaV1::peek_created(s).ok(),
+        }
+    }
+
+    /// Returns whether this GPP string carries the same consent as `other`, comparing decoded
+    /// sections rather than their encoded form.
+    ///
+    /// Two strings differ in bytes but not in meaning when, for instance, a vendor set is
+    /// encoded as a bitfield in one and as ranges in the other, or when a section has trailing
+    /// padding. Both strings must have the same sections in the same order. Sections which fail
+    /// to decode are compared by their raw text.
+    ///
+    /// This decodes every section of both strings, which is far more expensive than comparing
+    /// them with [`as_str`](GPPString::as_str). Prefer plain string equality to detect that a
+    /// string did not change at all, and use this method to decide whether a changed string
+    /// actually changed consent, e.g. before triggering a consent refresh.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use iab_gpp::v1::GPPString;
+    ///
+    /// let a = GPPString::parse_str("DBABM~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA").unwrap();
+    /// let b = GPPString::parse_str("DBABM~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAAAA").unwrap();
+    ///
+    /// assert_ne!(a.as_str(), b.as_str());
+    /// assert!(a.content_eq(&b));
+    /// ```
+    pub fn content_eq(&self, other: &GPPString) -> bool {
+        if self.section_ids != other.section_ids {
+            return false;
+        }
+        self.section_ids
+            .iter()
+            .enumerate()
+            .zip(self.decode_all_sections())
+            .zip(other.decode_all_sections())
+            .all(|(((idx, _), a), b)| match (a, b) {
+                (Ok(a), Ok(b)) => a == b,
+                (Err(_), Err(_)) => self.section_at(idx) == other.section_at(idx),
+                _ => false,
+            })
+    }
+
+    #[inline]
+    fn section_index(&self, id: SectionId) -> Option<usize> {
+        self.section_ids.iter().position(|&section_id| section_id == id)
+    }
+
+    #[inline]
+    fn section_at(&self, idx: usize) -> &str {
+        let range = &self.section_ranges[idx];
+        &self.source[range.start..range.end]
+    }
+}
+
+impl AsRef<str> for GPPString {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl TryFrom<&str> for GPPString {
+    type Error = GPPDecodeError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl FromStr for GPPString {
+    type Err = GPPDecodeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (section_ids, section_ranges) = extract_gpp_sections_from_str(s)?;
+
+        Ok(Self {
+            section_ids,
+            source: s.into(),
+            section_ranges,
+            options: DecodeOptions::default(),
+        })
+    }
+}
+
+/// Parses newline-delimited GPP strings from a reader, one line at a time.
+///
+/// Only the current line is kept in memory, which makes this suitable for large log files or
+/// sockets. Blank lines are skipped and surrounding whitespace is trimmed before each line is
+/// parsed with [`GPPString::parse_str`].
+///
+/// Each item is the result of parsing one line. An I/O error while reading is returned as
+/// [`GPPDecodeError::Read`].
+///
+/// # Example
+///
+/// ```
+/// use iab_gpp::sections::SectionId;
+/// use iab_gpp::v1::decode_reader;
+///
+/// let input = "DBABTA~1YNN\n\nDBABTA~1NNN\r\n";
+/// let ids = decode_reader(input.as_bytes())
+///     .map(|r| r.unwrap().section_ids().copied().collect::<Vec<_>>())
+///     .collect::<Vec<_>>();
+///
+/// assert_eq!(ids, vec![vec![SectionId::UspV1], vec![SectionId::UspV1]]);
+/// ```
+///
+pub fn decode_reader<R: BufRead>(r: R) -> DecodeReader<R> {
+    DecodeReader(r.lines())
+}
+
+/// Created with the function [`decode_reader`].
+pub struct DecodeReader<R>(Lines<R>);
+
+impl<R: BufRead> Iterator for DecodeReader<R> {
+    type Item = Result<GPPString, GPPDecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for line in self.0.by_ref() {
+            match line {
+                Ok(line) => {
+                    let line = line.trim();
+                    if !line.is_empty() {
+                        return Some(line.parse());
+                    }
+                }
+                Err(e) => return Some(Err(e.into())),
+            }
+        }
+        None
+    }
+}
+
+/// Parses newline-delimited GPP strings from chunks of bytes, as they arrive.
+///
+/// This is meant for transports which split strings across reads, such as TCP sockets: chunks
+/// are given to [`feed`](GppStreamDecoder::feed), which returns the strings completed by each
+/// chunk, and [`finish`](GppStreamDecoder::finish) parses whatever remains once the input ends.
+/// Lines are handled like with [`decode_reader`]: blank lines are skipped and surrounding
+/// whitespace is trimmed.
+///
+/// Only the current incomplete line is buffered. If
+/// [`max_input_len`](DecodeOptions::max_input_len) is set, a longer line stops being buffered
+/// and is reported as [`GPPDecodeError::InputTooLong`] once its end is reached.
+///
+/// # Example
+///
+/// ```
+/// use iab_gpp::sections::SectionId;
+/// use iab_gpp::v1::GppStreamDecoder;
+///
+/// let mut decoder = GppStreamDecoder::new();
+///
+/// assert!(decoder.feed(b"DBABTA~1").is_empty());
+/// let completed = decoder.feed(b"YNN\nDBAB");
+/// assert_eq!(completed.len(), 1);
+/// assert_eq!(completed[0].as_ref().unwrap().section(SectionId::UspV1), Some("1YNN"));
+///
+/// assert!(decoder.feed(b"TA~1NNN").is_empty());
+/// let last = decoder.finish().unwrap().unwrap();
+/// assert_eq!(last.section(SectionId::UspV1), Some("1NNN"));
+/// ```
+///
+#[derive(Debug, Default)]
+pub struct GppStreamDecoder {
+    buf: Vec<u8>,
+    options: DecodeOptions,
+    /// Length of the current line when it exceeded the maximum length, and stopped being
+    /// buffered.
+    overflow_len: Option<usize>,
+}
+
+impl GppStreamDecoder {
+    /// Creates a decoder which parses strings with the default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a decoder which parses strings with
+    /// [`parse_str_with_options`](GPPString::parse_str_with_options).
+    pub fn with_options(options: DecodeOptions) -> Self {
+        Self {
+            options,
+            ..Self::default()
+        }
+    }
+
+    /// Consumes a chunk of input, and returns the result of parsing each line it completed, in
+    /// order.
+    ///
+    /// Bytes after the last newline of the chunk are buffered until a later chunk completes
+    /// their line, or until [`finish`](GppStreamDecoder::finish) is called.
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<Result<GPPString, GPPDecodeError>> {
+        let mut completed = vec![];
+        let mut lines = chunk.split(|&b| b == b'\n');
+        // the last piece is never terminated, even if empty
+        let pending = lines.next_back().unwrap_or_default();
+
+        for piece in lines {
+            self.push(piece);
+            completed.extend(self.take_line());
+        }
+        self.push(pending);
+
+        completed
+    }
+
+    /// Ends the input, and parses the bytes buffered after the last newline, if any.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`GPPDecodeError`] if the remaining bytes are not a valid GPP string, which
+    /// usually means that the input was cut in the middle of a string.
+    pub fn finish(mut self) -> Result<Option<GPPString>, GPPDecodeError> {
+        self.take_line().transpose()
+    }
+
+    fn push(&mut self, piece: &[u8]) {
+        if let Some(len) = &mut self.overflow_len {
+            *len += piece.len();
+            return;
+        }
+
+        self.buf.extend_from_slice(piece);
+        if let Some(max) = self.options.max_input_len
+            && self.buf.len() > max
+        {
+            self.overflow_len = Some(self.buf.len());
+            self.buf = vec![];
+        }
+    }
+
+    /// Parses the buffered line and resets the buffer, returns [`None`] if the line is blank.
+    fn take_line(&mut self) -> Option<Result<GPPString, GPPDecodeError>> {
+        if let Some(len) = self.overflow_len.take() {
+            let max = self.options.max_input_len.unwrap_or_default();
+            return Some(Err(GPPDecodeError::InputTooLong { len, max }));
+        }
+
+        let line = std::mem::take(&mut self.buf);
+        let line = line.trim_ascii();
+        if line.is_empty() {
+            return None;
+        }
+
+        Some(match std::str::from_utf8(line) {
+            Ok(s) if s.is_ascii() => GPPString::parse_str_with_options(s, &self.options),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidData, "non-ASCII byte").into()),
+        })
+    }
+}
+
+/// Decodes a section with the given function and context, logging its id, input length and
+/// decode duration if the `trace` feature is enabled.
+///
+/// The length is the number of characters of the section string, segment separators included,
+/// not the number of bits the decoder consumed, which may stop before the end of a segment.
+#[inline]
+fn traced<T, F>(
+    ctx: &DecodeContext,
+    id: SectionId,
+    s: &str,
+    decode: F,
+) -> Result<T, SectionDecodeError>
+where
+    F: FnOnce(SectionId, &str, &DecodeContext) -> Result<T, SectionDecodeError>,
+{
+    // an empty fragment can only come from consecutive delimiters, such as in `DBACNY~~1YNN`,
+    // report it as such rather than as whatever error the section parser would raise
+    if s.is_empty() {
+        return Err(SectionDecodeError::EmptyFragment { id });
+    }
+
+    #[cfg(feature = "trace")]
+    let start = std::time::Instant::now();
+
+    let r = decode(id, s, ctx);
+
+    #[cfg(feature = "trace")]
+    log::trace!(
+        "decoded section {id} ({} input characters) in {:?}: {}",
+        s.len(),
+        start.elapsed(),
+        if r.is_ok() { "ok" } else { "error" },
+    );
+
+    r
+}
 
 fn extract_gpp_sections_from_str(
     s: &str,
@@ -388,27 +1234,44 @@ fn extract_gpp_sections_from_str(
         .into_iter()
         .map(|id| SectionId::from_u8(id).ok_or(GPPDecodeError::UnsupportedSectionId(id)))
         .collect::<Result<Vec<_>, _>>()?;
+    check_section_order(&section_ids)?;
 
-    let sections = sections_iter.collect::<Vec<_>>();
-    if sections.len() != section_ids.len() {
+    // the header tells us how many sections to expect, so reserve room for exactly that
+    // and compute section ranges directly from the split iterator
+    let base = s.as_ptr() as usize;
+    let mut section_ranges = Vec::with_capacity(section_ids.len());
+    section_ranges.extend(sections_iter.map(|section| {
+        let start = section.as_ptr() as usize - base;
+        start..(start + section.len())
+    }));
+
+    if section_ranges.len() != section_ids.len() {
         return Err(GPPDecodeError::IdSectionMismatch {
             ids: section_ids.len(),
-            sections: sections.len(),
+            sections: section_ranges.len(),
         });
     }
 
-    let base = s.as_ptr() as usize;
-    let section_ranges = sections
-        .into_iter()
-        .map(|section| {
-            let start = section.as_ptr() as usize - base;
-            start..(start + section.len())
-        })
-        .collect();
-
     Ok((section_ids, section_ranges))
 }
 
+/// Checks that section ids are unique and in ascending order, as required by the specification.
+///
+/// Since Fibonacci ranges are delta-encoded with positive offsets, this only fails if the ids
+/// were decoded incorrectly, which would otherwise make lookups by id ambiguous.
+fn check_section_order(section_ids: &[SectionId]) -> Result<(), GPPDecodeError> {
+    for pair in section_ids.windows(2) {
+        let (previous, id) = (pair[0], pair[1]);
+        if previous == id {
+            return Err(GPPDecodeError::DuplicateSection { id });
+        }
+        if (previous as u8) > (id as u8) {
+            return Err(GPPDecodeError::SectionsOutOfOrder { previous, id });
+        }
+    }
+    Ok(())
+}
+
 /// Created with the method [`sections`](GPPString::sections).
 pub struct Sections<'a> {
     gpp_str: &'a GPPString,
@@ -458,6 +1321,7 @@ impl<'a> FusedIterator for SectionIds<'a> {}
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::sections::DecodeWarning;
     use crate::sections::uspv1::UspV1;
     use test_case::test_case;
 
@@ -478,6 +1342,12 @@ mod tests {
         GPPString::from_str(s).unwrap().section_ids
     }
 
+    #[test_case("DBABM~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA" => 1 ; "single section")]
+    #[test_case("DBACNY~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN" => 2 ; "tcf eu and us sections")]
+    fn gpp_string_section_count(s: &str) -> usize {
+        GPPString::from_str(s).unwrap().section_count()
+    }
+
     #[test_case("DBABM~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA" => vec!["CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA"] ; "single section")]
     #[test_case("DBACNY~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN" => vec!["CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA", "1YNN"] ; "tcf eu and us sections")]
     #[test_case("DBABjw~BPXuQIAPXuQIAAfKABENB-CgAAAAAAAAAAAAAAAA.YAAAAAAAAAA~1YNN" => vec!["BPXuQIAPXuQIAAfKABENB-CgAAAAAAAAAAAAAAAA.YAAAAAAAAAA", "1YNN"] ; "tcf ca and us sections")]
@@ -536,6 +1406,439 @@ mod tests {
         GPPString::from_str(s).unwrap().decode().unwrap()
     }
 
+    #[test_case("DBABTA~1YNN" ; "usp v1")]
+    #[test_case("DBACNY~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN" ; "tcf eu and us sections")]
+    #[test_case("DBABjw~BPXuQIAPXuQIAAfKABENB-CgAAAAAAAAAAAAAAAA.YAAAAAAAAAA~1YNN" ; "tcf ca and us sections")]
+    fn from_bytes_matches_from_str(s: &str) {
+        let from_bytes = GPPString::from_bytes(s.as_bytes()).unwrap();
+        let from_str = GPPString::from_str(s).unwrap();
+
+        assert_eq!(from_bytes.as_str(), from_str.as_str());
+        assert_eq!(from_bytes.section_ids, from_str.section_ids);
+        assert_eq!(from_bytes.section_ranges, from_str.section_ranges);
+    }
+
+    #[test_case(b"" ; "empty")]
+    #[test_case(b"DB*BTA~1YNN" ; "invalid base64")]
+    #[test_case(b"DBABTA~1YN\xc3\xa9" ; "non ascii")]
+    #[test_case(b"DBABTA~1YN\xff" ; "invalid utf8")]
+    fn from_bytes_error(b: &[u8]) {
+        assert!(matches!(
+            GPPString::from_bytes(b),
+            Err(GPPDecodeError::Read { .. })
+        ));
+    }
+
+    #[test_case("DBABVg~BVVVVVVg.YA" => Some(true) ; "us ct applying")]
+    #[test_case("DBABVg~BVVVVVVg.QA" => Some(false) ; "us ct not applying")]
+    #[test_case("DBABVg~BVVVVVVg" => None ; "us ct absent")]
+    #[test_case("DBABAY~BVVVVVVVW.YA" => Some(true) ; "us nj applying")]
+    #[test_case("DBABTA~1YNN" => None ; "usp v1")]
+    #[test_case("DBACNY~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN" => None ; "tcf eu and usp v1")]
+    fn gpc_applies(s: &str) -> Option<bool> {
+        GPPString::from_str(s).unwrap().gpc_applies()
+    }
+
+    #[test_case("DBACNY~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN" => Some(1650492000) ; "tcf eu and usp v1")]
+    #[test_case("DBABjw~BPuy0IAPuy0IAPoABABGCyCAAAAAAAAAAAAAAAAA~1YNN" => Some(1689120000) ; "tcf ca and usp v1")]
+    #[test_case("DBABTA~1YNN" => None ; "usp v1 only")]
+    #[test_case("DBABM~DPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA" => None ; "unknown tcf eu version")]
+    fn tcf_created(s: &str) -> Option<u64> {
+        GPPString::from_str(s).unwrap().tcf_created()
+    }
+
+    #[test_case("DBABM~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA", "DBABM~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA" => true ; "identical")]
+    #[test_case("DBABM~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA", "DBABM~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAAAA" => true ; "trailing padding")]
+    #[test_case("DBABM~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA", "DBABM~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA.QAAA" => false ; "extra segment")]
+    #[test_case("DBABM~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA", "DBACNY~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN" => false ; "extra section")]
+    #[test_case("DBABTA~1YNN", "DBABTA~1YYN" => false ; "different consent")]
+    #[test_case("DBABM~CPX", "DBABM~CPX" => true ; "same invalid section")]
+    #[test_case("DBABM~CPX", "DBABM~CPY" => false ; "different invalid sections")]
+    fn content_eq(a: &str, b: &str) -> bool {
+        let a = GPPString::from_str(a).unwrap();
+        let b = GPPString::from_str(b).unwrap();
+        assert_eq!(a.content_eq(&b), b.content_eq(&a));
+        a.content_eq(&b)
+    }
+
+    #[test]
+    fn header_with_overflowing_range_is_rejected() {
+        assert!(GPPString::from_str("DBzlBrNNaOJqpjXwL2kohh1YY47gMVznYGwLSvQlRRaYs4b-3X").is_err());
+    }
+
+    #[test_case(SectionId::UsIn ; "us in")]
+    #[test_case(SectionId::UsKy ; "us ky")]
+    #[test_case(SectionId::UsMd ; "us md")]
+    #[test_case(SectionId::UsRi ; "us ri")]
+    fn section_with_overflowing_integer_is_rejected(id: SectionId) {
+        let gpp = GPPString::from_section_str(id, "QULGkis");
+        assert!(gpp.decode_section(id).is_err());
+    }
+
+    #[test]
+    fn try_from_gpp_string() {
+        let gpp =
+            GPPString::from_str("DBABjw~BPXuQIAPXuQIAAfKABENB-CgAAAAAAAAAAAAAAAA.YAAAAAAAAAA~1YNN")
+                .unwrap();
+
+        let usp: UspV1 = (&gpp).try_into().unwrap();
+        assert_eq!(usp.opt_out_notice, crate::sections::uspv1::Flag::Yes);
+
+        let tcfca: Result<crate::sections::tcfcav1::TcfCaV1, _> = (&gpp).try_into();
+        assert!(tcfca.is_ok());
+
+        let tcfeu: Result<crate::sections::tcfeuv2::TcfEuV2, _> = (&gpp).try_into();
+        assert!(matches!(
+            tcfeu,
+            Err(SectionDecodeError::MissingSection(SectionId::TcfEuV2))
+        ));
+    }
+
+    #[test]
+    fn from_section_str() {
+        let gpp = GPPString::from_section_str(
+            SectionId::TcfEuV2,
+            "CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA",
+        );
+
+        assert_eq!(gpp.section_ids().len(), 1);
+        assert_eq!(
+            gpp.sections().collect::<Vec<_>>(),
+            vec!["CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA"]
+        );
+        assert!(matches!(
+            gpp.decode_section(SectionId::TcfEuV2),
+            Ok(Section::TcfEuV2(_))
+        ));
+        assert!(matches!(
+            gpp.decode_section(SectionId::UspV1),
+            Err(SectionDecodeError::MissingSection(SectionId::UspV1))
+        ));
+    }
+
+    #[test_case("DBABTA~1YNN", None => matches Ok(_) ; "no limit")]
+    #[test_case("DBABTA~1YNN", Some(11) => matches Ok(_) ; "exact limit")]
+    #[test_case("DBABTA~1YNN", Some(10) => matches Err(GPPDecodeError::InputTooLong { len: 11, max: 10 }) ; "over limit")]
+    fn max_input_len(s: &str, max: Option<usize>) -> Result<GPPString, GPPDecodeError> {
+        let mut options = DecodeOptions::new();
+        options.max_input_len = max;
+        GPPString::parse_str_with_options(s, &options)
+    }
+
+    #[test_case(None => matches Err(SectionDecodeError::Read { .. }) ; "no limit")]
+    #[test_case(Some(16) => matches Err(SectionDecodeError::Read { source }) if source.kind() == io::ErrorKind::InvalidData ; "over limit")]
+    fn max_ranges(max: Option<usize>) -> Result<Section, SectionDecodeError> {
+        // TCF CA section declaring 4095 publisher restrictions, none of which are present
+        let s = "DBABDA~BPuy0IAPuy0IAPoABABGCyCAAAAAAAAAAAAAAAB_-";
+        let mut options = DecodeOptions::new();
+        options.max_ranges = max;
+        GPPString::parse_str_with_options(s, &options)
+            .unwrap()
+            .decode_section(SectionId::TcfCaV1)
+    }
+
+    #[test_case("DBABDA~BPuy0IAPuy0IAPoABABGCyCAAAAAAAAAAAAAAAAA" => Vec::<DecodeWarning>::new() ; "clean")]
+    #[test_case("DBABDA~CPuy0IAPuy0IAPoABABGCyCAAAAAAAAAAAAAAAAA" => vec![DecodeWarning::NonStandardSegmentVersion { segment_version: 2 }] ; "tcf ca version 2")]
+    #[test_case("DBABMA~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA" => Vec::<DecodeWarning>::new() ; "tcf eu clean")]
+    fn decode_section_with_warnings(s: &str) -> Vec<DecodeWarning> {
+        let gpp = GPPString::from_str(s).unwrap();
+        let id = gpp.section_ids().next().copied().unwrap();
+        gpp.decode_section_with_warnings(id).unwrap().warnings
+    }
+
+    #[test]
+    fn decode_all_sections_with_warnings() {
+        let gpp =
+            GPPString::from_str("DBABjw~CPuy0IAPuy0IAPoABABGCyCAAAAAAAAAAAAAAAAA~1YNN").unwrap();
+        let r = gpp.decode_all_sections_with_warnings();
+
+        assert_eq!(r.len(), 2);
+        assert!(!r[0].as_ref().unwrap().is_clean());
+        assert!(r[1].as_ref().unwrap().is_clean());
+        // warnings are not collected by the regular decode methods
+        assert!(gpp.decode_all_sections().iter().all(Result::is_ok));
+    }
+
+    #[test]
+    fn validate_all() {
+        // TCF EU v2 with a reserved CMP id, and an undecodable USP
+        let gpp =
+            GPPString::from_str("DBACNY~CPXxRfAPXxRfAAAKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YN").unwrap();
+        let r = gpp.validate_all();
+
+        assert_eq!(r.len(), 2, "{r:?}");
+        assert!(matches!(
+            r[0],
+            (
+                SectionId::TcfEuV2,
+                ValidationWarning::ReservedCmpId { cmp_id: 0 }
+            )
+        ));
+        assert!(matches!(
+            r[1],
+            (SectionId::UspV1, ValidationWarning::DecodeFailed(_))
+        ));
+
+        // US Virginia with both MSPA modes set
+        let gpp = GPPString::from_str("DBABRg~BVVVVVVV").unwrap();
+        let r = gpp.validate_all();
+
+        assert!(matches!(
+            r[..],
+            [(SectionId::UsVa, ValidationWarning::ConflictingMspaModes)]
+        ));
+    }
+
+    #[test]
+    fn decode_reader_lines() {
+        let input = "  DBABTA~1YNN  \n\n   \nnot a gpp string\nDBACNY~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN";
+        let r = decode_reader(input.as_bytes()).collect::<Vec<_>>();
+
+        assert_eq!(r.len(), 3);
+        assert_eq!(r[0].as_ref().unwrap().as_str(), "DBABTA~1YNN");
+        assert!(r[1].is_err());
+        assert_eq!(r[2].as_ref().unwrap().section_count(), 2);
+    }
+
+    #[test]
+    fn decode_reader_invalid_utf8() {
+        let input = b"DBABTA~1YNN\n\xff\n";
+        let r = decode_reader(&input[..]).collect::<Vec<_>>();
+
+        assert_eq!(r.len(), 2);
+        assert!(r[0].is_ok());
+        assert!(matches!(r[1], Err(GPPDecodeError::Read { .. })));
+    }
+
+    fn feed_all(decoder: &mut GppStreamDecoder, chunks: &[&[u8]]) -> Vec<String> {
+        chunks
+            .iter()
+            .flat_map(|chunk| decoder.feed(chunk))
+            .map(|r| r.map_or_else(|e| format!("error: {e}"), |g| g.as_str().to_string()))
+            .collect()
+    }
+
+    #[test_case(&[b"DBABTA~1YNN\n"] ; "single chunk")]
+    #[test_case(&[b"DBAB", b"TA~1Y", b"NN", b"\n"] ; "split string")]
+    #[test_case(&[b"DBABTA~1YNN\r", b"\n\n  \n"] ; "crlf and blank lines")]
+    #[test_case(&[b"  DBABTA~1YNN  \nDBABTA~1NNN\n"] ; "two strings")]
+    fn stream_decoder(chunks: &[&[u8]]) {
+        let mut decoder = GppStreamDecoder::new();
+        let r = feed_all(&mut decoder, chunks);
+
+        assert_eq!(r[0], "DBABTA~1YNN");
+        assert!(r[1..].iter().all(|s| s == "DBABTA~1NNN"));
+        assert!(decoder.finish().unwrap().is_none());
+    }
+
+    #[test]
+    fn stream_decoder_finish() {
+        let mut decoder = GppStreamDecoder::new();
+        assert!(decoder.feed(b"DBABTA~1YNN\nDBACNY~CPXxRf").len() == 1);
+        assert!(matches!(
+            decoder.finish(),
+            Err(GPPDecodeError::IdSectionMismatch {
+                ids: 2,
+                sections: 1
+            })
+        ));
+
+        let mut decoder = GppStreamDecoder::new();
+        decoder.feed(b"DBABTA~1YNN  ");
+        assert_eq!(decoder.finish().unwrap().unwrap().as_str(), "DBABTA~1YNN");
+    }
+
+    #[test]
+    fn stream_decoder_errors() {
+        let options = DecodeOptions::new().with_max_input_len(12);
+        let mut decoder = GppStreamDecoder::with_options(options);
+        let r = feed_all(
+            &mut decoder,
+            &[
+                b"DBABTA~1YNN\nDBACNY~CPXxRf",
+                b"APXxRfAAfKABENB",
+                b"\n\xff\nDBABTA~1NNN\n",
+            ],
+        );
+
+        assert_eq!(
+            r,
+            [
+                "DBABTA~1YNN",
+                "error: input too long (28 bytes, maximum 12)",
+                "error: unable to read string: non-ASCII byte",
+                "DBABTA~1NNN",
+            ]
+        );
+        assert!(decoder.buf.capacity() <= 2 * 12);
+    }
+
+    #[test]
+    fn read_error_source() {
+        let e = GPPString::from_str("DB").unwrap_err();
+
+        let source = std::error::Error::source(&e)
+            .and_then(|s| s.downcast_ref::<io::Error>())
+            .expect("io error source");
+        assert_eq!(source.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test_case("DBABTA~1YNN" => matches Ok(_) ; "not encoded")]
+    #[test_case("DBABTA%7E1YNN" => matches Ok(_) ; "encoded tilde")]
+    #[test_case("DBABTA%7e1YNN" => matches Ok(_) ; "lowercase hex")]
+    #[test_case("%44%42%41%42%54%41%7E%31%59%4E%4E" => matches Ok(_) ; "fully encoded")]
+    #[test_case("DBABTA%7" => matches Err(GPPDecodeError::Read { .. }) ; "truncated escape")]
+    #[test_case("DBABTA%7G1YNN" => matches Err(GPPDecodeError::Read { .. }) ; "invalid hex")]
+    #[test_case("DBABTA%+71YNN" => matches Err(GPPDecodeError::Read { .. }) ; "sign in escape")]
+    #[test_case("DBABTA%C3%A9" => matches Err(GPPDecodeError::Read { .. }) ; "non ascii")]
+    fn from_query_param(s: &str) -> Result<String, GPPDecodeError> {
+        GPPString::from_query_param(s).map(|gpp| gpp.as_str().to_string())
+    }
+
+    #[test]
+    fn from_query_param_decodes_sections() {
+        let gpp = GPPString::from_query_param("DBABTA%7E1YNN").unwrap();
+
+        assert_eq!(gpp.as_str(), "DBABTA~1YNN");
+        assert_eq!(gpp.section(SectionId::UspV1), Some("1YNN"));
+    }
+
+    #[test]
+    fn three_single_section_ids() {
+        let gpp = GPPString::from_str(
+            "DBADNb~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN~BAAAAAAAAABA.QA",
+        )
+        .unwrap();
+
+        assert_eq!(
+            gpp.section_ids().copied().collect::<Vec<_>>(),
+            [SectionId::TcfEuV2, SectionId::UspV1, SectionId::UsNat]
+        );
+        assert_eq!(gpp.section(SectionId::UsNat), Some("BAAAAAAAAABA.QA"));
+    }
+
+    #[test_case(&[SectionId::TcfEuV2, SectionId::UspV1] => matches Ok(()) ; "ascending")]
+    #[test_case(&[SectionId::UspV1, SectionId::UspV1] => matches Err(GPPDecodeError::DuplicateSection { id: SectionId::UspV1 }) ; "duplicate")]
+    #[test_case(&[SectionId::TcfEuV2, SectionId::UsNat, SectionId::TcfEuV2] => matches Err(GPPDecodeError::SectionsOutOfOrder { previous: SectionId::UsNat, id: SectionId::TcfEuV2 }) ; "out of order")]
+    fn check_section_order(ids: &[SectionId]) -> Result<(), GPPDecodeError> {
+        super::check_section_order(ids)
+    }
+
+    #[test_case("DBACNY~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN" => matches Ok(()) ; "valid")]
+    #[test_case("DBABMA~CPXuQIAPXuQIAAfKABENB-CgACAAAAAAAAYgF5wAQF5gAAAA.YAAAAAAAAAAA" => matches Ok(()) ; "segments")]
+    #[test_case("DBACNY~~1YNN" => matches Err(GPPDecodeError::EmptySection { id: SectionId::TcfEuV2 }) ; "empty section")]
+    #[test_case("DBABTA~1Y N" => matches Err(GPPDecodeError::InvalidByte { offset: 9, byte: b' ' }) ; "space")]
+    #[test_case("DBABM~CPXxRfAPXxRfAAfKABENB+CgAAAAAAAAAAYgAAAAAAAA" => matches Err(GPPDecodeError::InvalidByte { offset: 27, byte: b'+' }) ; "standard alphabet")]
+    #[test_case("DBABTA=~1YNN" => matches Err(GPPDecodeError::InvalidByte { offset: 6, byte: b'=' }) ; "header padding")]
+    #[test_case("DBABTA.~1YNN" => matches Err(GPPDecodeError::InvalidByte { offset: 6, byte: b'.' }) ; "header segment separator")]
+    #[test_case("DBABTA" => matches Err(GPPDecodeError::IdSectionMismatch { ids: 1, sections: 0 }) ; "missing section")]
+    #[test_case("CBABTA~1YNN" => matches Err(GPPDecodeError::InvalidHeaderType { found: 2 }) ; "invalid header")]
+    fn validate_structure(s: &str) -> Result<(), GPPDecodeError> {
+        GPPString::validate_structure(s)
+    }
+
+    #[test]
+    fn empty_fragment() {
+        let gpp = GPPString::from_str("DBACNY~~1YNN").unwrap();
+
+        assert_eq!(gpp.sections().collect::<Vec<_>>(), ["", "1YNN"]);
+        assert!(matches!(
+            gpp.decode_section(SectionId::TcfEuV2),
+            Err(SectionDecodeError::EmptyFragment {
+                id: SectionId::TcfEuV2
+            })
+        ));
+        assert!(matches!(
+            gpp.decode::<TcfEuV2>(),
+            Err(SectionDecodeError::EmptyFragment { .. })
+        ));
+        assert!(matches!(
+            gpp.decode_section(SectionId::UspV1),
+            Ok(Section::UspV1(_))
+        ));
+    }
+
+    #[test]
+    fn decode_first() {
+        let s = "DBACNY~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YN";
+        let gpp = GPPString::from_str(s).unwrap();
+
+        assert!(matches!(gpp.decode_first::<TcfEuV2>(), Some(Ok(_))));
+        assert!(matches!(
+            gpp.decode_first::<UspV1>(),
+            Some(Err(SectionDecodeError::UnexpectedEndOfString(_)))
+        ));
+        assert!(gpp.decode_first::<TcfCaV1>().is_none());
+    }
+
+    #[test_case("DBABTA~1YNN\n" ; "trailing newline")]
+    #[test_case(" DBABTA ~ 1YNN" ; "spaces")]
+    #[test_case("DBA\r\nBTA~1Y\tNN" ; "inside header and section")]
+    fn skip_whitespace(s: &str) {
+        let strict = GPPString::parse_str(s);
+        assert!(strict.is_err() || strict.unwrap().decode::<UspV1>().is_err());
+
+        // the limit applies once whitespace is skipped
+        let options = DecodeOptions::new()
+            .with_skip_whitespace()
+            .with_max_input_len(11);
+        let gpp = GPPString::parse_str_with_options(s, &options).unwrap();
+
+        assert_eq!(gpp.as_str(), "DBABTA~1YNN");
+        assert!(gpp.decode::<UspV1>().is_ok());
+    }
+
+    #[test_case("DBABTA~1YNN" => Some("DBABTA".to_string()) ; "one section")]
+    #[test_case("DBACNY~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN" => Some("DBACNY".to_string()) ; "two sections")]
+    #[test_case("DBAA" => Some("DBAA".to_string()) ; "no section")]
+    fn header(s: &str) -> Option<String> {
+        GPPString::from_str(s).unwrap().header().map(str::to_string)
+    }
+
+    #[test]
+    fn header_bytes() {
+        let s = "DBACNY~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN";
+        let bytes = GPPString::from_str(s).unwrap().header_bytes().unwrap();
+
+        // 000011 000001 000000000010 0 011 0 1011 000
+        assert_eq!(bytes, [0x0C, 0x10, 0x02, 0x35, 0x80]);
+    }
+
+    #[test]
+    fn bare_section_has_no_header() {
+        let gpp = GPPString::from_section_str(SectionId::UspV1, "1YNN");
+
+        assert_eq!(gpp.header(), None);
+        assert!(matches!(
+            gpp.header_bytes(),
+            Err(GPPDecodeError::NoHeaderFound)
+        ));
+    }
+
+    #[test]
+    fn as_str_returns_exact_input() {
+        let s = "DBACNY~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN";
+        let gpp = GPPString::from_str(s).unwrap();
+
+        assert_eq!(gpp.as_str(), s);
+        assert_eq!(AsRef::<str>::as_ref(&gpp), s);
+    }
+
+    #[test]
+    fn as_str_preserves_segment_order() {
+        // publisher purposes segment before disclosed vendors, which decodes the same as the
+        // canonical order
+        let s = "DBABM~COvFyGBOvFyGBAbAAAENAPCAAOAAAAAAAAAAAEEUACCKAAA.ZAAgH9794ulA.IFoEUQQgAIQwgIwQABAEAAAAOIAACAIAAAAQAIAgEAACEAAAAAgAQBAAAAAAAGBAAgAAAAAAAFAAECAAAgAAQARAEQAAAAAJAAIAAgAAAYQEAAAQmAgBC3ZAYzUw";
+        let gpp = GPPString::from_str(s).unwrap();
+
+        assert!(gpp.decode::<TcfEuV2>().is_ok());
+        assert_eq!(gpp.as_str(), s);
+        assert_eq!(
+            gpp.section(SectionId::TcfEuV2),
+            s.split_once('~').map(|(_, s)| s)
+        );
+    }
+
     #[test]
     fn truncated_string() {
         let r = GPPString::from_str(
@@ -550,6 +1853,16 @@ mod tests {
         ));
     }
 
+    #[test_case("DBABTA" => (1, 0) ; "header only")]
+    #[test_case("DBACNY~1YNN" => (2, 1) ; "more ids than sections")]
+    #[test_case("DBABTA~1YNN~1YNN" => (1, 2) ; "more sections than ids")]
+    fn id_section_mismatch(s: &str) -> (usize, usize) {
+        match GPPString::from_str(s) {
+            Err(GPPDecodeError::IdSectionMismatch { ids, sections }) => (ids, sections),
+            r => panic!("unexpected result {r:?}"),
+        }
+    }
+
     #[test]
     fn non_gpp_tcfeuv2_string() {
         let r = GPPString::from_str(
@@ -25,18 +25,15 @@ impl TestCase {
     pub fn assert_json_matches(&self) {
         let s = GPPString::from_str(&self.gpp_string).expect("invalid GPP string");
 
-        let sections = s
+        let sections = match s
             .decode_all_sections()
             .into_iter()
-            .collect::<Result<Vec<_>, SectionDecodeError>>();
+            .collect::<Result<Vec<_>, SectionDecodeError>>()
+        {
+            Ok(sections) => sections,
+            Err(e) => panic!("sections decode error: {:?}", e.to_string()),
+        };
 
-        if sections.is_err() {
-            panic!(
-                "sections decode error: {:?}",
-                sections.unwrap_err().to_string()
-            );
-        }
-
-        assert_json_eq!(sections.unwrap(), self.expected_sections);
+        assert_json_eq!(sections, self.expected_sections);
     }
 }
@@ -0,0 +1,72 @@
+//! Conformance tests against decode vectors.
+//!
+//! Each vector in `tests/conformance/vectors.json` lists a GPP string, the section to check and
+//! the expected values of some of its fields, as dotted paths into the serialized section.
+//! Unlike the fixtures in `tests/data`, vectors only assert the fields they list, so they can be
+//! transcribed field by field from the GPP specification or from IAB's published test vectors.
+use iab_gpp::v1::GPPString;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::fs::File;
+use std::str::FromStr;
+
+const VECTORS: &str = "tests/conformance/vectors.json";
+
+#[derive(Deserialize)]
+struct Vector {
+    description: String,
+    gpp_string: String,
+    /// The name of the [`Section`](iab_gpp::sections::Section) variant to check.
+    section: String,
+    fields: Map<String, Value>,
+}
+
+impl Vector {
+    /// Returns a description of every field which does not match the vector.
+    fn mismatches(&self) -> Vec<String> {
+        let gpp_str = match GPPString::from_str(&self.gpp_string) {
+            Ok(gpp_str) => gpp_str,
+            Err(e) => return vec![format!("invalid GPP string: {e}")],
+        };
+
+        let section = gpp_str
+            .decode_all_sections()
+            .into_iter()
+            .filter_map(Result::ok)
+            .map(|section| serde_json::to_value(section).expect("sections always serialize"))
+            .find_map(|mut value| value.get_mut(&self.section).map(Value::take));
+        let Some(section) = section else {
+            return vec![format!("section {} not decoded", self.section)];
+        };
+
+        self.fields
+            .iter()
+            .filter_map(|(path, expected)| {
+                let pointer = format!("/{}", path.replace('.', "/"));
+                match section.pointer(&pointer) {
+                    Some(found) if found == expected => None,
+                    Some(found) => Some(format!("{path}: expected {expected}, found {found}")),
+                    None => Some(format!("{path}: missing")),
+                }
+            })
+            .collect()
+    }
+}
+
+#[test]
+fn conformance_vectors() {
+    let f = File::open(VECTORS).expect("vectors file should exist");
+    let vectors: Vec<Vector> = serde_json::from_reader(f).expect("vectors should be valid");
+    assert!(!vectors.is_empty());
+
+    let failures = vectors
+        .iter()
+        .flat_map(|v| {
+            v.mismatches()
+                .into_iter()
+                .map(move |m| format!("{}: {m}", v.description))
+        })
+        .collect::<Vec<_>>();
+
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
@@ -0,0 +1,53 @@
+//! Real GPP strings gathered from the unit tests and benchmarks, shared by integration tests.
+//!
+//! Every string was emitted by a CMP or transcribed from the GPP specification, so together
+//! they cover the layouts decoders meet in practice. Unlike the fixtures in `tests/data`, they
+//! come without expected values: tests use them to check properties which hold for any valid
+//! string.
+
+/// A real GPP string, along with whether its TCF sections are canonical.
+pub struct Fixture {
+    pub name: &'static str,
+    pub gpp_string: &'static str,
+    /// Whether every TCF section is encoded the way a conforming encoder would encode its
+    /// decoded values: vendor sets use the shorter of their bitfield and range encodings, and
+    /// every declared publisher restriction is present.
+    pub canonical: bool,
+}
+
+pub const FIXTURES: &[Fixture] = &[
+    Fixture {
+        name: "tcf eu v2 and usp v1",
+        gpp_string: "DBACNY~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA~1YNN",
+        canonical: true,
+    },
+    Fixture {
+        name: "tcf eu v2 core only",
+        gpp_string: "DBABM~CPXxRfAPXxRfAAfKABENB-CgAAAAAAAAAAYgAAAAAAAA",
+        canonical: true,
+    },
+    Fixture {
+        name: "tcf eu v2 with all segments",
+        gpp_string: "DBABM~COvFyGBOvFyGBAbAAAENAPCAAOAAAAAAAAAAAEEUACCKAAA.IFoEUQQgAIQwgIwQABAEAAAAOIAACAIAAAAQAIAgEAACEAAAAAgAQBAAAAAAAGBAAgAAAAAAAFAAECAAAgAAQARAEQAAAAAJAAIAAgAAAYQEAAAQmAgBC3ZAYzUw.QFoEUQQgAIQwgIwQABAEAAAAOIAACAIAAAAQAIAgEAACEAAAAAgAQBAAAAAAAGBAAgAAAAAAAFAAECAAAgAAQARAEQAAAAAJAAIAAgAAAYQEAAAQmAgBC3ZAYzUw.ZAAgH9794ulA",
+        canonical: true,
+    },
+    // Not canonical: vendor legitimate interests are encoded with the longer of the bitfield
+    // and range encodings, and 20 publisher restrictions are declared while the string ends in
+    // the middle of the 15th, which decoding drops with a `TruncatedPublisherRestrictions`
+    // warning. Re-encoding the decoded values yields a string 165 characters shorter.
+    Fixture {
+        name: "tcf eu v2 legacy sample",
+        gpp_string: "DBABM~CQaXJQAQaXJQAAGABCENCCFsAP_gAEPgAAiQKmNR_G_fbXlj8TZ36ftkeYxf99hjrsQxBgaJk24FyJvW7JwW32EzNAzapqYKmRIAu1BBAQNlGIDURUCgKIgVqTDMaESEoTNKJ6BEgBMRA2JYCFxvmwBDWQCY5tp9dld5mB-N7dr8ydzyy4BHn3I5XsS1WBAAAAAAAAAAAAAAAQAAgAAAgAAAAAAAAAAAABAAEAAAIAAAAAACAAAAAAAAAAAAAAAAAACAAAAAQSNgfgAKgAcAB4AFwAVAAuAB-AF0ANAAfABCACKAEcAMsAc4A7gCAQEHAQgAiMBGQEaAI4ASIAn4BUACxAF6AMUAa8A6QB2wD_gIQAR6AlYBMUCZAJlATbApACkQFJgKyAV2AsIBagC4AFxALmAXRAvIC8wF9AMQAYsAyEBkYDRgGmgNTAa8A2gBtgDbgG6AN-AgmBI0BQJA5AAXABQAFQALgAcAA8ACAAF8AMgA1AB4AEwAKoAbwA_QCGAIkATQArQBgADDgGUAZYA2YB3AHfAPYA-IB9gH6AQAAikBFwEYgJEAkwBQYCoAKuAXMAvQBigDaAG4AOIAe0BDoCRAE0gJ2AUOAo8BSIC2AFwALkAXYAu8BhoDJAGTgMuAZmAzmBq4GsgNvAbmFABgCKAXQBI0IAQAA2ACQAjgBKQCdgGiAP6AmUBNgCkAFiALcAX-AwIBtQDhAwAIBNgDahAAMAEgCbAG1CgAQCbAG1DAAQCbAG1DoIQAC4AKAAqABwAEEALgAvgBkAGoAPAAmABTACqAFwAMQAbwA_QCGAIgATQAowBWgDAAGGAMoAaIA2QB3wD2APiAfYB-wEUARiAjoCTAFBgKiAq4BYgC5gF5AMUAbQA3ABxAD2gH2AQ6Ai8BIgCaQE7AKHAUeAqwBYoC2AFugLgAXJAuwC7QF3gMNAY9AyMDJAGTgMqgZYBlwDMwGcwNXA1gBt4D-wI7DwAwAPwBFAERAIyAugCRo4AiACQAKAAfAByAEcAJSATsAzIB_QE2ALEAWyAtwBf4DaoG5gboA4QhAeAAWABQAFwANQAqgBcADEAG8APwAwIB3AHeARQAlIBQYCogKuAXMAxQBtAEOgJpAVYAsUBaIC4AFyALsAZGAycBnID-yIAIAjICYiAAkAB4A5ACOAGZATYAsQBngDagG6EoEQACwAKAAcAB4AEwAKoAXAAxQCGAIkAUYArQBgADKAGiANkAd8A_AD9AIsARgAjoBJQCgwFRAVcAuYBeQDaAG4AOIAe0A-wCHQEXgJEATSAnYBQ4CkwFNAKsAWKAtgBcAC5IF2AXaAw2BkYGSAMngZYBlwDOYGsAayA28B_YEdioAMABQCZQF0FAB4AJAAZABQAC2AOQAfYBBwCOAEpAQgAmwBUgC3AGeQNzA3QtALABqAMAAdwBegD7AKHAU0AqwBcAC7AGZgAAA.f_wAAAAAAAAA",
+        canonical: false,
+    },
+    Fixture {
+        name: "tcf ca v1 with core version 2",
+        gpp_string: "DBABjw~CPuy0IAPuy0IAPoABABGCyCAAAAAAAAAAAAAAAAA.YAAAAAAAAAA~1YNN",
+        canonical: true,
+    },
+    Fixture {
+        name: "us nat with gpc",
+        gpp_string: "DBABLA~BVVVVVVVVWA.YA",
+        canonical: true,
+    },
+];
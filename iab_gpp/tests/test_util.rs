@@ -0,0 +1,35 @@
+#![cfg(feature = "test-util")]
+
+use iab_gpp::sections::tcfeuv2::{Core, TcfEuV2};
+use iab_gpp::sections::usnat::{self, UsNat};
+use iab_gpp::sections::uspv1::{Flag, UspV1};
+use iab_gpp::sections::{IdSet, TwoLetterCode};
+
+fn has_vendor_consent(tcf: &TcfEuV2, vendor_id: u16) -> bool {
+    tcf.core.vendor_consents.contains(&vendor_id)
+}
+
+#[test]
+fn build_tcf_eu_v2_fixture() {
+    let mut core = Core::default();
+    core.cmp_id = 42;
+    core.consent_language = TwoLetterCode::try_from("FR").unwrap();
+    core.vendor_consents = IdSet::from([1, 755]);
+
+    let mut tcf = TcfEuV2::default();
+    tcf.core = core;
+
+    assert!(has_vendor_consent(&tcf, 755));
+    assert!(!has_vendor_consent(&tcf, 2));
+    assert_eq!(tcf.disclosed_vendors, None);
+}
+
+#[test]
+fn build_us_fixtures() {
+    let usp = UspV1::default();
+    assert_eq!(usp.opt_out_sale, Flag::NotApplicable);
+
+    let usnat = UsNat::default();
+    assert!(matches!(usnat.core, usnat::Core::V2(_)));
+    assert_eq!(usnat.gpc, None);
+}
@@ -22,7 +22,7 @@ impl GPPFieldParser {
     pub fn to_token_stream(&self) -> proc_macro2::TokenStream {
         match &self {
             GPPFieldParser::FromBitStream => quote! {
-                r.parse()
+                r.parse_with(ctx)
             },
             GPPFieldParser::Bool => quote! {
                 r.read_bit()
@@ -33,19 +33,35 @@ impl GPPFieldParser {
             GPPFieldParser::U16 => quote! {
                 r.read_unsigned::<12, u16>()
             },
+            // ids read from ranges may contain id 0, which is handled by the decode context
+            GPPFieldParser::ReaderCall(c) if Self::reads_range_ids(c) => quote! {
+                r.#c.map_err(Into::into).and_then(|ids| ctx.check_ids(ids))
+            },
             GPPFieldParser::ReaderCall(c) => quote! {
                 r.#c
             },
             GPPFieldParser::Function(f) => quote! {
-                #f(r)
+                #f(r, ctx)
             },
         }
     }
+
+    fn reads_range_ids(call: &ExprCall) -> bool {
+        matches!(
+            &*call.func,
+            Expr::Path(path) if path.path.get_ident().is_some_and(|ident| {
+                ident == "read_optimized_range"
+                    || ident == "read_optimized_integer_range"
+                    || ident == "read_integer_range_set"
+            })
+        )
+    }
 }
 
 pub struct GPPFieldHelperAttribute {
     pub optional_segment_type: Option<u8>,
     pub where_spec: Option<WhereSpec>,
+    pub present_if: Option<Ident>,
     pub parser: GPPFieldParser,
 }
 
@@ -59,6 +75,7 @@ impl GPPFieldHelperAttribute {
         let mut gpp_attr = Self {
             optional_segment_type: None,
             where_spec: None,
+            present_if: None,
             parser: GPPFieldParser::FromBitStream,
         };
 
@@ -97,6 +114,15 @@ impl GPPFieldHelperAttribute {
                     return Ok(());
                 }
 
+                // #[gpp(present_if = field_name)]
+                // the field is an Option which is only read when the
+                // previously decoded bool field is true
+                if meta.path.is_ident("present_if") {
+                    let value = meta.value()?; // parses the `=`
+                    gpp_attr.present_if = Some(value.parse::<Ident>()?);
+                    return Ok(());
+                }
+
                 // #[gpp(where(n = PARSER))]
                 // declares that the current field is preceded by a
                 // binding named "n" which is parsed using PARSER as
@@ -131,16 +157,13 @@ impl GPPFieldHelperAttribute {
     }
 
     fn extract_option_inner_type(ty: &Type) -> Option<&Type> {
-        if let Type::Path(type_path) = ty {
-            if let Some(segment) = type_path.path.segments.last() {
-                if segment.ident == "Option" {
-                    if let PathArguments::AngleBracketed(args) = &segment.arguments {
-                        if let Some(GenericArgument::Type(inner_ty)) = args.args.first() {
-                            return Some(inner_ty);
-                        }
-                    }
-                }
-            }
+        if let Type::Path(type_path) = ty
+            && let Some(segment) = type_path.path.segments.last()
+            && segment.ident == "Option"
+            && let PathArguments::AngleBracketed(args) = &segment.arguments
+            && let Some(GenericArgument::Type(inner_ty)) = args.args.first()
+        {
+            return Some(inner_ty);
         }
         None
     }
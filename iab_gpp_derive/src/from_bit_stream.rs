@@ -2,7 +2,7 @@ use crate::enum_variant_attr::GPPEnumVariantHelperAttribute;
 use crate::field_attr::GPPFieldHelperAttribute;
 use crate::struct_attr::GPPStructHelperAttribute;
 use proc_macro2::Ident;
-use quote::quote;
+use quote::{format_ident, quote};
 use syn::{DataEnum, DataStruct, Visibility};
 
 pub fn derive_struct_from_bit_stream(
@@ -16,17 +16,22 @@ pub fn derive_struct_from_bit_stream(
     // - by default call a FromBitStream implementation
     // - use BitRead methods if specified
     let mut parse_statements = vec![];
+    // same statements, recording the position of each field, for the bitfield-debug feature
+    let mut layout_statements = vec![];
     let mut field_names = vec![];
 
     if let Some(version) = struct_attr.section_version {
-        parse_statements.push(quote! {
-            let version = r.read_unsigned::<6, u8>()?;
+        let bits = struct_attr.version_bits;
+        let statement = quote! {
+            let version = r.read_unsigned::<#bits, u8>()?;
             if version != #version {
                 return Err(crate::sections::SectionDecodeError::UnknownSegmentVersion {
                     segment_version: version,
                 });
             }
-        });
+        };
+        layout_statements.push(record_layout(&format_ident!("version"), &statement));
+        parse_statements.push(statement);
     }
 
     for field in &input.fields {
@@ -42,7 +47,6 @@ pub fn derive_struct_from_bit_stream(
         }
 
         let name = name.unwrap();
-        field_names.push(name.clone());
 
         let attr = GPPFieldHelperAttribute::new(&field.attrs, &field.ty)
             .expect("attribute parsing failed");
@@ -56,30 +60,54 @@ pub fn derive_struct_from_bit_stream(
         if let Some(where_spec) = attr.where_spec {
             let name = where_spec.name;
             let expr = where_spec.parser.to_token_stream();
-            parse_statements.push(quote! {
+            let statement = quote! {
                 let #name: u64 = #expr?;
-            })
+            };
+            layout_statements.push(record_layout(&name, &statement));
+            parse_statements.push(statement);
         }
 
         // Handle optional segments
         if attr.optional_segment_type.is_some() {
-            parse_statements.push(quote! {
+            let statement = quote! {
                 let #name = None;
-            });
+            };
+            layout_statements.push(statement.clone());
+            parse_statements.push(statement);
+        } else if let Some(flag) = attr.present_if {
+            if !field_names.contains(&flag) {
+                return syn::Error::new(
+                    flag.span(),
+                    "present_if must reference a previous field of the struct",
+                )
+                .to_compile_error();
+            }
+            let expr = attr.parser.to_token_stream();
+            let statement = quote! {
+                let #name = if #flag { Some(#expr?) } else { None };
+            };
+            layout_statements.push(record_layout(&name, &statement));
+            parse_statements.push(statement);
         } else {
             let expr = attr.parser.to_token_stream();
-            parse_statements.push(quote! {
+            let statement = quote! {
                 let #name = #expr?;
-            });
+            };
+            layout_statements.push(record_layout(&name, &statement));
+            parse_statements.push(statement);
         }
+
+        field_names.push(name);
     }
 
     quote! {
-        impl bitstream_io::read::FromBitStream for #ident {
+        impl<'a> bitstream_io::read::FromBitStreamWith<'a> for #ident {
+            type Context = crate::sections::DecodeContext;
             type Error = crate::sections::SectionDecodeError;
 
             fn from_reader<R: bitstream_io::read::BitRead + ?core::marker::Sized>(
                 mut r: &mut R,
+                ctx: &Self::Context,
             ) -> Result<Self, Self::Error>
             where
                 Self: core::marker::Sized
@@ -91,6 +119,88 @@ pub fn derive_struct_from_bit_stream(
                 })
             }
         }
+
+        impl bitstream_io::read::FromBitStream for #ident {
+            type Error = crate::sections::SectionDecodeError;
+
+            fn from_reader<R: bitstream_io::read::BitRead + ?core::marker::Sized>(
+                r: &mut R,
+            ) -> Result<Self, Self::Error>
+            where
+                Self: core::marker::Sized
+            {
+                r.parse_with(&crate::sections::DecodeContext::default())
+            }
+        }
+
+        #[cfg(feature = "bitfield-debug")]
+        #[allow(dead_code)]
+        impl #ident {
+            /// Decodes a Base64-URL encoded string and renders each field with its bit offset,
+            /// width and decoded value, one field per line.
+            ///
+            /// The string must start with the bits of this type. Nested types are rendered as
+            /// a single field.
+            pub fn as_bitfield_debug(
+                s: &str,
+            ) -> Result<String, crate::sections::SectionDecodeError> {
+                let ctx = crate::sections::DecodeContext::default();
+                let mut r = crate::core::layout::BitCounter::new(ctx.bit_reader(s));
+                let (_, fields) = Self::decode_layout(&mut r, &ctx)?;
+                Ok(crate::core::layout::render(&fields))
+            }
+
+            /// Decodes a Base64-URL encoded string, and returns the decoded value along with
+            /// the bit offset and width of each field, in decoding order.
+            ///
+            /// The string must start with the bits of this type. Nested types are traced as a
+            /// single field.
+            #[cfg(feature = "provenance")]
+            pub fn decode_traced(
+                s: &str,
+            ) -> Result<
+                (Self, Vec<crate::core::FieldProvenance>),
+                crate::sections::SectionDecodeError,
+            > {
+                let ctx = crate::sections::DecodeContext::default();
+                let mut r = crate::core::layout::BitCounter::new(ctx.bit_reader(s));
+                let (value, fields) = Self::decode_layout(&mut r, &ctx)?;
+                Ok((value, fields.iter().map(Into::into).collect()))
+            }
+
+            pub(crate) fn decode_layout<R: bitstream_io::read::BitRead>(
+                mut r: &mut crate::core::layout::BitCounter<R>,
+                ctx: &crate::sections::DecodeContext,
+            ) -> Result<
+                (Self, Vec<crate::core::layout::FieldLayout>),
+                crate::sections::SectionDecodeError,
+            > {
+                use bitstream_io::read::BitRead;
+
+                let mut layout = Vec::new();
+
+                #(#layout_statements)*
+
+                Ok((Self{
+                    #(#field_names),*
+                }, layout))
+            }
+        }
+    }
+}
+
+/// Wraps a statement binding `name` so that the position, width and value of the binding
+/// are appended to `layout`.
+fn record_layout(name: &Ident, statement: &proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    quote! {
+        let offset = r.bits_read();
+        #statement
+        layout.push(crate::core::layout::FieldLayout::new(
+            stringify!(#name),
+            offset,
+            r.bits_read() - offset,
+            &#name,
+        ));
     }
 }
 
@@ -110,7 +220,7 @@ pub fn derive_enum_from_bit_stream(input: &DataEnum, ident: &Ident) -> proc_macr
         // Handle each versioned variant
         if let Some(v) = attr.variant_version {
             versions.push(quote! {
-                #v => Ok(Self::#name(r.parse()?)),
+                #v => Ok(Self::#name(r.parse_with(ctx)?)),
             });
         }
     }
@@ -130,6 +240,21 @@ pub fn derive_enum_from_bit_stream(input: &DataEnum, ident: &Ident) -> proc_macr
                     Ok(Self::from_u8(r.read_unsigned::<2, u8>()?).unwrap_or(Self::default()))
                 }
             }
+
+            impl<'a> bitstream_io::read::FromBitStreamWith<'a> for #ident {
+                type Context = crate::sections::DecodeContext;
+                type Error = std::io::Error;
+
+                fn from_reader<R: bitstream_io::read::BitRead + ?core::marker::Sized>(
+                    r: &mut R,
+                    _ctx: &Self::Context,
+                ) -> Result<Self, Self::Error>
+                where
+                    Self: core::default::Default
+                {
+                    r.parse()
+                }
+            }
         };
     }
 
@@ -138,8 +263,23 @@ pub fn derive_enum_from_bit_stream(input: &DataEnum, ident: &Ident) -> proc_macr
         impl bitstream_io::read::FromBitStream for #ident {
             type Error = crate::sections::SectionDecodeError;
 
+            fn from_reader<R: bitstream_io::read::BitRead + ?core::marker::Sized>(
+                r: &mut R,
+            ) -> Result<Self, Self::Error>
+            where
+                Self: core::marker::Sized
+            {
+                r.parse_with(&crate::sections::DecodeContext::default())
+            }
+        }
+
+        impl<'a> bitstream_io::read::FromBitStreamWith<'a> for #ident {
+            type Context = crate::sections::DecodeContext;
+            type Error = crate::sections::SectionDecodeError;
+
             fn from_reader<R: bitstream_io::read::BitRead + ?core::marker::Sized>(
                 mut r: &mut R,
+                ctx: &Self::Context,
             ) -> Result<Self, Self::Error>
             where
                 Self: core::marker::Sized
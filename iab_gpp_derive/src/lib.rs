@@ -39,10 +39,32 @@ pub fn derive_gpp_section(input: TokenStream) -> TokenStream {
     let ident = input.ident;
 
     if let Data::Struct(s) = input.data {
-        // first derive DecodableSection which applies to all sections
+        // first derive DecodableSection, TryFrom<&GPPString> and TryFrom<&str> which apply to
+        // all sections
         let stream = quote! {
+            impl #ident {
+                /// The id of the section.
+                pub const ID: crate::sections::SectionId = crate::sections::SectionId::#ident;
+            }
+
             impl crate::sections::DecodableSection for #ident {
-                const ID: crate::sections::SectionId = crate::sections::SectionId::#ident;
+                const ID: crate::sections::SectionId = Self::ID;
+            }
+
+            impl ::std::convert::TryFrom<&crate::v1::GPPString> for #ident {
+                type Error = crate::sections::SectionDecodeError;
+
+                fn try_from(gpp: &crate::v1::GPPString) -> Result<Self, Self::Error> {
+                    gpp.decode()
+                }
+            }
+
+            impl ::std::convert::TryFrom<&str> for #ident {
+                type Error = crate::sections::SectionDecodeError;
+
+                fn try_from(s: &str) -> Result<Self, Self::Error> {
+                    s.parse()
+                }
             }
         };
 
@@ -85,9 +107,18 @@ fn impl_base64_gpp_section(
             type Err = crate::sections::SectionDecodeError;
 
             fn from_str(s: &str) -> Result<Self, Self::Err> {
-                use bitstream_io::BitRead;
+                use crate::sections::DecodeStr;
+                Self::decode_str(s, &crate::sections::DecodeContext::default())
+            }
+        }
 
-                crate::core::base64_bit_reader(s.as_bytes()).parse()
+        impl crate::sections::DecodeStr for #ident {
+            fn decode_str(
+                s: &str,
+                ctx: &crate::sections::DecodeContext,
+            ) -> Result<Self, crate::sections::SectionDecodeError> {
+                use bitstream_io::BitRead;
+                ctx.bit_reader(s).parse_with(ctx)
             }
         }
     });
@@ -109,8 +140,18 @@ fn impl_segmented_gpp_section(
             type Err = crate::sections::SectionDecodeError;
 
             fn from_str(s: &str) -> Result<Self, Self::Err> {
+                use crate::sections::DecodeStr;
+                Self::decode_str(s, &crate::sections::DecodeContext::default())
+            }
+        }
+
+        impl crate::sections::DecodeStr for #ident {
+            fn decode_str(
+                s: &str,
+                ctx: &crate::sections::DecodeContext,
+            ) -> Result<Self, crate::sections::SectionDecodeError> {
                 use crate::sections::SegmentedStr;
-                s.parse_segmented_str()
+                s.parse_segmented_str(ctx)
             }
         }
     });
@@ -135,8 +176,18 @@ fn impl_header_based_gpp_section(
             type Err = crate::sections::SectionDecodeError;
 
             fn from_str(s: &str) -> Result<Self, Self::Err> {
+                use crate::sections::DecodeStr;
+                Self::decode_str(s, &crate::sections::DecodeContext::default())
+            }
+        }
+
+        impl crate::sections::DecodeStr for #ident {
+            fn decode_str(
+                s: &str,
+                ctx: &crate::sections::DecodeContext,
+            ) -> Result<Self, crate::sections::SectionDecodeError> {
                 use crate::sections::SegmentedStrWithHeader;
-                s.parse_segmented_str()
+                s.parse_segmented_str(ctx)
             }
         }
     });
@@ -10,6 +10,7 @@ pub fn derive_optional_segment_parser(
     struct_attr: &GPPStructHelperAttribute,
 ) -> proc_macro2::TokenStream {
     let parse_match_arms = build_parse_match_arms(input);
+    let segment_types = optional_segment_types(input);
 
     let read_segment_type_override = match struct_attr.kind {
         GPPStructKind::WithOptionalSegments(3) => None,
@@ -25,10 +26,15 @@ pub fn derive_optional_segment_parser(
         impl crate::sections::OptionalSegmentParser for #ident {
             #read_segment_type_override
 
+            fn is_optional_segment_type(segment_type: u8) -> bool {
+                matches!(segment_type, #(#segment_types)|*)
+            }
+
             fn parse_optional_segment<R: bitstream_io::read::BitRead>(
                 segment_type: u8,
                 r: &mut R,
                 into: &mut Self,
+                ctx: &crate::sections::DecodeContext,
             ) -> Result<(), crate::sections::SectionDecodeError> {
                 match segment_type {
                     #(#parse_match_arms)*
@@ -54,6 +60,7 @@ pub fn derive_optional_sub_section_parser(
                 segment_type: u8,
                 r: &mut R,
                 into: &mut Self,
+                ctx: &crate::sections::DecodeContext,
             ) -> Result<(), crate::sections::SectionDecodeError> {
                 match segment_type {
                     #(#parse_match_arms)*
@@ -87,6 +94,8 @@ fn build_parse_match_arms(input: &DataStruct) -> Vec<proc_macro2::TokenStream> {
             .expect("attribute parsing failed");
 
         if let Some(segment_type) = attr.optional_segment_type {
+            // a segment which is present always yields Some, even if its payload decodes
+            // to an empty value, so that it stays distinguishable from an absent segment
             let expr = attr.parser.to_token_stream();
             parse_match_arms.push(quote! {
                 #segment_type => {
@@ -98,3 +107,16 @@ fn build_parse_match_arms(input: &DataStruct) -> Vec<proc_macro2::TokenStream> {
 
     parse_match_arms
 }
+
+fn optional_segment_types(input: &DataStruct) -> Vec<u8> {
+    input
+        .fields
+        .iter()
+        .filter(|field| matches!(field.vis, Visibility::Public(_)))
+        .filter_map(|field| {
+            GPPFieldHelperAttribute::new(&field.attrs, &field.ty)
+                .expect("attribute parsing failed")
+                .optional_segment_type
+        })
+        .collect()
+}
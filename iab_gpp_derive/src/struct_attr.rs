@@ -10,6 +10,7 @@ pub enum GPPStructKind {
 pub struct GPPStructHelperAttribute {
     pub kind: GPPStructKind,
     pub section_version: Option<u8>,
+    pub version_bits: u32,
 }
 
 impl GPPStructHelperAttribute {
@@ -17,6 +18,8 @@ impl GPPStructHelperAttribute {
         let mut gpp_attr = Self {
             kind: GPPStructKind::Base64Data,
             section_version: None,
+            // default value is 6 bits (as seen in all current sections)
+            version_bits: 6,
         };
 
         if let Some(attr) = find_gpp_attr(attrs) {
@@ -65,6 +68,20 @@ impl GPPStructHelperAttribute {
                     return Ok(());
                 }
 
+                // #[gpp(version_bits = N)]
+                if meta.path.is_ident("version_bits") {
+                    let value = meta.value()?; // parses the `=`
+                    let s = value.parse::<LitInt>()?;
+                    let bits = s.base10_parse()?;
+                    // versions are reported as u8 in SectionDecodeError
+                    if !(1..=8).contains(&bits) {
+                        return Err(meta.error("version_bits must be between 1 and 8"));
+                    }
+                    gpp_attr.version_bits = bits;
+
+                    return Ok(());
+                }
+
                 Err(meta.error("unrecognized gpp struct parameter"))
             })?;
         }